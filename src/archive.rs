@@ -0,0 +1,232 @@
+//! Collections of Proguard Mapping Files for multiple builds.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "uuid")]
+use uuid_::Uuid;
+
+use crate::buffer::ProguardMappingBuf;
+use crate::mapping::ProguardMapping;
+
+#[derive(Clone, Debug)]
+struct ArchiveEntry {
+    name: String,
+    buf: ProguardMappingBuf,
+    /// The UUID of the mapping, which is computed once up-front.
+    #[cfg(feature = "uuid")]
+    uuid: Uuid,
+}
+
+/// A set of named Proguard Mapping files, such as the mappings of different
+/// build variants or versions of an app.
+///
+/// Mappings can be selected by their name, by their UUID, or by a header
+/// preceding the first class, such as `# pg_map_id: 5b46a1f`.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{MappingArchive, ProguardMappingBuf};
+///
+/// let mut archive = MappingArchive::new();
+/// archive.insert("debug", ProguardMappingBuf::from(b"# variant: debug\na -> b:".to_vec()));
+/// archive.insert("release", ProguardMappingBuf::from(b"# variant: release\nc -> d:".to_vec()));
+///
+/// let mapping = archive.find_by_header("variant", "release").unwrap();
+/// assert_eq!(mapping.class_names().next(), Some(("c", "d")));
+/// assert!(archive.get("debug").is_some());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MappingArchive {
+    entries: Vec<ArchiveEntry>,
+}
+
+impl MappingArchive {
+    /// Creates an empty archive.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads all files of the directory at `path` into an archive.
+    ///
+    /// The mappings are named after their file name. Subdirectories are not
+    /// traversed, and files are added in the order of their names.
+    pub fn from_dir<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort();
+
+        let mut archive = Self::new();
+        for path in paths {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let buf = ProguardMappingBuf::from_reader(fs::File::open(&path)?)?;
+            archive.insert(name, buf);
+        }
+        Ok(archive)
+    }
+
+    /// Adds a mapping under `name`, replacing any mapping with the same name.
+    pub fn insert<S: Into<String>>(&mut self, name: S, buf: ProguardMappingBuf) {
+        let entry = ArchiveEntry {
+            name: name.into(),
+            #[cfg(feature = "uuid")]
+            uuid: buf.as_mapping().identifier_uuid(),
+            buf,
+        };
+        match self.entries.iter_mut().find(|e| e.name == entry.name) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+    }
+
+    /// Removes the mapping named `name`, returning it.
+    pub fn remove(&mut self, name: &str) -> Option<ProguardMappingBuf> {
+        let index = self.entries.iter().position(|entry| entry.name == name)?;
+        Some(self.entries.remove(index).buf)
+    }
+
+    /// The number of mappings in the archive.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the archive contains no mappings.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterates over the names of all mappings, in insertion order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.name.as_str())
+    }
+
+    /// Returns the mapping named `name`.
+    pub fn get(&self, name: &str) -> Option<ProguardMapping<'_>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.buf.as_mapping())
+    }
+
+    /// Returns the mapping with the given UUID.
+    ///
+    /// This is the [`ProguardMapping::header_uuid`] of the mapping if it has
+    /// one, and its [`ProguardMapping::uuid`] otherwise.
+    #[cfg(feature = "uuid")]
+    pub fn find_by_uuid(&self, uuid: Uuid) -> Option<ProguardMapping<'_>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.uuid == uuid)
+            .map(|entry| entry.buf.as_mapping())
+    }
+
+    /// Returns the first mapping with a `# key: value` header preceding its first class.
+    pub fn find_by_header(&self, key: &str, value: &str) -> Option<ProguardMapping<'_>> {
+        self.entries
+            .iter()
+            .map(|entry| entry.buf.as_mapping())
+            .find(|mapping| {
+                mapping
+                    .leading_headers()
+                    .any(|header| header == (key, Some(value)))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_and_remove() {
+        let mut archive = MappingArchive::new();
+        assert!(archive.is_empty());
+
+        archive.insert("a", ProguardMappingBuf::from(b"a -> a:".to_vec()));
+        archive.insert("b", ProguardMappingBuf::from(b"b -> b:".to_vec()));
+        archive.insert("a", ProguardMappingBuf::from(b"c -> c:".to_vec()));
+        assert_eq!(archive.names().collect::<Vec<_>>(), vec!["a", "b"]);
+        assert_eq!(archive.get("a").unwrap().source(), b"c -> c:");
+
+        assert_eq!(archive.remove("a").unwrap().as_bytes(), b"c -> c:");
+        assert!(archive.remove("a").is_none());
+        assert_eq!(archive.len(), 1);
+    }
+
+    #[test]
+    fn find_by_header() {
+        let mut archive = MappingArchive::new();
+        archive.insert(
+            "1",
+            ProguardMappingBuf::from(b"# compiler: R8\n# pg_map_id: 1\na -> a:".to_vec()),
+        );
+        archive.insert(
+            "2",
+            ProguardMappingBuf::from(b"# compiler: R8\n# pg_map_id: 2\nb -> b:".to_vec()),
+        );
+        archive.insert(
+            "3",
+            ProguardMappingBuf::from(b"c -> c:\n# pg_map_id: 3\n".to_vec()),
+        );
+
+        let found = archive.find_by_header("pg_map_id", "2").unwrap();
+        assert!(found
+            .source()
+            .starts_with(b"# compiler: R8\n# pg_map_id: 2"));
+        // headers after the first class are not considered
+        assert!(archive.find_by_header("pg_map_id", "3").is_none());
+        assert!(archive.find_by_header("compiler", "ProGuard").is_none());
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn find_by_uuid() {
+        let mut archive = MappingArchive::new();
+        archive.insert("a", ProguardMappingBuf::from(b"a -> a:".to_vec()));
+        archive.insert("b", ProguardMappingBuf::from(b"b -> b:".to_vec()));
+
+        let uuid = ProguardMapping::new(b"b -> b:").uuid();
+        assert_eq!(archive.find_by_uuid(uuid).unwrap().source(), b"b -> b:");
+        assert!(archive.find_by_uuid(Uuid::nil()).is_none());
+
+        // a `# uuid` header takes precedence over the checksum.
+        let mut buf = ProguardMappingBuf::from(b"c -> c:\n".to_vec());
+        let uuid = buf.insert_uuid_header();
+        let checksum_uuid = buf.as_mapping().uuid();
+        archive.insert("c", buf);
+        assert!(archive
+            .find_by_uuid(uuid)
+            .unwrap()
+            .source()
+            .ends_with(b"c -> c:\n"));
+        assert!(archive.find_by_uuid(checksum_uuid).is_none());
+    }
+
+    #[test]
+    fn from_dir() {
+        let dir = std::env::temp_dir().join(format!("proguard-archive-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("release.txt"), b"b -> b:").unwrap();
+        fs::write(dir.join("debug.txt"), b"a -> a:").unwrap();
+
+        let archive = MappingArchive::from_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let archive = archive.unwrap();
+        assert_eq!(
+            archive.names().collect::<Vec<_>>(),
+            vec!["debug.txt", "release.txt"]
+        );
+        assert_eq!(archive.get("release.txt").unwrap().source(), b"b -> b:");
+    }
+}
@@ -0,0 +1,250 @@
+//! Translation of symbols in ART baseline profiles.
+//!
+//! Baseline profiles, such as `baseline-prof.txt`, list one class or method
+//! per line, using JVM descriptors and optional `H`, `S` and `P` flags:
+//!
+//! ```text
+//! HSPLcom/example/Foo;->bar(ILjava/lang/String;)Ljava/util/List;
+//! Lcom/example/Foo;
+//! ```
+//!
+//! Translation rewrites the class and method names, and all classes in the
+//! method descriptor. Lines which can not be parsed, or which use wildcards
+//! such as `Lcom/example/**;`, are copied verbatim.
+
+use std::fmt::Write;
+
+use crate::java::Type;
+use crate::stacktrace;
+
+/// A JVM field descriptor, such as `I` or `[Ljava/lang/String;`.
+struct Descriptor<'a> {
+    dimensions: usize,
+    /// The descriptor character of a primitive, or the internal name of a class.
+    base: Result<char, &'a str>,
+}
+
+impl<'a> Descriptor<'a> {
+    /// Parses a descriptor at the start of `input`, returning it and the rest.
+    fn parse(input: &'a str) -> Option<(Self, &'a str)> {
+        let rest = input.trim_start_matches('[');
+        let dimensions = input.len() - rest.len();
+        let mut chars = rest.chars();
+        let base = match chars.next()? {
+            'L' => {
+                let (name, _) = chars.as_str().split_once(';')?;
+                Err(name)
+            }
+            c @ ('Z' | 'B' | 'C' | 'S' | 'I' | 'J' | 'F' | 'D' | 'V') => Ok(c),
+            _ => return None,
+        };
+        let len = match base {
+            Ok(_) => 1,
+            Err(name) => name.len() + 2,
+        };
+        Some((Self { dimensions, base }, &rest[len..]))
+    }
+
+    /// The Java name of the type, with the class named `class`.
+    fn java_name(&self, class: &str) -> String {
+        let mut name = match self.base {
+            Ok(c) => primitive_name(c).to_owned(),
+            Err(_) => class.to_owned(),
+        };
+        for _ in 0..self.dimensions {
+            name.push_str("[]");
+        }
+        name
+    }
+
+    /// Writes the descriptor, with the class named `class`.
+    fn write(&self, output: &mut String, class: &str) {
+        for _ in 0..self.dimensions {
+            output.push('[');
+        }
+        match self.base {
+            Ok(c) => output.push(c),
+            Err(_) => {
+                let _ = write!(output, "L{};", class.replace('.', "/"));
+            }
+        }
+    }
+}
+
+fn primitive_name(c: char) -> &'static str {
+    match c {
+        'Z' => "boolean",
+        'B' => "byte",
+        'C' => "char",
+        'S' => "short",
+        'I' => "int",
+        'J' => "long",
+        'F' => "float",
+        'D' => "double",
+        _ => "void",
+    }
+}
+
+/// Translates every line of a baseline profile.
+///
+/// `class` translates a fully-qualified Java class name. `method` translates
+/// the name of a method, given the untranslated name of its class, and its
+/// arguments as a comma-separated list of Java types, both untranslated and
+/// translated.
+pub(crate) fn translate<'r, C, M>(input: &str, class: C, method: M) -> String
+where
+    C: Fn(&str) -> Option<&'r str>,
+    M: Fn(&str, &str, &str, &str) -> Option<&'r str>,
+{
+    let mut output = String::with_capacity(input.len());
+    for (line, terminator) in stacktrace::split_lines(input) {
+        match translate_line(line, &class, &method) {
+            Some(translated) => output.push_str(&translated),
+            None => output.push_str(line),
+        }
+        output.push_str(terminator);
+    }
+    output
+}
+
+fn translate_line<'r, C, M>(line: &str, class: &C, method: &M) -> Option<String>
+where
+    C: Fn(&str) -> Option<&'r str>,
+    M: Fn(&str, &str, &str, &str) -> Option<&'r str>,
+{
+    // the class name of a descriptor, along with its translation.
+    let translate_class = |descriptor: &Descriptor<'_>| {
+        let name = descriptor.base.err().unwrap_or_default().replace('/', ".");
+        let translated = class(&name).map_or_else(|| name.clone(), str::to_owned);
+        (name, translated)
+    };
+
+    let flags = line.len() - line.trim_start_matches(['H', 'S', 'P']).len();
+    let (owner, rest) = Descriptor::parse(&line[flags..])?;
+    if owner.dimensions > 0 || owner.base.is_ok() || owner.base.err()?.contains('*') {
+        return None;
+    }
+
+    let mut output = String::with_capacity(line.len());
+    output.push_str(&line[..flags]);
+    let (owner_name, owner_translated) = translate_class(&owner);
+    owner.write(&mut output, &owner_translated);
+    if rest.is_empty() {
+        return Some(output);
+    }
+
+    let rest = rest.strip_prefix("->")?;
+    let (name, rest) = rest.split_once('(')?;
+    let (mut arguments, return_type) = rest.split_once(')')?;
+
+    let mut descriptors = String::new();
+    let mut names = Vec::new();
+    let mut translated_names = Vec::new();
+    while !arguments.is_empty() {
+        let (argument, rest) = Descriptor::parse(arguments)?;
+        let (name, translated) = translate_class(&argument);
+        argument.write(&mut descriptors, &translated);
+        names.push(argument.java_name(&name));
+        translated_names.push(argument.java_name(&translated));
+        arguments = rest;
+    }
+    let (return_type, rest) = Descriptor::parse(return_type)?;
+    if !rest.is_empty() {
+        return None;
+    }
+
+    let translated_name = method(
+        &owner_name,
+        name,
+        &names.join(","),
+        &translated_names.join(","),
+    );
+    let _ = write!(
+        output,
+        "->{}({})",
+        translated_name.unwrap_or(name),
+        descriptors
+    );
+    let (_, translated) = translate_class(&return_type);
+    return_type.write(&mut output, &translated);
+    Some(output)
+}
+
+/// Whether the arguments of a mapping match a comma-separated list of types.
+pub(crate) fn same_arguments(mapping: &str, arguments: &str) -> bool {
+    match (Type::parse_list(mapping), Type::parse_list(arguments)) {
+        (Some(mapping), Some(arguments)) => mapping == arguments,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate_test(input: &str) -> String {
+        translate(
+            input,
+            |class| match class {
+                "a.a" => Some("com.example.Foo"),
+                "a.b" => Some("com.example.Bar"),
+                _ => None,
+            },
+            |class, method, _, arguments| match (class, method, arguments) {
+                ("a.a", "a", "int,com.example.Bar[]") => Some("run"),
+                ("a.a", "a", "") => Some("stop"),
+                _ => None,
+            },
+        )
+    }
+
+    #[test]
+    fn classes() {
+        assert_eq!(translate_test("La/a;"), "Lcom/example/Foo;");
+        assert_eq!(translate_test("HSPLa/b;\n"), "HSPLcom/example/Bar;\n");
+        assert_eq!(translate_test("Lx/y;"), "Lx/y;");
+    }
+
+    #[test]
+    fn methods() {
+        assert_eq!(
+            translate_test("HSPLa/a;->a(I[La/b;)La/b;\r\n"),
+            "HSPLcom/example/Foo;->run(I[Lcom/example/Bar;)Lcom/example/Bar;\r\n"
+        );
+        assert_eq!(
+            translate_test("PLa/a;->a()V"),
+            "PLcom/example/Foo;->stop()V"
+        );
+        // overloads are told apart by their arguments.
+        assert_eq!(translate_test("La/a;->a(J)V"), "Lcom/example/Foo;->a(J)V");
+        assert_eq!(
+            translate_test("La/a;-><init>(Ljava/lang/String;)V"),
+            "Lcom/example/Foo;-><init>(Ljava/lang/String;)V"
+        );
+    }
+
+    #[test]
+    fn verbatim() {
+        for line in [
+            "",
+            "# comment",
+            "La/**;",
+            "La/a;->a(I",
+            "La/a;->a(X)V",
+            "La/a;->a()VV",
+            "[La/a;",
+        ] {
+            assert_eq!(translate_test(line), line);
+        }
+    }
+
+    #[test]
+    fn arguments() {
+        assert!(same_arguments(
+            "int,java.lang.String[]",
+            "int, java.lang.String[]"
+        ));
+        assert!(same_arguments("", ""));
+        assert!(!same_arguments("int", "long"));
+    }
+}
@@ -0,0 +1,407 @@
+//! Owned buffers for Proguard Mapping Files.
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+#[cfg(feature = "uuid")]
+use uuid_::Uuid;
+
+#[cfg(feature = "compression")]
+use crate::decompress::{decompress, DecompressError, DecompressLimits};
+use crate::mapping::ProguardMapping;
+
+/// An owned buffer holding the contents of a Proguard Mapping file.
+///
+/// [`ProguardMapping`] borrows its source, which requires the whole file to be
+/// available as a byte slice. This buffer can be filled from any reader, for
+/// example a region of a larger container file, and then handed out as a
+/// [`ProguardMapping`] via [`as_mapping`](Self::as_mapping).
+#[derive(Clone, Default, PartialEq)]
+pub struct ProguardMappingBuf {
+    source: Vec<u8>,
+}
+
+impl fmt::Debug for ProguardMappingBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProguardMappingBuf")
+            .field("len", &self.source.len())
+            .finish()
+    }
+}
+
+impl From<Vec<u8>> for ProguardMappingBuf {
+    fn from(source: Vec<u8>) -> Self {
+        Self { source }
+    }
+}
+
+impl ProguardMappingBuf {
+    /// Reads a complete Proguard Mapping from `reader`.
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut source = Vec::new();
+        reader.read_to_end(&mut source)?;
+        Ok(Self { source })
+    }
+
+    /// Reads a Proguard Mapping of `len` bytes starting at `offset` of `reader`.
+    ///
+    /// This is useful when the mapping is stored inside of a larger container
+    /// file, or when only a byte range of a remote blob is being fetched.
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if `reader` ends before
+    /// `len` bytes could be read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use proguard::ProguardMappingBuf;
+    ///
+    /// let container = b"HEADERa -> b:\n    void method() -> b\nTRAILER";
+    /// let buf = ProguardMappingBuf::from_reader_range(Cursor::new(&container[..]), 6, 30).unwrap();
+    ///
+    /// assert_eq!(buf.as_bytes(), b"a -> b:\n    void method() -> b");
+    /// assert!(buf.as_mapping().is_valid());
+    /// ```
+    pub fn from_reader_range<R: Read + Seek>(
+        mut reader: R,
+        offset: u64,
+        len: u64,
+    ) -> io::Result<Self> {
+        reader.seek(SeekFrom::Start(offset))?;
+
+        // `len` is untrusted input, so cap the preallocation.
+        let mut source = Vec::with_capacity(len.min(1 << 20) as usize);
+        reader.take(len).read_to_end(&mut source)?;
+        if (source.len() as u64) < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "mapping range extends past the end of the reader",
+            ));
+        }
+
+        Ok(Self { source })
+    }
+
+    /// Reads a complete, possibly compressed Proguard Mapping from `reader`.
+    ///
+    /// Mappings compressed with gzip or zstd are detected by their magic
+    /// bytes and decompressed. Fails with a [`DecompressError`] as soon as the
+    /// decompressed mapping exceeds one of the `limits`, so hostile uploads
+    /// can not exhaust memory.
+    ///
+    /// This requires the `compression` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{DecompressError, DecompressLimits, ProguardMappingBuf};
+    ///
+    /// let source = &b"a -> b:\n    void method() -> b"[..];
+    /// let limits = DecompressLimits::default();
+    /// let buf = ProguardMappingBuf::from_compressed_reader(source, &limits).unwrap();
+    /// assert!(buf.as_mapping().is_valid());
+    ///
+    /// let limits = DecompressLimits {
+    ///     max_size: 10,
+    ///     ..Default::default()
+    /// };
+    /// assert!(matches!(
+    ///     ProguardMappingBuf::from_compressed_reader(source, &limits),
+    ///     Err(DecompressError::TooLarge { limit: 10 })
+    /// ));
+    /// ```
+    #[cfg(feature = "compression")]
+    pub fn from_compressed_reader<R: Read>(
+        reader: R,
+        limits: &DecompressLimits,
+    ) -> Result<Self, DecompressError> {
+        let source = decompress(reader, limits)?;
+        Ok(Self { source })
+    }
+
+    /// Downloads a Proguard Mapping from `url`.
+    ///
+    /// The mapping is decompressed and checked against `limits` like in
+    /// [`from_compressed_reader`](Self::from_compressed_reader), whether it
+    /// was compressed by the server or stored as a compressed file.
+    ///
+    /// Connecting to the server times out after 10 seconds, and reading
+    /// from it after 30 seconds without receiving any data, so a stalled
+    /// server can not block the caller forever.
+    ///
+    /// Error responses, such as `404`, fail with
+    /// [`DecompressError::Http`] and their status code.
+    ///
+    /// This requires the `http` feature.
+    #[cfg(feature = "http")]
+    pub fn from_url(url: &str, limits: &DecompressLimits) -> Result<Self, DecompressError> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(10))
+            .timeout_read(Duration::from_secs(30))
+            .build();
+        let response = agent.get(url).call().map_err(|err| match err {
+            ureq::Error::Status(status, _) => DecompressError::Http { status },
+            ureq::Error::Transport(err) => DecompressError::Io(io::Error::other(err)),
+        })?;
+        Self::from_compressed_reader(response.into_reader(), limits)
+    }
+
+    /// The raw bytes of the mapping file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.source
+    }
+
+    /// Returns a [`ProguardMapping`] borrowing from this buffer.
+    pub fn as_mapping(&self) -> ProguardMapping<'_> {
+        ProguardMapping::new(&self.source)
+    }
+
+    /// Prepends a `# uuid: <value>` header to the mapping, unless it already has one.
+    ///
+    /// The injected UUID is the checksum-based [`ProguardMapping::uuid`] of
+    /// the mapping before the header was added. It can later be read using
+    /// [`ProguardMapping::header_uuid`] without reading the whole file, and
+    /// stays stable if the file is reformatted or recompressed. As the header
+    /// changes the checksum, the `uuid` of the resulting mapping differs from
+    /// the header, which takes precedence wherever the mapping is identified.
+    ///
+    /// Returns the UUID of the header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMappingBuf;
+    ///
+    /// let mut buf = ProguardMappingBuf::from(b"a -> b:\n".to_vec());
+    /// let uuid = buf.as_mapping().uuid();
+    ///
+    /// assert_eq!(buf.insert_uuid_header(), uuid);
+    /// assert!(buf.as_bytes().starts_with(format!("# uuid: {}\n", uuid).as_bytes()));
+    /// assert_eq!(buf.as_mapping().header_uuid(), Some(uuid));
+    /// ```
+    #[cfg(feature = "uuid")]
+    pub fn insert_uuid_header(&mut self) -> Uuid {
+        let mapping = self.as_mapping();
+        if let Some(uuid) = mapping.header_uuid() {
+            return uuid;
+        }
+        let uuid = mapping.checksum_uuid();
+        let header = format!("# uuid: {}\n", uuid);
+        self.source.splice(0..0, header.into_bytes());
+        uuid
+    }
+
+    /// Consumes the buffer, returning the raw bytes.
+    pub fn into_inner(self) -> Vec<u8> {
+        self.source
+    }
+}
+
+/// A cheaply cloneable, thread-safe handle to the contents of a Proguard Mapping file.
+///
+/// The contents are reference counted, so the handle can be shared across
+/// worker threads without copying the file. It can be backed by any
+/// `Arc<T>` where `T: AsRef<[u8]>`, such as an `Arc<Vec<u8>>` or a
+/// memory-mapped file.
+///
+/// # Examples
+///
+/// ```
+/// use std::thread;
+/// use proguard::SharedProguardMapping;
+///
+/// let shared = SharedProguardMapping::from(b"a -> b:\n    void method() -> b".to_vec());
+///
+/// let worker = shared.clone();
+/// let handle = thread::spawn(move || worker.as_mapping().is_valid());
+///
+/// assert!(handle.join().unwrap());
+/// assert_eq!(shared.as_bytes().len(), 30);
+/// ```
+#[derive(Clone)]
+pub struct SharedProguardMapping {
+    source: Arc<dyn AsRef<[u8]> + Send + Sync>,
+}
+
+impl fmt::Debug for SharedProguardMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedProguardMapping")
+            .field("len", &self.as_bytes().len())
+            .finish()
+    }
+}
+
+impl From<Vec<u8>> for SharedProguardMapping {
+    fn from(source: Vec<u8>) -> Self {
+        Self::from_arc(Arc::new(source))
+    }
+}
+
+impl From<ProguardMappingBuf> for SharedProguardMapping {
+    fn from(buf: ProguardMappingBuf) -> Self {
+        Self::from(buf.into_inner())
+    }
+}
+
+impl SharedProguardMapping {
+    /// Creates a handle from shared contents, for example an `Arc<Mmap>`.
+    pub fn from_arc<T>(source: Arc<T>) -> Self
+    where
+        T: AsRef<[u8]> + Send + Sync + 'static,
+    {
+        Self { source }
+    }
+
+    /// The raw bytes of the mapping file.
+    pub fn as_bytes(&self) -> &[u8] {
+        (*self.source).as_ref()
+    }
+
+    /// Returns a [`ProguardMapping`] borrowing from this handle.
+    pub fn as_mapping(&self) -> ProguardMapping<'_> {
+        ProguardMapping::new(self.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn reader_range() {
+        let container = b"0123456789a -> b:\n    void method() -> b\n";
+        let buf =
+            ProguardMappingBuf::from_reader_range(Cursor::new(&container[..]), 10, 7).unwrap();
+        assert_eq!(buf.as_bytes(), b"a -> b:");
+
+        let buf =
+            ProguardMappingBuf::from_reader_range(Cursor::new(&container[..]), 10, 0).unwrap();
+        assert_eq!(buf.as_bytes(), b"");
+    }
+
+    #[test]
+    fn reader_range_eof() {
+        let container = b"0123456789a -> b:\n";
+        let err = ProguardMappingBuf::from_reader_range(Cursor::new(&container[..]), 10, 100)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let err =
+            ProguardMappingBuf::from_reader_range(Cursor::new(&container[..]), 100, 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    /// Serves `body` to a single HTTP request, returning its URL.
+    #[cfg(feature = "http")]
+    fn serve(body: Vec<u8>) -> String {
+        serve_status("200 OK", body)
+    }
+
+    /// Serves `body` with `status` to a single HTTP request, returning its URL.
+    #[cfg(feature = "http")]
+    fn serve_status(status: &'static str, body: Vec<u8>) -> String {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/mapping.txt", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            let header = format!(
+                "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                status,
+                body.len()
+            );
+            stream.write_all(header.as_bytes()).unwrap();
+            stream.write_all(&body).unwrap();
+        });
+        url
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn from_url() {
+        use std::io::Write;
+
+        let mapping = b"a -> b:\n    void method() -> b\n".repeat(10);
+        let limits = DecompressLimits {
+            max_size: 1000,
+            ..Default::default()
+        };
+        let buf = ProguardMappingBuf::from_url(&serve(mapping.clone()), &limits).unwrap();
+        assert_eq!(buf.as_bytes(), &mapping[..]);
+
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(&mapping).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert!(compressed.len() < 100);
+        let buf = ProguardMappingBuf::from_url(&serve(compressed.clone()), &limits).unwrap();
+        assert_eq!(buf.as_bytes(), &mapping[..]);
+
+        // the limit applies to the decompressed mapping as well.
+        let limits = DecompressLimits {
+            max_size: 100,
+            ..Default::default()
+        };
+        let err = ProguardMappingBuf::from_url(&serve(compressed), &limits).unwrap_err();
+        assert!(matches!(err, DecompressError::TooLarge { limit: 100 }));
+
+        // error responses are not read as a mapping.
+        let url = serve_status("404 Not Found", b"not found".to_vec());
+        let err = ProguardMappingBuf::from_url(&url, &limits).unwrap_err();
+        assert!(matches!(err, DecompressError::Http { status: 404 }));
+        assert_eq!(
+            err.to_string(),
+            "failed to download mapping: HTTP status 404"
+        );
+    }
+
+    #[test]
+    fn shared_auto_traits() {
+        fn assert_send_sync_clone<T: Send + Sync + Clone>() {}
+        assert_send_sync_clone::<SharedProguardMapping>();
+        assert_send_sync_clone::<ProguardMappingBuf>();
+    }
+
+    #[test]
+    fn shared_from_arc() {
+        let source: Arc<[u8]> = Arc::from(&b"a -> b:"[..]);
+        let shared = SharedProguardMapping::from_arc(Arc::new(source.clone()));
+        let clone = shared.clone();
+        assert_eq!(clone.as_bytes().as_ptr(), source.as_ptr());
+        assert_eq!(clone.as_mapping().iter().count(), 1);
+
+        let buf = ProguardMappingBuf::from(b"a -> b:".to_vec());
+        assert_eq!(SharedProguardMapping::from(buf).as_bytes(), b"a -> b:");
+    }
+
+    #[cfg(feature = "uuid")]
+    #[test]
+    fn uuid_header() {
+        let mut buf = ProguardMappingBuf::from(b"# compiler: R8\na -> b:\n".to_vec());
+        let uuid = buf.as_mapping().uuid();
+        assert_eq!(buf.insert_uuid_header(), uuid);
+        assert_eq!(buf.as_mapping().header_uuid(), Some(uuid));
+        assert_ne!(buf.as_mapping().uuid(), uuid);
+        let len = buf.as_bytes().len();
+        assert_eq!(buf.insert_uuid_header(), uuid);
+        assert_eq!(buf.as_bytes().len(), len);
+
+        // headers after the first class are not considered
+        let source = format!("a -> b:\n# uuid: {}\n", uuid);
+        let mapping = ProguardMapping::new(source.as_bytes());
+        assert_eq!(mapping.header_uuid(), None);
+        assert_ne!(mapping.uuid(), uuid);
+
+        // invalid values are ignored
+        let mapping = ProguardMapping::new(b"# uuid: nope\na -> b:\n");
+        assert_eq!(mapping.header_uuid(), None);
+    }
+}
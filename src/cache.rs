@@ -0,0 +1,1247 @@
+//! A binary cache format for Proguard Mapping Files.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+#[cfg(feature = "uuid")]
+use uuid_::Uuid;
+
+use crate::classes::ClassMapping;
+use crate::mapper::Confidence;
+use crate::mapping::ProguardMapping;
+use crate::stacktrace::StackFrame;
+
+const MAGIC: &[u8; 4] = b"PGCA";
+const HEADER_LEN: usize = 64;
+/// The header length of version `1.0`, which did not have a line index.
+const HEADER_LEN_1_0: usize = 32;
+/// The header length of versions `1.1` to `1.4`, which did not have a UUID.
+const HEADER_LEN_1_1: usize = 40;
+const CLASS_LEN: usize = 24;
+const MEMBER_LEN: usize = 48;
+const LINE_RANGE_LEN: usize = 8;
+const LINE_ENTRY_LEN: usize = 16;
+const CLASS_HASH_LEN: usize = 8;
+/// The flag marking caches of a mapping with line info.
+const FLAG_LINE_INFO: u32 = 1;
+/// Marks an absent string or number.
+const NONE: u32 = u32::MAX;
+
+/// An error parsing a [`ProguardCache`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum CacheError {
+    /// The data does not start with the cache magic.
+    InvalidMagic,
+    /// The cache was written with an unsupported major version.
+    UnsupportedVersion {
+        /// The major version of the cache.
+        major: u16,
+        /// The minor version of the cache.
+        minor: u16,
+    },
+    /// The data is too short for the tables declared in its header.
+    Truncated,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::InvalidMagic => f.write_str("not a proguard cache"),
+            CacheError::UnsupportedVersion { major, minor } => {
+                write!(f, "unsupported cache version {}.{}", major, minor)
+            }
+            CacheError::Truncated => f.write_str("cache is truncated"),
+        }
+    }
+}
+
+impl Error for CacheError {}
+
+impl From<CacheError> for io::Error {
+    fn from(err: CacheError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+fn align(offset: usize) -> usize {
+    (offset + 7) & !7
+}
+
+/// A member of a class, as stored in the member table.
+#[derive(Clone, Copy, Debug)]
+struct CachedMember {
+    obfuscated: (u32, u32),
+    original: (u32, u32),
+    original_class: (u32, u32),
+    startline: u32,
+    endline: u32,
+    original_startline: u32,
+    original_endline: u32,
+}
+
+impl CachedMember {
+    fn read(data: &[u8]) -> Self {
+        let string = |offset| (read_u32(data, offset), read_u32(data, offset + 4));
+        Self {
+            obfuscated: string(0),
+            original: string(8),
+            original_class: string(16),
+            startline: read_u32(data, 24),
+            endline: read_u32(data, 28),
+            original_startline: read_u32(data, 32),
+            original_endline: read_u32(data, 36),
+        }
+    }
+
+    /// Whether the member matches `line`, which is always the case for
+    /// members without a line mapping.
+    fn matches(&self, line: usize) -> bool {
+        self.endline == 0 || (self.startline as usize..=self.endline as usize).contains(&line)
+    }
+
+    /// The span of the line range of a member with a line mapping.
+    fn span(&self) -> Option<u32> {
+        (self.endline > 0).then(|| self.endline - self.startline)
+    }
+}
+
+/// The line index of a cache, see the layout of [`ProguardCache`].
+#[derive(Clone, Copy, Debug)]
+struct LineIndex<'data> {
+    /// The `(start, count)` of the entries of each class.
+    ranges: &'data [u8],
+    /// `(startline, endline, member, max_endline)` entries, where
+    /// `max_endline` is the maximum `endline` of all preceding entries of the
+    /// same name.
+    entries: &'data [u8],
+}
+
+/// Hashes the original name and members block of `class`.
+fn class_hash(class: &ClassMapping<'_>) -> [u8; CLASS_HASH_LEN] {
+    let mut sha1 = sha1_smol::Sha1::new();
+    sha1.update(class.original().as_bytes());
+    sha1.update(b"\n");
+    sha1.update(class.members_source());
+    let mut hash = [0; CLASS_HASH_LEN];
+    hash.copy_from_slice(&sha1.digest().bytes()[..CLASS_HASH_LEN]);
+    hash
+}
+
+/// Returns the first index in `range` for which `pred` is false, assuming
+/// `pred` is true for a prefix of `range`.
+fn partition_point(range: std::ops::Range<usize>, pred: impl Fn(usize) -> bool) -> usize {
+    let (mut low, mut high) = (range.start, range.end);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// A binary cache of a Proguard Mapping file, optimized for remapping.
+///
+/// Parsing a cache only validates its header and table sizes, so it is cheap
+/// to open a cache which was written ahead of time using
+/// [`write`](Self::write). The layout and compatibility policy of the format
+/// are described below.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{ProguardCache, ProguardMapping, StackFrame};
+///
+/// let mapping = ProguardMapping::new(b"com.example.Foo -> a:\n    1:1:void bar():10 -> a");
+/// let mut buf = Vec::new();
+/// ProguardCache::write(&mapping, &mut buf).unwrap();
+///
+/// let cache = ProguardCache::parse(&buf).unwrap();
+/// assert_eq!(cache.remap_class("a"), Some("com.example.Foo"));
+/// assert_eq!(
+///     cache.remap_frame(&StackFrame::new("a", "a", 1)).collect::<Vec<_>>(),
+///     vec![StackFrame::new("com.example.Foo", "bar", 10)]
+/// );
+/// ```
+///
+/// # Layout
+///
+/// All integers are little-endian. The cache starts with a header:
+///
+/// | Offset | Size | Field                    |
+/// |--------|------|--------------------------|
+/// | 0      | 4    | magic, `PGCA`            |
+/// | 4      | 2    | major version            |
+/// | 6      | 2    | minor version            |
+/// | 8      | 4    | header length in bytes   |
+/// | 12     | 4    | number of classes        |
+/// | 16     | 4    | number of members        |
+/// | 20     | 4    | string table length      |
+/// | 24     | 8    | reserved, zero           |
+/// | 32     | 4    | number of line entries   |
+/// | 36     | 4    | flags                    |
+/// | 40     | 16   | UUID of the mapping      |
+/// | 56     | 8    | `pg_map_id` header       |
+///
+/// The header is followed by the class table, the member table, the string
+/// table, the line index and the class hashes, in this order. Each section starts at an offset
+/// that is a multiple of 8 from the start of the cache, padded with zeroes, so
+/// the tables can be read in place from a suitably aligned buffer. Strings are
+/// referenced by their offset and length within the string table.
+///
+/// The class table is sorted by obfuscated name, so a class can be found using
+/// a binary search without building an index when the cache is loaded.
+/// Classes with the same obfuscated name are kept in file order.
+///
+/// The members of each class are sorted by their obfuscated name, and
+/// otherwise kept in file order. The line index holds the `(start, count)` of
+/// the line entries of each class, followed by the entries themselves. These
+/// are sorted by obfuscated name and minified start line, so the members
+/// matching a frame can be found using a binary search.
+///
+/// The class hashes hold 8 bytes per class, in the order of the class table,
+/// which are the start of the SHA-1 digest of the original name and members
+/// block of the class. They are used to reuse unchanged classes when a cache
+/// is updated for a new mapping.
+///
+/// The flags are a bit set, where bit `0` is set if any member of the mapping
+/// has line info.
+///
+/// The UUID is the one of `ProguardMapping::header_uuid`, or of
+/// `ProguardMapping::uuid` if the mapping has no `# uuid` header, and zero if
+/// the cache was written without the `uuid` feature. The `pg_map_id` header of the mapping
+/// is referenced in the string table, if present.
+///
+/// # Versions
+///
+/// - `1.0`: Initial version.
+/// - `1.1`: Added the line index.
+/// - `1.2`: Added the flags.
+/// - `1.3`: Sorted the class table by obfuscated name.
+/// - `1.4`: Added the class hashes.
+/// - `1.5`: Added the UUID and `pg_map_id` of the mapping.
+/// - `1.6`: Line ranges starting at `0` keep their line mapping, and only the
+///   narrowest matching line ranges are used.
+///
+/// # Compatibility
+///
+/// The major version is incremented for every change that older readers can
+/// not handle. Readers reject caches with a major version they do not support
+/// with [`CacheError::UnsupportedVersion`], instead of misinterpreting them.
+///
+/// The minor version is incremented for backwards compatible changes, which
+/// only ever append fields to the header, or sections after the string table.
+/// Readers accept caches with any minor version of a supported major version,
+/// and ignore data they do not know about.
+#[derive(Clone, Copy)]
+pub struct ProguardCache<'data> {
+    version: (u16, u16),
+    classes: &'data [u8],
+    members: &'data [u8],
+    strings: &'data [u8],
+    line_index: Option<LineIndex<'data>>,
+    /// The flags of the cache, which are only known since version `1.2`.
+    flags: Option<u32>,
+    /// Whether the class table is sorted, which it is since version `1.3`.
+    sorted_classes: bool,
+    /// The hashes of all classes, which are only known since version `1.4`.
+    class_hashes: Option<&'data [u8]>,
+    /// Whether only the narrowest matching line ranges are used, which they
+    /// are since version `1.6`.
+    narrowest_ranges: bool,
+    /// The UUID of the mapping, which is only known since version `1.5`.
+    #[cfg(feature = "uuid")]
+    uuid: Option<[u8; 16]>,
+    /// The `pg_map_id` of the mapping, which is only known since version `1.5`.
+    pg_map_id: (u32, u32),
+}
+
+impl fmt::Debug for ProguardCache<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProguardCache")
+            .field("version", &self.version)
+            .field("classes", &(self.classes.len() / CLASS_LEN))
+            .field("members", &(self.members.len() / MEMBER_LEN))
+            .finish()
+    }
+}
+
+impl<'data> ProguardCache<'data> {
+    /// The major version of the cache format written by this crate.
+    pub const MAJOR_VERSION: u16 = 1;
+    /// The minor version of the cache format written by this crate.
+    pub const MINOR_VERSION: u16 = 6;
+
+    /// Parses a cache, validating its header and table sizes.
+    pub fn parse(data: &'data [u8]) -> Result<Self, CacheError> {
+        trace_span!(DEBUG, "proguard.cache_load", len = data.len());
+        if data.len() < 8 || &data[..4] != MAGIC {
+            return Err(CacheError::InvalidMagic);
+        }
+        let major = read_u16(data, 4);
+        let minor = read_u16(data, 6);
+        if major != Self::MAJOR_VERSION {
+            return Err(CacheError::UnsupportedVersion { major, minor });
+        }
+        if data.len() < HEADER_LEN_1_0 {
+            return Err(CacheError::Truncated);
+        }
+
+        let header_len = read_u32(data, 8) as usize;
+        let class_count = read_u32(data, 12) as usize;
+        let member_count = read_u32(data, 16) as usize;
+        let string_len = read_u32(data, 20) as usize;
+        let has_line_index = minor >= 1;
+        let min_header_len = match minor {
+            0 => HEADER_LEN_1_0,
+            1..=4 => HEADER_LEN_1_1,
+            _ => HEADER_LEN,
+        };
+        if header_len < min_header_len || data.len() < min_header_len {
+            return Err(CacheError::Truncated);
+        }
+
+        let section = |start: usize, len: Option<usize>| {
+            let end = len.and_then(|len| start.checked_add(len));
+            match end {
+                Some(end) if end <= data.len() => Ok((&data[start..end], align(end))),
+                _ => Err(CacheError::Truncated),
+            }
+        };
+        let (classes, offset) = section(align(header_len), class_count.checked_mul(CLASS_LEN))?;
+        let (members, offset) = section(offset, member_count.checked_mul(MEMBER_LEN))?;
+        let (strings, offset) = section(offset, Some(string_len))?;
+
+        let (line_index, offset) = if has_line_index {
+            let entry_count = read_u32(data, 32) as usize;
+            let (ranges, offset) = section(offset, class_count.checked_mul(LINE_RANGE_LEN))?;
+            let (entries, offset) = section(offset, entry_count.checked_mul(LINE_ENTRY_LEN))?;
+            (Some(LineIndex { ranges, entries }), offset)
+        } else {
+            (None, offset)
+        };
+        let class_hashes = if minor >= 4 {
+            Some(section(offset, class_count.checked_mul(CLASS_HASH_LEN))?.0)
+        } else {
+            None
+        };
+        trace_event!(
+            DEBUG,
+            classes = class_count,
+            members = member_count,
+            "loaded cache"
+        );
+
+        Ok(Self {
+            version: (major, minor),
+            classes,
+            members,
+            strings,
+            line_index,
+            flags: (minor >= 2).then(|| read_u32(data, 36)),
+            sorted_classes: minor >= 3,
+            class_hashes,
+            narrowest_ranges: minor >= 6,
+            #[cfg(feature = "uuid")]
+            uuid: (minor >= 5)
+                .then(|| data[40..56].try_into().unwrap())
+                .filter(|uuid| *uuid != [0; 16]),
+            pg_map_id: if minor >= 5 {
+                (read_u32(data, 56), read_u32(data, 60))
+            } else {
+                (NONE, 0)
+            },
+        })
+    }
+
+    /// The `(major, minor)` version of the cache.
+    pub fn version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    /// The number of classes in the cache.
+    pub fn class_count(&self) -> usize {
+        self.classes.len() / CLASS_LEN
+    }
+
+    /// The UUID of the mapping the cache was written from.
+    ///
+    /// This is the `# uuid` header of the mapping if it has one, and its
+    /// checksum-based [`ProguardMapping::uuid`](crate::ProguardMapping::uuid)
+    /// otherwise. It is `None` for caches older than version `1.5`, and for caches
+    /// written without the `uuid` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardCache, ProguardMapping};
+    ///
+    /// let mapping = ProguardMapping::new(b"# pg_map_id: 5b46a1f\na.A -> a:");
+    /// let mut buf = Vec::new();
+    /// ProguardCache::write(&mapping, &mut buf).unwrap();
+    ///
+    /// let cache = ProguardCache::parse(&buf).unwrap();
+    /// assert_eq!(cache.uuid(), Some(mapping.uuid()));
+    /// assert_eq!(cache.pg_map_id(), Some("5b46a1f"));
+    /// ```
+    #[cfg(feature = "uuid")]
+    pub fn uuid(&self) -> Option<Uuid> {
+        self.uuid.map(Uuid::from_bytes)
+    }
+
+    /// The `pg_map_id` header of the mapping the cache was written from.
+    ///
+    /// This is `None` for caches older than version `1.5`, and for mappings
+    /// without the header.
+    pub fn pg_map_id(&self) -> Option<&'data str> {
+        self.string(self.pg_map_id)
+    }
+
+    /// Whether any member of the mapping has line info.
+    ///
+    /// This is recorded when the cache is written. For caches older than
+    /// version `1.2`, all members are checked instead.
+    pub fn has_line_info(&self) -> bool {
+        match self.flags {
+            Some(flags) => flags & FLAG_LINE_INFO != 0,
+            None => self
+                .members
+                .chunks_exact(MEMBER_LEN)
+                .map(CachedMember::read)
+                .any(|member| member.startline > 0 || member.endline > 0),
+        }
+    }
+
+    fn string(&self, (offset, len): (u32, u32)) -> Option<&'data str> {
+        if offset == NONE {
+            return None;
+        }
+        let bytes = self
+            .strings
+            .get(offset as usize..offset as usize + len as usize)?;
+        std::str::from_utf8(bytes).ok()
+    }
+
+    fn member(&self, index: usize) -> Option<CachedMember> {
+        let member = self
+            .members
+            .get(index * MEMBER_LEN..(index + 1) * MEMBER_LEN)?;
+        Some(CachedMember::read(member))
+    }
+
+    /// Returns the index, original name and member range of the class with
+    /// the obfuscated name `class`.
+    ///
+    /// The class table is searched using a binary search, unless the cache
+    /// predates version `1.3`, where it is not sorted. If the table has
+    /// several classes named `class`, the last one is returned.
+    fn class(&self, class: &str) -> Option<(usize, &'data str, std::ops::Range<usize>)> {
+        let count = self.class_count();
+        let entry = |index: usize| &self.classes[index * CLASS_LEN..(index + 1) * CLASS_LEN];
+        let string = |index, offset| {
+            let entry = entry(index);
+            self.string((read_u32(entry, offset), read_u32(entry, offset + 4)))
+        };
+        let name = |index| string(index, 8).unwrap_or_default();
+
+        let index = if self.sorted_classes {
+            Some(partition_point(0..count, |i| name(i) < class)).filter(|&i| i < count)
+        } else {
+            (0..count).rev().find(|&i| name(i) == class)
+        }?;
+        if name(index) != class {
+            return None;
+        }
+        let start = read_u32(entry(index), 16) as usize;
+        let count = read_u32(entry(index), 20) as usize;
+        Some((index, string(index, 0)?, start..start + count))
+    }
+
+    /// Looks up the members of class `index` which match `method` and `line`,
+    /// using the line index.
+    fn lookup(&self, index: usize, method: &str, line: usize) -> Option<Vec<usize>> {
+        let line_index = self.line_index?;
+        let range = line_index
+            .ranges
+            .get(index * LINE_RANGE_LEN..(index + 1) * LINE_RANGE_LEN)?;
+        let start = read_u32(range, 0) as usize;
+        let count = read_u32(range, 4) as usize;
+        let entries = line_index
+            .entries
+            .get(start * LINE_ENTRY_LEN..(start + count) * LINE_ENTRY_LEN)?;
+
+        let field =
+            |i: usize, offset: usize| read_u32(entries, i * LINE_ENTRY_LEN + offset) as usize;
+        let name = |i: usize| {
+            self.member(field(i, 8))
+                .and_then(|member| self.string(member.obfuscated))
+                .unwrap_or_default()
+        };
+
+        let first = partition_point(0..count, |i| name(i) < method);
+        let end = partition_point(first..count, |i| name(i) == method);
+        // members without line mapping come first, and match any line.
+        let ranged = partition_point(first..end, |i| field(i, 4) == 0);
+        let mut matches: Vec<_> = (first..ranged).map(|i| field(i, 8)).collect();
+
+        let after = partition_point(ranged..end, |i| field(i, 0) <= line);
+        for i in (ranged..after).rev() {
+            if field(i, 12) < line {
+                break;
+            }
+            if field(i, 4) >= line {
+                matches.push(field(i, 8));
+            }
+        }
+        matches.sort_unstable();
+        Some(matches)
+    }
+
+    /// Returns the member range, member data and line index entries of
+    /// `class`, if the cache holds the class with the same `hash`.
+    ///
+    /// Returns `None` if any of the ranges are out of bounds, or a line index
+    /// entry refers to a member outside of the class, so the class is written
+    /// from scratch instead.
+    fn unchanged_class(
+        &self,
+        class: &ClassMapping<'_>,
+        hash: &[u8],
+    ) -> Option<(std::ops::Range<usize>, &'data [u8], &'data [u8])> {
+        // older caches lose the line mapping of ranges starting at `0`.
+        if !self.narrowest_ranges {
+            return None;
+        }
+        let (index, original, members) = self.class(class.obfuscated())?;
+        let class_hashes = self.class_hashes?;
+        let cached = class_hashes.get(index * CLASS_HASH_LEN..(index + 1) * CLASS_HASH_LEN)?;
+        if cached != hash || original != class.original() {
+            return None;
+        }
+        let range = self
+            .line_index?
+            .ranges
+            .get(index * LINE_RANGE_LEN..(index + 1) * LINE_RANGE_LEN)?;
+        let start = read_u32(range, 0) as usize;
+        let count = read_u32(range, 4) as usize;
+
+        let member_data = self
+            .members
+            .get(members.start.checked_mul(MEMBER_LEN)?..members.end.checked_mul(MEMBER_LEN)?)?;
+        let entries = self.line_index?.entries.get(
+            start.checked_mul(LINE_ENTRY_LEN)?
+                ..start.checked_add(count)?.checked_mul(LINE_ENTRY_LEN)?,
+        )?;
+        let valid_member = |data: &[u8]| {
+            let member = CachedMember::read(data);
+            self.string(member.obfuscated).is_some() && self.string(member.original).is_some()
+        };
+        let valid_entry = |entry: &[u8]| {
+            (read_u32(entry, 8) as usize)
+                .checked_sub(members.start)
+                .is_some_and(|member| member < members.len())
+        };
+        if !member_data.chunks_exact(MEMBER_LEN).all(valid_member)
+            || !entries.chunks_exact(LINE_ENTRY_LEN).all(valid_entry)
+        {
+            return None;
+        }
+        Some((members, member_data, entries))
+    }
+
+    /// Remaps an obfuscated Class.
+    pub fn remap_class(&self, class: &str) -> Option<&'data str> {
+        self.class(class).map(|(_, original, _)| original)
+    }
+
+    /// Remaps a single Stackframe.
+    ///
+    /// This behaves the same as [`ProguardMapper::remap_frame`](crate::ProguardMapper::remap_frame).
+    pub fn remap_frame<'a>(&'a self, frame: &StackFrame<'a>) -> CacheFrameIter<'a> {
+        trace_span!(
+            TRACE,
+            "proguard.cache_remap_frame",
+            class = frame.class,
+            method = frame.method
+        );
+        let (index, class, members) = match self.class(frame.class) {
+            Some(class) => class,
+            None => {
+                trace_event!(TRACE, "no mapping for frame");
+                return CacheFrameIter::default();
+            }
+        };
+        let mut candidates = self
+            .lookup(index, frame.method, frame.line)
+            .unwrap_or_else(|| members.clone().collect());
+        candidates.retain(|&index| {
+            self.member(index).is_some_and(|member| {
+                self.string(member.obfuscated) == Some(frame.method) && member.matches(frame.line)
+            })
+        });
+        // catch-all ranges like `0:65535` match every line, so prefer the
+        // most specific range if more than one matches.
+        if self.narrowest_ranges {
+            let span = |index| self.member(index).and_then(|member| member.span());
+            let narrowest = candidates.iter().filter_map(|&index| span(index)).min();
+            candidates.retain(|&index| span(index).is_none() || span(index) == narrowest);
+        }
+        // frames without a line are remapped anyway if their method is unique.
+        let line_less = frame.line == 0 && candidates.is_empty();
+        if line_less {
+            candidates = self
+                .unique_method(members, frame.method)
+                .into_iter()
+                .collect();
+        }
+        let mut frame = frame.clone();
+        frame.class = class;
+        CacheFrameIter {
+            inner: Some((*self, frame, candidates.into_iter())),
+            line_less,
+        }
+    }
+
+    /// Returns the first of the `members` named `method`, if all of them map
+    /// to the same original method.
+    fn unique_method(&self, members: std::ops::Range<usize>, method: &str) -> Option<usize> {
+        let original = |member: &CachedMember| {
+            let class = self.string(member.original_class);
+            (class, self.string(member.original))
+        };
+        let mut first = None;
+        for index in members {
+            let member = self.member(index)?;
+            if self.string(member.obfuscated) != Some(method) {
+                continue;
+            }
+            match first {
+                None => first = Some((index, original(&member))),
+                Some((_, ref first)) if *first != original(&member) => return None,
+                Some(_) => {}
+            }
+        }
+        first.map(|(index, _)| index)
+    }
+
+    /// Writes the cache for `mapping` into `writer`.
+    pub fn write<W: Write>(mapping: &ProguardMapping<'_>, writer: &mut W) -> io::Result<()> {
+        Self::write_reusing(mapping, None, writer)
+    }
+
+    /// Writes the cache for `mapping` into `writer`, reusing the classes of
+    /// this cache which did not change.
+    ///
+    /// A class is reused if the hash of its original name and members block
+    /// matches the one recorded in this cache, so only the classes which
+    /// changed are parsed again. The result is the same as that of
+    /// [`write`](Self::write). Caches older than version `1.4` do not record
+    /// class hashes, so nothing is reused from them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardCache, ProguardMapping};
+    ///
+    /// let old = ProguardMapping::new(b"a.A -> a:\n    1:1:void m():3 -> a");
+    /// let mut buf = Vec::new();
+    /// ProguardCache::write(&old, &mut buf).unwrap();
+    /// let cache = ProguardCache::parse(&buf).unwrap();
+    ///
+    /// let new = ProguardMapping::new(b"a.A -> a:\n    1:1:void m():3 -> a\nb.B -> b:");
+    /// let mut updated = Vec::new();
+    /// cache.update(&new, &mut updated).unwrap();
+    ///
+    /// let cache = ProguardCache::parse(&updated).unwrap();
+    /// assert_eq!(cache.remap_class("b"), Some("b.B"));
+    /// ```
+    pub fn update<W: Write>(
+        &self,
+        mapping: &ProguardMapping<'_>,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        Self::write_reusing(mapping, Some(self), writer)
+    }
+
+    /// Writes the cache for `mapping` into `writer`, reusing the unchanged
+    /// classes of `previous`.
+    fn write_reusing<W: Write>(
+        mapping: &ProguardMapping<'_>,
+        previous: Option<&ProguardCache<'_>>,
+        writer: &mut W,
+    ) -> io::Result<()> {
+        trace_span!(DEBUG, "proguard.cache_write");
+        let mut strings = StringTable::default();
+        let mut class_entries = Vec::new();
+        let mut members = Vec::new();
+        let mut line_entries = Vec::new();
+        let mut flags = 0;
+
+        // only the last of several classes with the same obfuscated name is
+        // written, as it is the one `ProguardMapper` uses.
+        let last: HashMap<_, _> = mapping
+            .classes()
+            .enumerate()
+            .map(|(index, class)| (class.obfuscated(), index))
+            .collect();
+        for (index, class) in mapping.classes().enumerate() {
+            if last.get(class.obfuscated()) != Some(&index) {
+                continue;
+            }
+            let start = members.len() / MEMBER_LEN;
+            let hash = class_hash(&class);
+            let unchanged = previous
+                .and_then(|previous| Some((previous, previous.unchanged_class(&class, &hash)?)));
+            if let Some((previous, (member_range, member_data, entries))) = unchanged {
+                for data in member_data.chunks_exact(MEMBER_LEN) {
+                    let member = CachedMember::read(data);
+                    if member.startline > 0 || member.endline > 0 {
+                        flags |= FLAG_LINE_INFO;
+                    }
+                    strings.push(&mut members, previous.string(member.obfuscated));
+                    strings.push(&mut members, previous.string(member.original));
+                    strings.push(&mut members, previous.string(member.original_class));
+                    members.extend_from_slice(&data[24..]);
+                }
+
+                // entries refer to members by their index, which moved to
+                // `start`. `unchanged_class` checked that they are in range.
+                let line_start = line_entries.len() / LINE_ENTRY_LEN;
+                for entry in entries.chunks_exact(LINE_ENTRY_LEN) {
+                    let member = read_u32(entry, 8) as usize - member_range.start + start;
+                    line_entries.extend_from_slice(&entry[..8]);
+                    line_entries.extend_from_slice(&(member as u32).to_le_bytes());
+                    line_entries.extend_from_slice(&entry[12..]);
+                }
+
+                class_entries.push((
+                    class.obfuscated(),
+                    class.original(),
+                    start,
+                    member_range.len(),
+                    (line_start, entries.len() / LINE_ENTRY_LEN),
+                    hash,
+                ));
+                continue;
+            }
+
+            let mut methods: Vec<_> = class.methods().collect();
+            // this is a stable sort, so members with the same name stay in file order.
+            methods.sort_by(|a, b| a.obfuscated.cmp(b.obfuscated));
+
+            let mut entries = Vec::with_capacity(methods.len());
+            for (index, method) in methods.iter().enumerate() {
+                if method.line_mapping.is_some() {
+                    flags |= FLAG_LINE_INFO;
+                }
+                let (startline, endline, original_startline, original_endline) =
+                    match method.line_mapping {
+                        None => (0, 0, 0, None),
+                        Some(ref line_mapping) => match line_mapping.original_startline {
+                            Some(original_startline) => (
+                                line_mapping.startline,
+                                line_mapping.endline,
+                                original_startline,
+                                line_mapping.original_endline,
+                            ),
+                            None => (
+                                line_mapping.startline,
+                                line_mapping.endline,
+                                line_mapping.startline,
+                                Some(line_mapping.endline),
+                            ),
+                        },
+                    };
+                let original_endline = original_endline.map_or(NONE, |line| line as u32);
+
+                strings.push(&mut members, Some(method.obfuscated));
+                strings.push(&mut members, Some(method.original));
+                strings.push(&mut members, method.original_class);
+                for value in [
+                    startline as u32,
+                    endline as u32,
+                    original_startline as u32,
+                    original_endline,
+                    0,
+                    0,
+                ] {
+                    members.extend_from_slice(&value.to_le_bytes());
+                }
+                entries.push((method.obfuscated, startline, endline, start + index));
+            }
+            let count = members.len() / MEMBER_LEN - start;
+
+            // members without line mapping sort first, as they match any line.
+            entries.sort_by_key(|&(name, startline, endline, member)| {
+                (name, endline != 0, startline, member)
+            });
+            let line_range = (line_entries.len() / LINE_ENTRY_LEN, entries.len());
+            let mut max_endline = (None, 0);
+            for (name, startline, endline, member) in entries {
+                if max_endline.0 != Some(name) {
+                    max_endline = (Some(name), 0);
+                }
+                max_endline.1 = max_endline.1.max(endline);
+                for value in [startline, endline, member, max_endline.1] {
+                    line_entries.extend_from_slice(&(value as u32).to_le_bytes());
+                }
+            }
+
+            class_entries.push((
+                class.obfuscated(),
+                class.original(),
+                start,
+                count,
+                line_range,
+                hash,
+            ));
+        }
+
+        class_entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        let mut classes = Vec::with_capacity(class_entries.len() * CLASS_LEN);
+        let mut line_ranges = Vec::with_capacity(class_entries.len() * LINE_RANGE_LEN);
+        let mut class_hashes = Vec::with_capacity(class_entries.len() * CLASS_HASH_LEN);
+        for (obfuscated, original, start, count, (line_start, line_count), hash) in class_entries {
+            strings.push(&mut classes, Some(original));
+            strings.push(&mut classes, Some(obfuscated));
+            for value in [start, count] {
+                classes.extend_from_slice(&(value as u32).to_le_bytes());
+            }
+            for value in [line_start, line_count] {
+                line_ranges.extend_from_slice(&(value as u32).to_le_bytes());
+            }
+            class_hashes.extend_from_slice(&hash);
+        }
+
+        // the string table has to be complete before its length is written.
+        let mut pg_map_id = Vec::with_capacity(8);
+        strings.push(&mut pg_map_id, mapping.headers().get("pg_map_id"));
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&Self::MAJOR_VERSION.to_le_bytes());
+        header.extend_from_slice(&Self::MINOR_VERSION.to_le_bytes());
+        for value in [
+            HEADER_LEN,
+            classes.len() / CLASS_LEN,
+            members.len() / MEMBER_LEN,
+            strings.bytes.len(),
+        ] {
+            header.extend_from_slice(&(value as u32).to_le_bytes());
+        }
+        header.resize(HEADER_LEN_1_0, 0);
+        header.extend_from_slice(&((line_entries.len() / LINE_ENTRY_LEN) as u32).to_le_bytes());
+        header.extend_from_slice(&flags.to_le_bytes());
+        #[cfg(feature = "uuid")]
+        header.extend_from_slice(mapping.identifier_uuid().as_bytes());
+        #[cfg(not(feature = "uuid"))]
+        header.extend_from_slice(&[0; 16]);
+        header.extend_from_slice(&pg_map_id);
+
+        let mut written = 0;
+        for section in [
+            &header,
+            &classes,
+            &members,
+            &strings.bytes,
+            &line_ranges,
+            &line_entries,
+            &class_hashes,
+        ] {
+            writer.write_all(section)?;
+            written += section.len();
+            let padding = align(written) - written;
+            writer.write_all(&[0; 7][..padding])?;
+            written += padding;
+        }
+        Ok(())
+    }
+}
+
+/// An owned buffer holding a validated [`ProguardCache`].
+///
+/// [`ProguardCache`] borrows its data, which requires the whole cache to be
+/// available as a byte slice. This buffer can be filled from any reader, such
+/// as a download from object storage, and then handed out as a
+/// [`ProguardCache`] via [`as_cache`](Self::as_cache). Caches are written to
+/// any [`Write`] using [`ProguardCache::write`].
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Cursor;
+/// use proguard::{ProguardCache, ProguardCacheBuf, ProguardMapping};
+///
+/// let mapping = ProguardMapping::new(b"com.example.Foo -> a:\n    1:1:void bar():10 -> a");
+/// let mut stream = Cursor::new(Vec::new());
+/// ProguardCache::write(&mapping, &mut stream).unwrap();
+///
+/// stream.set_position(0);
+/// let buf = ProguardCacheBuf::from_reader(stream).unwrap();
+/// assert_eq!(buf.as_cache().remap_class("a"), Some("com.example.Foo"));
+/// ```
+#[derive(Clone, PartialEq)]
+pub struct ProguardCacheBuf {
+    data: Vec<u8>,
+}
+
+impl fmt::Debug for ProguardCacheBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProguardCacheBuf")
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+impl ProguardCacheBuf {
+    /// Validates `data` as a cache, taking ownership of it.
+    pub fn new(data: Vec<u8>) -> Result<Self, CacheError> {
+        ProguardCache::parse(&data)?;
+        Ok(Self { data })
+    }
+
+    /// Reads a complete cache from `reader`.
+    ///
+    /// Fails with [`Error::Cache`](crate::Error::Cache) if the data is not a
+    /// valid cache.
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, crate::Error> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(Self::new(data)?)
+    }
+
+    /// Reads a cache of `len` bytes starting at `offset` of `reader`.
+    ///
+    /// This is useful when the cache is stored inside of a larger container
+    /// file, or when only a byte range of a remote blob is being fetched.
+    /// Fails with an [`io::ErrorKind::UnexpectedEof`] error if `reader` ends
+    /// before `len` bytes could be read, and with
+    /// [`Error::Cache`](crate::Error::Cache) if the data is not a valid cache.
+    pub fn from_reader_range<R: Read + Seek>(
+        mut reader: R,
+        offset: u64,
+        len: u64,
+    ) -> Result<Self, crate::Error> {
+        reader.seek(SeekFrom::Start(offset))?;
+
+        // `len` is untrusted input, so cap the preallocation.
+        let mut data = Vec::with_capacity(len.min(1 << 20) as usize);
+        reader.take(len).read_to_end(&mut data)?;
+        if (data.len() as u64) < len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "cache range extends past the end of the reader",
+            )
+            .into());
+        }
+
+        Ok(Self::new(data)?)
+    }
+
+    /// The raw bytes of the cache.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Returns a [`ProguardCache`] borrowing from this buffer.
+    pub fn as_cache(&self) -> ProguardCache<'_> {
+        // the data was validated when the buffer was created.
+        ProguardCache::parse(&self.data).unwrap()
+    }
+}
+
+/// A deduplicating table of strings.
+#[derive(Default)]
+struct StringTable<'s> {
+    bytes: Vec<u8>,
+    offsets: HashMap<&'s str, u32>,
+}
+
+impl<'s> StringTable<'s> {
+    /// Adds `string` to the table, and writes a reference to it into `buf`.
+    fn push(&mut self, buf: &mut Vec<u8>, string: Option<&'s str>) {
+        let (offset, len) = match string {
+            Some(string) => {
+                let bytes = &mut self.bytes;
+                let offset = *self.offsets.entry(string).or_insert_with(|| {
+                    let offset = bytes.len() as u32;
+                    bytes.extend_from_slice(string.as_bytes());
+                    offset
+                });
+                (offset, string.len() as u32)
+            }
+            None => (NONE, 0),
+        };
+        buf.extend_from_slice(&offset.to_le_bytes());
+        buf.extend_from_slice(&len.to_le_bytes());
+    }
+}
+
+/// An Iterator over remapped StackFrames, created by [`ProguardCache::remap_frame`].
+#[derive(Clone, Debug, Default)]
+pub struct CacheFrameIter<'a> {
+    inner: Option<(ProguardCache<'a>, StackFrame<'a>, std::vec::IntoIter<usize>)>,
+    /// Whether the frame has no line, and is remapped by its method name only.
+    line_less: bool,
+}
+
+impl<'a> CacheFrameIter<'a> {
+    /// Returns the next remapped frame, along with how trustworthy it is.
+    ///
+    /// See [`RemappedFrameIter::next_with_confidence`](crate::RemappedFrameIter::next_with_confidence).
+    pub fn next_with_confidence(&mut self) -> Option<(StackFrame<'a>, Confidence)> {
+        let (frame, member) = self.next_member()?;
+        let confidence = Confidence::of_range(member.startline as usize, member.endline as usize);
+        Some((frame, confidence))
+    }
+
+    fn next_member(&mut self) -> Option<(StackFrame<'a>, CachedMember)> {
+        let (cache, frame, members) = self.inner.as_mut()?;
+
+        let index = members.next()?;
+        let mut member = cache.member(index)?;
+        if self.line_less {
+            member.startline = 0;
+            member.endline = 0;
+            member.original_startline = 0;
+            member.original_endline = NONE;
+        }
+        let startline = member.startline as usize;
+        let original_startline = member.original_startline as usize;
+        let line = if member.original_endline == NONE {
+            original_startline
+        } else {
+            // corrupt members may start after the line they matched.
+            original_startline + frame.line.saturating_sub(startline)
+        };
+        let original_class = cache.string(member.original_class);
+        let remapped = StackFrame {
+            class: original_class.unwrap_or(frame.class),
+            method: cache.string(member.original)?,
+            file: if original_class.is_some() {
+                None
+            } else {
+                frame.file
+            },
+            line,
+            module: frame.module,
+        };
+        Some((remapped, member))
+    }
+}
+
+impl<'a> Iterator for CacheFrameIter<'a> {
+    type Item = StackFrame<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_member().map(|(frame, _)| frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(mapping: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        ProguardCache::write(&ProguardMapping::new(mapping), &mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn layout() {
+        let buf = write(b"a.A -> a:\n    1:1:void m():3 -> a\nb.B -> b:");
+        assert_eq!(&buf[..8], b"PGCA\x01\x00\x06\x00");
+        assert_eq!(buf.len() % 8, 0);
+
+        let cache = ProguardCache::parse(&buf).unwrap();
+        assert_eq!(cache.version(), (1, 6));
+        assert!(cache.has_line_info());
+        assert_eq!(cache.class_count(), 2);
+        assert_eq!(cache.remap_class("b"), Some("b.B"));
+        assert_eq!(cache.remap_class("c"), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let mut buf = write(b"a.A -> a:\n    1:1:void m():3 -> a");
+
+        assert_eq!(
+            ProguardCache::parse(b"a.A -> a:").unwrap_err(),
+            CacheError::InvalidMagic
+        );
+        assert_eq!(
+            ProguardCache::parse(&buf[..HEADER_LEN]).unwrap_err(),
+            CacheError::Truncated
+        );
+
+        buf[4] = 2;
+        assert_eq!(
+            ProguardCache::parse(&buf).unwrap_err(),
+            CacheError::UnsupportedVersion { major: 2, minor: 6 }
+        );
+    }
+
+    #[test]
+    fn newer_minor() {
+        // a newer minor version with a longer header and a trailing section.
+        let buf = write(b"a.A -> a:\n    1:1:void m():3 -> a");
+        let mut newer = buf[..HEADER_LEN].to_vec();
+        newer[6] = 6;
+        newer[8] = HEADER_LEN as u8 + 8;
+        newer.extend_from_slice(&[0xff; 8]);
+        newer.extend_from_slice(&buf[HEADER_LEN..]);
+        newer.extend_from_slice(b"future section");
+
+        let cache = ProguardCache::parse(&newer).unwrap();
+        assert_eq!(cache.version(), (1, 6));
+        assert_eq!(
+            cache
+                .remap_frame(&StackFrame::new("a", "a", 1))
+                .collect::<Vec<_>>(),
+            vec![StackFrame::new("a.A", "m", 3)]
+        );
+    }
+
+    #[test]
+    fn corrupt_line_range() {
+        let mut buf = write(b"a.A -> a:\n    1:1:void m():3:5 -> a");
+        let cache = ProguardCache::parse(&buf).unwrap();
+        let offset = cache.members.as_ptr() as usize - buf.as_ptr() as usize;
+        // a member with a start line, but without an end line, matches any line.
+        buf[offset + 24..offset + 32].copy_from_slice(&[5, 0, 0, 0, 0, 0, 0, 0]);
+
+        let cache = ProguardCache::parse(&buf).unwrap();
+        let cache = ProguardCache {
+            line_index: None,
+            ..cache
+        };
+        for line in [0, 5, 7] {
+            let frame = StackFrame::new("a", "a", line);
+            assert_eq!(cache.remap_frame(&frame).next().unwrap().method, "m");
+        }
+    }
+
+    #[test]
+    fn update_corrupt() {
+        let source = b"a.A -> a:\n    1:1:void m():3:5 -> a\n    2:2:void n():4 -> b";
+        let expected = write(source);
+        let cache = ProguardCache::parse(&expected).unwrap();
+        let offset = |data: &[u8]| data.as_ptr() as usize - expected.as_ptr() as usize;
+        let member_count = offset(cache.classes) + 20;
+        let entry_member = offset(cache.line_index.unwrap().entries) + 8;
+
+        // a member count past the member table, and a line index entry which
+        // refers to a member of another class.
+        for (offset, value) in [(member_count, 1000u32), (entry_member, 1000)] {
+            let mut buf = expected.clone();
+            buf[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+            let cache = ProguardCache::parse(&buf).unwrap();
+
+            let mut updated = Vec::new();
+            cache
+                .update(&ProguardMapping::new(source), &mut updated)
+                .unwrap();
+            assert_eq!(updated, expected);
+        }
+    }
+
+    #[test]
+    fn sorted_classes() {
+        let buf = write(
+            b"\
+c.C -> c:
+    1:1:void c():3 -> a
+a.A -> a:
+    1:1:void a():3 -> a
+b.B1 -> b:
+    1:1:void b1():3 -> a
+b.B2 -> b:
+    1:1:void b2():3 -> a",
+        );
+        let cache = ProguardCache::parse(&buf).unwrap();
+        let names: Vec<_> = (0..cache.class_count())
+            .map(|index| cache.string((read_u32(cache.classes, index * CLASS_LEN + 8), 1)))
+            .collect();
+        // the last duplicate wins, like in `ProguardMapper`.
+        assert_eq!(names, vec![Some("a"), Some("b"), Some("c")]);
+
+        for (class, original, method) in [("a", "a.A", "a"), ("b", "b.B2", "b2"), ("c", "c.C", "c")]
+        {
+            assert_eq!(cache.remap_class(class), Some(original));
+            let frame = StackFrame::new(class, "a", 1);
+            assert_eq!(cache.remap_frame(&frame).next().unwrap().method, method);
+        }
+        for class in ["", "0", "aa", "d"] {
+            assert_eq!(cache.remap_class(class), None);
+        }
+
+        // caches before version 1.3 are not sorted, and searched linearly.
+        let unsorted = ProguardCache {
+            sorted_classes: false,
+            ..cache
+        };
+        assert_eq!(unsorted.remap_class("b"), Some("b.B2"));
+        assert_eq!(unsorted.remap_class("d"), None);
+    }
+
+    #[test]
+    fn line_index() {
+        let buf = write(
+            b"\
+a.A -> a:
+    1:5:void outer():10:14 -> a
+    3:3:void b.B.inlined():20:20 -> a
+    3:3:void outer():12 -> a
+    void other() -> a
+    0:2:void zero():30:32 -> b
+    6:9:void later():40:43 -> a",
+        );
+        let cache = ProguardCache::parse(&buf).unwrap();
+        let remap = |method, line| {
+            cache
+                .remap_frame(&StackFrame::new("a", method, line))
+                .map(|frame| (frame.method, frame.line))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(remap("a", 1), vec![("outer", 10), ("other", 0)]);
+        // the narrowest matching range is preferred.
+        assert_eq!(
+            remap("a", 3),
+            vec![("inlined", 20), ("outer", 12), ("other", 0)]
+        );
+        assert_eq!(remap("a", 7), vec![("other", 0), ("later", 41)]);
+        assert_eq!(remap("a", 0), vec![("other", 0)]);
+        assert_eq!(remap("b", 0), vec![("zero", 30)]);
+        assert_eq!(remap("b", 2), vec![("zero", 32)]);
+        assert_eq!(remap("b", 3), vec![]);
+        assert_eq!(remap("c", 1), vec![]);
+
+        // caches before version 1.6 use all matching ranges.
+        let mut buf = buf;
+        buf[6..8].copy_from_slice(&5u16.to_le_bytes());
+        let cache = ProguardCache::parse(&buf).unwrap();
+        let frames: Vec<_> = cache
+            .remap_frame(&StackFrame::new("a", "a", 3))
+            .map(|frame| (frame.method, frame.line))
+            .collect();
+        assert_eq!(
+            frames,
+            vec![("outer", 12), ("inlined", 20), ("outer", 12), ("other", 0)]
+        );
+    }
+}
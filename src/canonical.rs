@@ -0,0 +1,96 @@
+//! Canonicalization of Proguard Mapping Files.
+
+use std::fmt;
+use std::io::Write;
+
+use sha2::{Digest, Sha256};
+
+use crate::mapping::{ProguardMapping, ProguardRecord};
+
+/// A mapping file in canonical form, along with its content hash.
+///
+/// Created by [`ProguardMapping::canonicalize`]. Mappings which only differ in
+/// their formatting have the same canonical form, and thus the same hash:
+///
+/// - Lines end in `\n`, and empty lines are removed.
+/// - Records are written with the syntax of [`ProguardRecord`]'s `Display`
+///   implementation, so members are indented with four spaces.
+/// - The headers preceding the first class are sorted by key and value.
+///
+/// Lines which can not be parsed are kept unchanged.
+#[derive(Clone, PartialEq, Eq)]
+pub struct CanonicalMapping {
+    source: Vec<u8>,
+    sha256: [u8; 32],
+}
+
+impl fmt::Debug for CanonicalMapping {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CanonicalMapping")
+            .field("len", &self.source.len())
+            .field("sha256", &self.sha256_hex())
+            .finish()
+    }
+}
+
+impl CanonicalMapping {
+    pub(crate) fn new(mapping: &ProguardMapping<'_>) -> Self {
+        let header_len = mapping.header_len();
+        let mut headers = Vec::new();
+        let mut source = Vec::with_capacity(mapping.source().len());
+
+        for (_, line) in mapping.lines() {
+            let offset = line.as_ptr() as usize - mapping.source().as_ptr() as usize;
+            match ProguardRecord::try_parse(line) {
+                Ok(ProguardRecord::Header { key, value }) if offset < header_len => {
+                    headers.push((key, value));
+                }
+                // writing into a `Vec` never fails.
+                Ok(record) => writeln!(source, "{}", record).unwrap(),
+                Err(_) => {
+                    source.extend_from_slice(line);
+                    source.push(b'\n');
+                }
+            }
+        }
+
+        headers.sort_unstable();
+        let mut header_block = Vec::new();
+        for (key, value) in headers {
+            let record = ProguardRecord::Header { key, value };
+            writeln!(header_block, "{}", record).unwrap();
+        }
+        source.splice(0..0, header_block);
+
+        let sha256 = Sha256::digest(&source).into();
+        Self { source, sha256 }
+    }
+
+    /// The bytes of the canonical mapping file.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.source
+    }
+
+    /// Returns a [`ProguardMapping`] borrowing the canonical mapping file.
+    pub fn as_mapping(&self) -> ProguardMapping<'_> {
+        ProguardMapping::new(&self.source)
+    }
+
+    /// The SHA-256 digest of the canonical mapping file.
+    pub fn sha256(&self) -> [u8; 32] {
+        self.sha256
+    }
+
+    /// The hex-encoded SHA-256 digest of the canonical mapping file.
+    pub fn sha256_hex(&self) -> String {
+        self.sha256
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Returns the bytes of the canonical mapping file.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.source
+    }
+}
@@ -0,0 +1,922 @@
+//! Class-level views into Proguard Mapping Files.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::Range;
+use std::str;
+
+use crate::java::Type;
+use crate::mapping::{
+    is_segment_header, parse_class, LineIter, LineMapping, ProguardMapping, ProguardRecord,
+    ProguardRecordIter,
+};
+
+/// A Field Mapping, as yielded by [`ClassMapping::fields`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldMapping<'s> {
+    /// Type of the field
+    pub ty: &'s str,
+    /// Original name of the field.
+    pub original: &'s str,
+    /// Obfuscated name of the field.
+    pub obfuscated: &'s str,
+}
+
+impl<'s> FieldMapping<'s> {
+    /// The type of the field, parsed into a [`Type`].
+    pub fn java_type(&self) -> Option<Type<'s>> {
+        Type::parse(self.ty)
+    }
+
+    /// The class originally declaring the field, if it differs from the class
+    /// listing it.
+    ///
+    /// When R8 merges classes, the fields of the merged classes are listed
+    /// with their fully-qualified original name, such as
+    /// `int com.example.Merged.count -> a`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.Host -> a:\n    int a.Merged.count -> a\n    int size -> b");
+    /// let fields: Vec<_> = mapping.classes().next().unwrap().fields().collect();
+    ///
+    /// assert_eq!(fields[0].original_class(), Some("a.Merged"));
+    /// assert_eq!(fields[0].name(), "count");
+    /// assert_eq!(fields[1].original_class(), None);
+    /// assert_eq!(fields[1].name(), "size");
+    /// ```
+    pub fn original_class(&self) -> Option<&'s str> {
+        self.original.rsplit_once('.').map(|(class, _)| class)
+    }
+
+    /// The original name of the field, without the class of a merged field.
+    pub fn name(&self) -> &'s str {
+        self.original
+            .rsplit_once('.')
+            .map_or(self.original, |(_, name)| name)
+    }
+}
+
+impl<'s> TryFrom<ProguardRecord<'s>> for FieldMapping<'s> {
+    /// The record, if it is not a [`ProguardRecord::Field`].
+    type Error = ProguardRecord<'s>;
+
+    fn try_from(record: ProguardRecord<'s>) -> Result<Self, Self::Error> {
+        match record {
+            ProguardRecord::Field {
+                ty,
+                original,
+                obfuscated,
+            } => Ok(FieldMapping {
+                ty,
+                original,
+                obfuscated,
+            }),
+            record => Err(record),
+        }
+    }
+}
+
+impl<'s> From<FieldMapping<'s>> for ProguardRecord<'s> {
+    fn from(field: FieldMapping<'s>) -> Self {
+        ProguardRecord::Field {
+            ty: field.ty,
+            original: field.original,
+            obfuscated: field.obfuscated,
+        }
+    }
+}
+
+/// A Method Mapping, as yielded by [`ClassMapping::methods`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MethodMapping<'s> {
+    /// Return Type of the method.
+    pub ty: &'s str,
+    /// Original name of the method.
+    pub original: &'s str,
+    /// Obfuscated name of the method.
+    pub obfuscated: &'s str,
+    /// Arguments of the method as raw string.
+    pub arguments: &'s str,
+    /// Original class of a foreign inlined method.
+    pub original_class: Option<&'s str>,
+    /// Optional line mapping of the method.
+    pub line_mapping: Option<LineMapping>,
+}
+
+impl<'s> TryFrom<ProguardRecord<'s>> for MethodMapping<'s> {
+    /// The record, if it is not a [`ProguardRecord::Method`].
+    type Error = ProguardRecord<'s>;
+
+    fn try_from(record: ProguardRecord<'s>) -> Result<Self, Self::Error> {
+        match record {
+            ProguardRecord::Method {
+                ty,
+                original,
+                obfuscated,
+                arguments,
+                original_class,
+                line_mapping,
+            } => Ok(MethodMapping {
+                ty,
+                original,
+                obfuscated,
+                arguments,
+                original_class,
+                line_mapping,
+            }),
+            record => Err(record),
+        }
+    }
+}
+
+impl<'s> From<MethodMapping<'s>> for ProguardRecord<'s> {
+    fn from(method: MethodMapping<'s>) -> Self {
+        ProguardRecord::Method {
+            ty: method.ty,
+            original: method.original,
+            obfuscated: method.obfuscated,
+            arguments: method.arguments,
+            original_class: method.original_class,
+            line_mapping: method.line_mapping,
+        }
+    }
+}
+
+impl<'s> MethodMapping<'s> {
+    /// The return type of the method, parsed into a [`Type`].
+    pub fn return_type(&self) -> Option<Type<'s>> {
+        Type::parse(self.ty)
+    }
+
+    /// The argument types of the method, parsed into [`Type`]s.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    void method(int,java.lang.String[]) -> b");
+    /// let method = mapping.classes().next().unwrap().methods().next().unwrap();
+    /// let arguments: Vec<_> = method
+    ///     .argument_types()
+    ///     .unwrap()
+    ///     .iter()
+    ///     .map(|ty| ty.to_string())
+    ///     .collect();
+    ///
+    /// assert_eq!(arguments, vec!["int", "java.lang.String[]"]);
+    /// ```
+    pub fn argument_types(&self) -> Option<Vec<Type<'s>>> {
+        Type::parse_list(self.arguments)
+    }
+
+    /// The canonical signature of the method, such as `void foo(int, java.lang.String)`.
+    ///
+    /// Arguments are separated by `, ` regardless of the whitespace in the
+    /// mapping file, so signatures can be compared and used as keys across
+    /// tools. The name of a foreign inlined method is qualified with its
+    /// original class, like in the mapping file, such as
+    /// `void com.example.Bar.foo()`, so it does not collide with a method of
+    /// the same name in the class it was inlined into.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    1:2:void foo(int,java.lang.String):10:11 -> b");
+    /// let method = mapping.classes().next().unwrap().methods().next().unwrap();
+    ///
+    /// assert_eq!(method.signature(), "void foo(int, java.lang.String)");
+    /// ```
+    pub fn signature(&self) -> String {
+        let method = match self.original_class {
+            Some(class) => format!("{}.{}", class, self.original),
+            None => self.original.to_owned(),
+        };
+        format!(
+            "{} {}({})",
+            normalize_type(self.ty),
+            method,
+            self.argument_list().join(", ")
+        )
+    }
+
+    /// The arguments of the method, normalized like [`Type`]s format them.
+    ///
+    /// Arguments which can not be parsed are only trimmed of whitespace.
+    fn argument_list(&self) -> Vec<String> {
+        match Type::parse_list(self.arguments) {
+            Some(types) => types.iter().map(Type::to_string).collect(),
+            None => self
+                .arguments
+                .split(',')
+                .map(str::trim)
+                .filter(|argument| !argument.is_empty())
+                .map(str::to_owned)
+                .collect(),
+        }
+    }
+
+    /// Whether the arguments of the method match `filter`.
+    fn matches_arguments(&self, filter: ArgumentFilter<'_>) -> bool {
+        let arguments = self.argument_list();
+        match filter {
+            ArgumentFilter::Arity(arity) => arguments.len() == arity,
+            ArgumentFilter::Types(types) => {
+                arguments.len() == types.len()
+                    && arguments
+                        .iter()
+                        .zip(types)
+                        .all(|(a, b)| *a == normalize_type(b))
+            }
+        }
+    }
+
+    /// A stable hash of the [`signature`](Self::signature) of the method.
+    ///
+    /// This is the first 8 bytes of the SHA-1 digest of the signature,
+    /// interpreted as a big-endian integer.
+    pub fn signature_hash(&self) -> u64 {
+        let digest = sha1_smol::Sha1::from(self.signature()).digest().bytes();
+        let mut hash = [0; 8];
+        hash.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(hash)
+    }
+}
+
+/// Normalizes a type like [`Type`] formats it, or trims it if it can not be
+/// parsed.
+fn normalize_type(ty: &str) -> String {
+    match Type::parse(ty) {
+        Some(ty) => ty.to_string(),
+        None => ty.trim().to_owned(),
+    }
+}
+
+/// A filter on the arguments of methods, see [`ClassMapping::get_methods`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ArgumentFilter<'a> {
+    /// Matches methods with this number of arguments.
+    Arity(usize),
+    /// Matches methods with exactly these argument types, such as
+    /// `["int", "java.lang.String"]`.
+    Types(&'a [&'a str]),
+}
+
+/// A single class of a Proguard Mapping file, along with its members.
+///
+/// Created by [`ProguardMapping::classes`].
+#[derive(Clone, PartialEq)]
+pub struct ClassMapping<'s> {
+    original: &'s str,
+    obfuscated: &'s str,
+    members: &'s [u8],
+}
+
+impl fmt::Debug for ClassMapping<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClassMapping")
+            .field("original", &self.original)
+            .field("obfuscated", &self.obfuscated)
+            .finish()
+    }
+}
+
+impl<'s> From<&ClassMapping<'s>> for ProguardRecord<'s> {
+    /// The class line of the class, without its members.
+    fn from(class: &ClassMapping<'s>) -> Self {
+        ProguardRecord::Class {
+            original: class.original,
+            obfuscated: class.obfuscated,
+        }
+    }
+}
+
+impl<'s> ClassMapping<'s> {
+    pub(crate) fn new(original: &'s str, obfuscated: &'s str, members: &'s [u8]) -> Self {
+        Self {
+            original,
+            obfuscated,
+            members,
+        }
+    }
+
+    /// Original name of the class.
+    pub fn original(&self) -> &'s str {
+        self.original
+    }
+
+    /// Obfuscated name of the class.
+    pub fn obfuscated(&self) -> &'s str {
+        self.obfuscated
+    }
+
+    /// The raw bytes of all the lines following the class line.
+    pub(crate) fn members_source(&self) -> &'s [u8] {
+        self.members
+    }
+
+    /// Create an Iterator over all the [`ProguardRecord`]s following the class line.
+    ///
+    /// This, as well as [`fields`](Self::fields) and [`methods`](Self::methods),
+    /// yields members in file order.
+    pub fn members(&self) -> ProguardRecordIter<'s> {
+        ProguardMapping::new(self.members).iter()
+    }
+
+    /// Create an Iterator over the fields of this class.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{FieldMapping, ProguardMapping};
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> b:\n    int field -> a\n    void method() -> b");
+    /// let class = mapping.classes().next().unwrap();
+    ///
+    /// assert_eq!(
+    ///     class.fields().collect::<Vec<_>>(),
+    ///     vec![FieldMapping {
+    ///         ty: "int",
+    ///         original: "field",
+    ///         obfuscated: "a",
+    ///     }]
+    /// );
+    /// ```
+    pub fn fields(&self) -> FieldIter<'s> {
+        FieldIter {
+            inner: self.members(),
+        }
+    }
+
+    /// Finds a field of this class by its original name.
+    ///
+    /// Fields of merged classes match both by their fully-qualified original
+    /// name and by their name alone. If several fields match, the first one
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.Host -> a:\n    int a.Merged.count -> a\n    int size -> b");
+    /// let class = mapping.classes().next().unwrap();
+    ///
+    /// assert_eq!(class.get_field_by_original("size").unwrap().obfuscated, "b");
+    /// assert_eq!(class.get_field_by_original("count").unwrap().obfuscated, "a");
+    /// assert_eq!(class.get_field_by_original("a.Merged.count").unwrap().obfuscated, "a");
+    /// assert_eq!(class.get_field_by_original("b"), None);
+    /// ```
+    pub fn get_field_by_original(&self, original: &str) -> Option<FieldMapping<'s>> {
+        self.fields()
+            .find(|field| field.original == original || field.name() == original)
+    }
+
+    /// Create an Iterator over the methods of this class.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{MethodMapping, ProguardMapping};
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> b:\n    int field -> a\n    void method() -> b");
+    /// let class = mapping.classes().next().unwrap();
+    ///
+    /// assert_eq!(
+    ///     class.methods().collect::<Vec<_>>(),
+    ///     vec![MethodMapping {
+    ///         ty: "void",
+    ///         original: "method",
+    ///         obfuscated: "b",
+    ///         arguments: "",
+    ///         original_class: None,
+    ///         line_mapping: None,
+    ///     }]
+    /// );
+    /// ```
+    pub fn methods(&self) -> MethodIter<'s> {
+        MethodIter {
+            inner: self.members(),
+        }
+    }
+
+    /// Returns the methods with the obfuscated name `obfuscated`, in file order.
+    ///
+    /// If `line` is given, only methods whose line mapping covers the line,
+    /// or which have no line mapping at all, are returned. Callers which know
+    /// the signature of the method can narrow down ambiguous methods further
+    /// using an [`ArgumentFilter`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ArgumentFilter, ProguardMapping};
+    ///
+    /// let mapping = ProguardMapping::new(
+    ///     b"a.A -> a:\n    1:1:void foo(int):10 -> a\n    2:2:void foo(int,long):20 -> a\n    void bar() -> a",
+    /// );
+    /// let class = mapping.classes().next().unwrap();
+    ///
+    /// let methods = class.get_methods("a", Some(2), None);
+    /// assert_eq!(methods.len(), 2);
+    /// assert_eq!((methods[0].original, methods[1].original), ("foo", "bar"));
+    ///
+    /// let methods = class.get_methods("a", None, Some(ArgumentFilter::Arity(1)));
+    /// assert_eq!(methods.len(), 1);
+    /// assert_eq!(methods[0].arguments, "int");
+    ///
+    /// let methods = class.get_methods("a", None, Some(ArgumentFilter::Types(&["int", "long"])));
+    /// assert_eq!(methods.len(), 1);
+    /// assert_eq!(methods[0].arguments, "int,long");
+    /// ```
+    pub fn get_methods(
+        &self,
+        obfuscated: &str,
+        line: Option<usize>,
+        arguments: Option<ArgumentFilter<'_>>,
+    ) -> Vec<MethodMapping<'s>> {
+        self.methods()
+            .filter(|method| method.obfuscated == obfuscated)
+            .filter(|method| match (line, &method.line_mapping) {
+                (Some(line), Some(mapping)) => {
+                    (mapping.startline..=mapping.endline).contains(&line)
+                }
+                _ => true,
+            })
+            .filter(|method| match arguments {
+                Some(filter) => method.matches_arguments(filter),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// The source file of the class, as recorded by R8 metadata.
+    pub(crate) fn source_file(&self) -> Option<&'s str> {
+        self.members()
+            .map_while(|record| match record {
+                Ok(ProguardRecord::Header { key, value }) => Some(R8Header::parse(key, value)),
+                _ => None,
+            })
+            .find_map(|header| match header {
+                Some(R8Header::SourceFile(file_name)) => Some(file_name),
+                _ => None,
+            })
+    }
+}
+
+/// R8 metadata, which is emitted as a JSON header following a class or member.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum R8Header<'s> {
+    /// `{"id":"sourceFile","fileName":"..."}` following a class.
+    SourceFile(&'s str),
+    /// `{"id":"com.android.tools.r8.synthesized"}` following a class or member.
+    Synthesized,
+}
+
+impl<'s> R8Header<'s> {
+    /// Interprets the `key` and `value` of a [`ProguardRecord::Header`].
+    ///
+    /// The JSON is split at its first `:`, so `key` is always `{"id"`.
+    pub(crate) fn parse(key: &'s str, value: Option<&'s str>) -> Option<Self> {
+        if key != "{\"id\"" {
+            return None;
+        }
+        let value = value?;
+        if value.starts_with("\"com.android.tools.r8.synthesized\"") {
+            return Some(R8Header::Synthesized);
+        }
+        let file_name = value.strip_prefix("\"sourceFile\",\"fileName\":\"")?;
+        file_name.split('"').next().map(R8Header::SourceFile)
+    }
+}
+
+/// The location of a class block within a Proguard Mapping file.
+///
+/// The byte range covers the class line along with all of its members. It
+/// can be stored externally, for example in a database, to later jump
+/// straight to the class using [`ProguardMapping::class_at`] without
+/// indexing the mapping again.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ClassHandle<'s> {
+    /// Obfuscated name of the class.
+    pub name: &'s str,
+    /// Original name of the class.
+    pub original: &'s str,
+    /// Byte range of the class block within the mapping file.
+    pub byte_range: Range<usize>,
+}
+
+/// An Iterator yielding [`ClassMapping`]s, created by [`ProguardMapping::classes`].
+#[derive(Clone, Debug)]
+pub struct ClassIter<'s> {
+    source: &'s [u8],
+    lines: LineIter<'s>,
+    /// The names, start of the line, and start of the members of the last class.
+    pending: Option<(&'s str, &'s str, usize, usize)>,
+}
+
+impl<'s> ClassIter<'s> {
+    pub(crate) fn new(mapping: &ProguardMapping<'s>) -> Self {
+        Self {
+            source: mapping.source(),
+            lines: mapping.lines(),
+            pending: None,
+        }
+    }
+
+    fn finish(&mut self, end: usize) -> Option<(ClassMapping<'s>, Range<usize>)> {
+        let (original, obfuscated, start, members_start) = self.pending.take()?;
+        let class = ClassMapping::new(original, obfuscated, &self.source[members_start..end]);
+        Some((class, start..end))
+    }
+
+    /// Returns the next class along with the byte range of its block.
+    fn next_with_range(&mut self) -> Option<(ClassMapping<'s>, Range<usize>)> {
+        while let Some((_, line)) = self.lines.next() {
+            let start = line.as_ptr() as usize - self.source.as_ptr() as usize;
+            // the headers of a concatenated mapping are not part of the last class.
+            if self.pending.is_some() && is_segment_header(line) {
+                if let Some(class) = self.finish(start) {
+                    return Some(class);
+                }
+            }
+            let (original, obfuscated) = match parse_class_line(line) {
+                Some(class) => class,
+                None => continue,
+            };
+            let class = self.finish(start);
+            self.pending = Some((original, obfuscated, start, start + line.len()));
+            if class.is_some() {
+                return class;
+            }
+        }
+        self.finish(self.source.len())
+    }
+}
+
+/// Parses `line` if it is a class line.
+///
+/// This avoids fully parsing member and header lines.
+pub(crate) fn parse_class_line(line: &[u8]) -> Option<(&str, &str)> {
+    if matches!(line.first(), Some(b' ') | Some(b'\t') | Some(b'#')) {
+        return None;
+    }
+    parse_class(str::from_utf8(line).ok()?).ok()
+}
+
+impl<'s> Iterator for ClassIter<'s> {
+    type Item = ClassMapping<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_with_range().map(|(class, _)| class)
+    }
+}
+
+impl FusedIterator for ClassIter<'_> {}
+
+/// An Iterator yielding the [`ClassMapping`]s whose original name matches a
+/// pattern, created by [`ProguardMapping::find_classes_matching`].
+#[derive(Clone, Debug)]
+pub struct ClassMatchIter<'s, 'p> {
+    inner: ClassIter<'s>,
+    pattern: &'p str,
+}
+
+impl<'s, 'p> ClassMatchIter<'s, 'p> {
+    pub(crate) fn new(mapping: &ProguardMapping<'s>, pattern: &'p str) -> Self {
+        Self {
+            inner: ClassIter::new(mapping),
+            pattern,
+        }
+    }
+}
+
+impl<'s> Iterator for ClassMatchIter<'s, '_> {
+    type Item = ClassMapping<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let pattern = self.pattern;
+        self.inner
+            .find(|class| matches_pattern(pattern, class.original()))
+    }
+}
+
+impl FusedIterator for ClassMatchIter<'_, '_> {}
+
+/// Whether `name` matches `pattern`, ignoring ASCII case.
+///
+/// Patterns containing `*` or `?` are globs, which need to match all of
+/// `name`. All other patterns match any part of `name`.
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.as_bytes(), name.as_bytes());
+    if !pattern.iter().any(|&b| b == b'*' || b == b'?') {
+        return pattern.is_empty()
+            || name
+                .windows(pattern.len())
+                .any(|window| window.eq_ignore_ascii_case(pattern));
+    }
+
+    // the position after the last `*`, and the position in `name` it matched up to.
+    let mut backtrack = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(&b) if b == b'?' || b.eq_ignore_ascii_case(&name[n]) => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, n));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+/// An Iterator yielding [`ClassHandle`]s, created by [`ProguardMapping::class_handles`].
+#[derive(Clone, Debug)]
+pub struct ClassHandleIter<'s> {
+    inner: ClassIter<'s>,
+}
+
+impl<'s> ClassHandleIter<'s> {
+    pub(crate) fn new(mapping: &ProguardMapping<'s>) -> Self {
+        Self {
+            inner: ClassIter::new(mapping),
+        }
+    }
+}
+
+impl<'s> Iterator for ClassHandleIter<'s> {
+    type Item = ClassHandle<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (class, byte_range) = self.inner.next_with_range()?;
+        Some(ClassHandle {
+            name: class.obfuscated(),
+            original: class.original(),
+            byte_range,
+        })
+    }
+}
+
+impl FusedIterator for ClassHandleIter<'_> {}
+
+/// An Iterator yielding `(original, obfuscated)` class name pairs, created by
+/// [`ProguardMapping::class_names`].
+#[derive(Clone, Debug)]
+pub struct ClassNameIter<'s> {
+    lines: LineIter<'s>,
+}
+
+impl<'s> ClassNameIter<'s> {
+    pub(crate) fn new(mapping: &ProguardMapping<'s>) -> Self {
+        Self {
+            lines: mapping.lines(),
+        }
+    }
+}
+
+impl<'s> Iterator for ClassNameIter<'s> {
+    type Item = (&'s str, &'s str);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.find_map(|(_, line)| parse_class_line(line))
+    }
+}
+
+impl FusedIterator for ClassNameIter<'_> {}
+
+/// An Iterator yielding [`FieldMapping`]s, created by [`ClassMapping::fields`].
+#[derive(Clone, Debug)]
+pub struct FieldIter<'s> {
+    inner: ProguardRecordIter<'s>,
+}
+
+impl<'s> Iterator for FieldIter<'s> {
+    type Item = FieldMapping<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .by_ref()
+            .find_map(|record| FieldMapping::try_from(record.ok()?).ok())
+    }
+}
+
+impl FusedIterator for FieldIter<'_> {}
+
+/// An Iterator yielding [`MethodMapping`]s, created by [`ClassMapping::methods`].
+#[derive(Clone, Debug)]
+pub struct MethodIter<'s> {
+    inner: ProguardRecordIter<'s>,
+}
+
+impl<'s> Iterator for MethodIter<'s> {
+    type Item = MethodMapping<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .by_ref()
+            .find_map(|record| MethodMapping::try_from(record.ok()?).ok())
+    }
+}
+
+impl FusedIterator for MethodIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        for (pattern, name, matches) in [
+            ("", "a.A", true),
+            ("activity", "com.example.MainActivity", true),
+            ("activty", "com.example.MainActivity", false),
+            ("com.*.Main*", "com.example.MainActivity", true),
+            ("com.*.Main*", "org.example.MainActivity", false),
+            ("*$?", "a.Outer$1", true),
+            ("*$?", "a.Outer$12", false),
+            ("a*b*c", "abxbc", true),
+            ("a*b*c", "abcx", false),
+            ("**", "", true),
+        ] {
+            assert_eq!(
+                matches_pattern(pattern, name),
+                matches,
+                "{} {}",
+                pattern,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn classes() {
+        let mapping = ProguardMapping::new(
+            b"\
+# compiler: R8
+a.A -> a:
+    int field -> a
+a.B -> b:
+a.C -> c:
+# {\"id\":\"sourceFile\",\"fileName\":\"C.java\"}
+    1:1:void method():10:10 -> a
+    long other -> b",
+        );
+        let classes: Vec<_> = mapping.classes().collect();
+        let names: Vec<_> = classes
+            .iter()
+            .map(|class| (class.original(), class.obfuscated()))
+            .collect();
+        assert_eq!(names, vec![("a.A", "a"), ("a.B", "b"), ("a.C", "c")]);
+
+        assert_eq!(classes[0].fields().count(), 1);
+        assert_eq!(classes[0].methods().count(), 0);
+        assert_eq!(classes[1].members().count(), 0);
+        assert_eq!(classes[2].members().count(), 3);
+        assert_eq!(
+            classes[2].fields().map(|f| f.original).collect::<Vec<_>>(),
+            vec!["other"]
+        );
+        assert_eq!(
+            classes[2]
+                .methods()
+                .map(|m| (m.original, m.line_mapping.map(|l| l.startline)))
+                .collect::<Vec<_>>(),
+            vec![("method", Some(1))]
+        );
+
+        let class_names: Vec<_> = mapping.class_names().collect();
+        assert_eq!(class_names, names);
+    }
+
+    #[test]
+    fn signatures() {
+        let mapping = ProguardMapping::new(
+            b"\
+a.A -> a:
+    1:1:void foo(int,java.lang.String):10:10 -> a
+    void foo(int) -> b
+    java.lang.Object bar() -> c
+    1:1:void b.B.foo(int):20:20 -> a",
+        );
+        let mut methods: Vec<_> = mapping.classes().next().unwrap().methods().collect();
+        // the whitespace of hand-written arguments is normalized.
+        methods.insert(
+            1,
+            MethodMapping {
+                arguments: " int , java.lang.String ",
+                line_mapping: None,
+                ..methods[0].clone()
+            },
+        );
+        let signatures: Vec<_> = methods.iter().map(|m| m.signature()).collect();
+        assert_eq!(
+            signatures,
+            vec![
+                "void foo(int, java.lang.String)",
+                "void foo(int, java.lang.String)",
+                "void foo(int)",
+                "java.lang.Object bar()",
+                "void b.B.foo(int)",
+            ]
+        );
+        assert_eq!(methods[0].signature_hash(), methods[1].signature_hash());
+        assert_ne!(methods[0].signature_hash(), methods[2].signature_hash());
+        assert_ne!(methods[2].signature_hash(), methods[4].signature_hash());
+
+        // generic and array types are normalized, and not split at their commas.
+        let mapping = ProguardMapping::new(
+            b"a.A -> a:\n    java.util.List<K>[] baz(java.util.Map<K,int[]>,java.lang.String[]) -> a",
+        );
+        let class = mapping.classes().next().unwrap();
+        let method = class.methods().next().unwrap();
+        assert_eq!(
+            method.signature(),
+            "java.util.List<K>[] baz(java.util.Map<K, int[]>, java.lang.String[])"
+        );
+        assert_eq!(
+            class.get_methods("a", None, Some(ArgumentFilter::Arity(2))),
+            vec![method.clone()]
+        );
+        assert_eq!(
+            class.get_methods(
+                "a",
+                None,
+                Some(ArgumentFilter::Types(&[
+                    "java.util.Map<K,int[]>",
+                    "java.lang.String[]"
+                ]))
+            ),
+            vec![method]
+        );
+        assert!(class
+            .get_methods("a", None, Some(ArgumentFilter::Types(&["java.util.Map<K"])))
+            .is_empty());
+    }
+
+    #[test]
+    fn r8_headers() {
+        let header = |line: &'static str| match ProguardRecord::try_parse(line.as_bytes()) {
+            Ok(ProguardRecord::Header { key, value }) => R8Header::parse(key, value),
+            _ => None,
+        };
+        assert_eq!(
+            header("# {\"id\":\"sourceFile\",\"fileName\":\"Foo.kt\"}"),
+            Some(R8Header::SourceFile("Foo.kt"))
+        );
+        assert_eq!(
+            header("# {\"id\":\"com.android.tools.r8.synthesized\"}"),
+            Some(R8Header::Synthesized)
+        );
+        assert_eq!(
+            header("# {\"id\":\"com.android.tools.r8.rewriteFrame\"}"),
+            None
+        );
+        assert_eq!(header("# compiler: R8"), None);
+
+        let mapping = ProguardMapping::new(
+            b"a.A -> a:\n# {\"id\":\"sourceFile\",\"fileName\":\"A.kt\"}\n    int f -> a\nb.B -> b:",
+        );
+        let classes: Vec<_> = mapping.classes().collect();
+        assert_eq!(classes[0].source_file(), Some("A.kt"));
+        assert_eq!(classes[1].source_file(), None);
+    }
+
+    #[test]
+    fn record_conversions() {
+        let mapping = ProguardMapping::new(
+            b"a.A -> a:\n    int field -> a\n    1:2:void b.B.run(int):10:11 -> b",
+        );
+        let records: Vec<_> = mapping.iter().map(Result::unwrap).collect();
+        let class = mapping.classes().next().unwrap();
+        let field = class.fields().next().unwrap();
+        let method = class.methods().next().unwrap();
+
+        assert_eq!(ProguardRecord::from(&class), records[0]);
+        assert_eq!(ProguardRecord::from(field.clone()), records[1]);
+        assert_eq!(ProguardRecord::from(method.clone()), records[2]);
+
+        assert_eq!(FieldMapping::try_from(records[1].clone()), Ok(field));
+        assert_eq!(MethodMapping::try_from(records[2].clone()), Ok(method));
+        assert_eq!(
+            FieldMapping::try_from(records[2].clone()),
+            Err(records[2].clone())
+        );
+        assert_eq!(
+            MethodMapping::try_from(records[0].clone()),
+            Err(records[0].clone())
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Decompression of Proguard Mapping Files with size limits.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::mapping::find_line_terminator;
+
+/// The first bytes of a gzip stream.
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+/// The first bytes of a zstd frame.
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Limits enforced while reading a possibly compressed mapping.
+///
+/// Uploaded mappings are untrusted, and a small compressed file can expand
+/// to a huge mapping. The limits apply to the decompressed data, so reading
+/// fails early instead of exhausting memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecompressLimits {
+    /// The maximum size of the mapping in bytes. Defaults to 1 GiB.
+    pub max_size: u64,
+    /// The maximum length of a single line in bytes, excluding its line
+    /// terminator. Defaults to 1 MiB.
+    pub max_line_len: usize,
+}
+
+impl Default for DecompressLimits {
+    fn default() -> Self {
+        Self {
+            max_size: 1 << 30,
+            max_line_len: 1 << 20,
+        }
+    }
+}
+
+/// An error reading a mapping using [`DecompressLimits`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum DecompressError {
+    /// Reading or decompressing the data failed.
+    Io(io::Error),
+    /// The server responded to a download with an error status, such as `404`.
+    #[cfg(feature = "http")]
+    Http {
+        /// The HTTP status code of the response.
+        status: u16,
+    },
+    /// The mapping is larger than [`DecompressLimits::max_size`].
+    TooLarge {
+        /// The exceeded limit in bytes.
+        limit: u64,
+    },
+    /// A line is longer than [`DecompressLimits::max_line_len`].
+    LineTooLong {
+        /// The line number of the offending line, 1-based.
+        line_number: usize,
+        /// The exceeded limit in bytes.
+        limit: usize,
+    },
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecompressError::Io(e) => write!(f, "failed to read mapping: {}", e),
+            #[cfg(feature = "http")]
+            DecompressError::Http { status } => {
+                write!(f, "failed to download mapping: HTTP status {}", status)
+            }
+            DecompressError::TooLarge { limit } => {
+                write!(f, "mapping exceeds the size limit of {} bytes", limit)
+            }
+            DecompressError::LineTooLong { line_number, limit } => write!(
+                f,
+                "line {} exceeds the length limit of {} bytes",
+                line_number, limit
+            ),
+        }
+    }
+}
+
+impl Error for DecompressError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            DecompressError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DecompressError {
+    fn from(err: io::Error) -> Self {
+        DecompressError::Io(err)
+    }
+}
+
+/// Reads all of `reader`, decompressing gzip and zstd data, and enforcing `limits`.
+pub(crate) fn decompress<R: Read>(
+    mut reader: R,
+    limits: &DecompressLimits,
+) -> Result<Vec<u8>, DecompressError> {
+    // the magic is read up-front, and then chained back in front of the rest.
+    let mut magic = Vec::with_capacity(ZSTD_MAGIC.len());
+    (&mut reader)
+        .take(ZSTD_MAGIC.len() as u64)
+        .read_to_end(&mut magic)?;
+    let reader = io::Cursor::new(magic).chain(reader);
+
+    let head = reader.get_ref().0.get_ref();
+    if head.starts_with(GZIP_MAGIC) {
+        read_limited(flate2::read::MultiGzDecoder::new(reader), limits)
+    } else if head.starts_with(ZSTD_MAGIC) {
+        read_limited(zstd::Decoder::new(reader)?, limits)
+    } else {
+        read_limited(reader, limits)
+    }
+}
+
+/// Reads all of `reader`, enforcing `limits`.
+fn read_limited<R: Read>(reader: R, limits: &DecompressLimits) -> Result<Vec<u8>, DecompressError> {
+    let mut reader = reader.take(limits.max_size.saturating_add(1));
+    let mut source = Vec::new();
+    let mut chunk = [0; 64 * 1024];
+    let mut line_number = 1;
+    let mut line_len = 0;
+    // whether the last byte read was a `\r`, which may be followed by a `\n`
+    // in the next chunk.
+    let mut after_cr = false;
+
+    loop {
+        let read = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err.into()),
+        };
+        let mut rest = &chunk[..read];
+        while !rest.is_empty() {
+            let pos = find_line_terminator(rest);
+            line_len += pos.unwrap_or(rest.len());
+            if line_len > limits.max_line_len {
+                return Err(DecompressError::LineTooLong {
+                    line_number,
+                    limit: limits.max_line_len,
+                });
+            }
+            let pos = match pos {
+                Some(pos) => pos,
+                None => {
+                    after_cr = false;
+                    break;
+                }
+            };
+            // `\r\n` terminates a single line.
+            if !(pos == 0 && after_cr && rest[pos] == b'\n') {
+                line_number += 1;
+            }
+            after_cr = rest[pos] == b'\r';
+            line_len = 0;
+            rest = &rest[pos + 1..];
+        }
+        source.extend_from_slice(&chunk[..read]);
+        if source.len() as u64 > limits.max_size {
+            return Err(DecompressError::TooLarge {
+                limit: limits.max_size,
+            });
+        }
+    }
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Write;
+
+    const MAPPING: &[u8] = b"a -> b:\r\n    void method() -> b\n";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        let limits = DecompressLimits::default();
+        let zstd = zstd::encode_all(MAPPING, 0).unwrap();
+        for input in [MAPPING.to_vec(), gzip(MAPPING), zstd] {
+            assert_eq!(decompress(&input[..], &limits).unwrap(), MAPPING);
+        }
+        // inputs shorter than the magic are read as they are.
+        assert_eq!(decompress(&b"a"[..], &limits).unwrap(), b"a");
+        assert_eq!(decompress(&b""[..], &limits).unwrap(), b"");
+    }
+
+    #[test]
+    fn limits() {
+        let bomb = gzip(&vec![b'\n'; 1 << 20]);
+        let limits = DecompressLimits {
+            max_size: 1000,
+            ..Default::default()
+        };
+        assert!(matches!(
+            decompress(&bomb[..], &limits),
+            Err(DecompressError::TooLarge { limit: 1000 })
+        ));
+
+        let limits = DecompressLimits {
+            max_line_len: 21,
+            ..Default::default()
+        };
+        let err = decompress(&gzip(MAPPING)[..], &limits).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2 exceeds the length limit of 21 bytes"
+        );
+        assert!(matches!(
+            err,
+            DecompressError::LineTooLong {
+                line_number: 2,
+                limit: 21
+            }
+        ));
+
+        let limits = DecompressLimits {
+            max_size: MAPPING.len() as u64,
+            max_line_len: 22,
+        };
+        assert_eq!(decompress(MAPPING, &limits).unwrap(), MAPPING);
+
+        // a `\r\n` split across reads terminates a single line.
+        let limits = DecompressLimits {
+            max_line_len: 1,
+            ..Default::default()
+        };
+        let reader = (&b"a\r"[..]).chain(&b"\n\rbc"[..]);
+        assert!(matches!(
+            read_limited(reader, &limits),
+            Err(DecompressError::LineTooLong {
+                line_number: 3,
+                limit: 1
+            })
+        ));
+    }
+}
@@ -0,0 +1,192 @@
+//! The error type shared by all fallible operations of the crate.
+
+use std::fmt;
+use std::io;
+
+use crate::cache::CacheError;
+#[cfg(feature = "compression")]
+use crate::decompress::DecompressError;
+use crate::mapping::{ParseError, ParseErrorKind, SyntaxError, SyntaxErrorKind};
+use crate::view_hierarchy::ViewHierarchyError;
+
+/// An error of any operation of this crate.
+///
+/// APIs which can fail for more than one reason, such as reading a
+/// [`ProguardCacheBuf`](crate::ProguardCacheBuf), return this error. APIs with a single failure mode return a more specific
+/// error, which converts into this one, so `?` works across all of them.
+/// The specific error is available from [`source`](std::error::Error::source).
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{Error, ProguardMapping};
+///
+/// fn first_error(source: &[u8]) -> Result<(), Error> {
+///     ProguardMapping::new(source).parse_strict()?;
+///     Ok(())
+/// }
+///
+/// let error = first_error(b"a.A -> a:\n    invalid").unwrap_err();
+/// assert_eq!(error.to_string(), "invalid mapping at 2:12: unexpected end of line");
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing data failed.
+    Io(io::Error),
+    /// A mapping line failed to parse, see [`ParseError`].
+    Parse {
+        /// The offending line.
+        line: Vec<u8>,
+        /// The specific parse error.
+        kind: ParseErrorKind,
+    },
+    /// A mapping is malformed, see [`SyntaxError`].
+    Syntax {
+        /// The offending line.
+        line: Vec<u8>,
+        /// The line number of the offending line, 1-based.
+        line_number: usize,
+        /// The column at which the error was detected, as a 1-based byte offset.
+        column: usize,
+        /// The reason for the error.
+        kind: SyntaxErrorKind,
+    },
+    /// The data is not a valid [`ProguardCache`](crate::ProguardCache).
+    Cache(CacheError),
+    /// A view hierarchy document is malformed.
+    ViewHierarchy(ViewHierarchyError),
+    /// Formatting remapped output failed.
+    Format(fmt::Error),
+    /// Reading a compressed mapping failed, or exceeded its limits.
+    #[cfg(feature = "compression")]
+    Decompress(DecompressError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "i/o error: {}", e),
+            Error::Parse {
+                kind: ParseErrorKind::ParseError(reason),
+                ..
+            } => write!(f, "invalid mapping line: {}", reason),
+            Error::Parse {
+                kind: ParseErrorKind::Utf8Error(e),
+                ..
+            } => write!(f, "invalid mapping line: {}", e),
+            Error::Syntax {
+                line_number,
+                column,
+                kind,
+                ..
+            } => write!(f, "invalid mapping at {}:{}: {}", line_number, column, kind),
+            Error::Cache(e) => write!(f, "invalid proguard cache: {}", e),
+            Error::ViewHierarchy(e) => write!(f, "failed to remap view hierarchy: {}", e),
+            Error::Format(e) => write!(f, "failed to format output: {}", e),
+            #[cfg(feature = "compression")]
+            Error::Decompress(e) => write!(f, "failed to read compressed mapping: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            Error::Parse {
+                kind: ParseErrorKind::Utf8Error(e),
+                ..
+            } => Some(e),
+            Error::Parse { .. } | Error::Syntax { .. } => None,
+            Error::Cache(e) => Some(e),
+            Error::ViewHierarchy(e) => Some(e),
+            Error::Format(e) => Some(e),
+            #[cfg(feature = "compression")]
+            Error::Decompress(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<ParseError<'_>> for Error {
+    fn from(err: ParseError<'_>) -> Self {
+        Error::Parse {
+            line: err.line().to_vec(),
+            kind: err.kind(),
+        }
+    }
+}
+
+impl From<SyntaxError<'_>> for Error {
+    fn from(err: SyntaxError<'_>) -> Self {
+        Error::Syntax {
+            line: err.line().to_vec(),
+            line_number: err.line_number(),
+            column: err.column(),
+            kind: err.kind(),
+        }
+    }
+}
+
+impl From<CacheError> for Error {
+    fn from(err: CacheError) -> Self {
+        Error::Cache(err)
+    }
+}
+
+impl From<ViewHierarchyError> for Error {
+    fn from(err: ViewHierarchyError) -> Self {
+        Error::ViewHierarchy(err)
+    }
+}
+
+#[cfg(feature = "compression")]
+impl From<DecompressError> for Error {
+    fn from(err: DecompressError) -> Self {
+        Error::Decompress(err)
+    }
+}
+
+impl From<fmt::Error> for Error {
+    fn from(err: fmt::Error) -> Self {
+        Error::Format(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+    use crate::{ProguardCache, ProguardRecord};
+
+    #[test]
+    fn sources() {
+        let error = Error::from(ProguardRecord::try_parse(b"    int \xff").unwrap_err());
+        assert_eq!(
+            error.to_string(),
+            "invalid mapping line: invalid utf-8 sequence of 1 bytes from index 8"
+        );
+        assert!(error.source().unwrap().is::<std::str::Utf8Error>());
+
+        let error = Error::from(ProguardCache::parse(b"PGCA\x01\x00\x00\x00").unwrap_err());
+        assert_eq!(
+            error.to_string(),
+            "invalid proguard cache: cache is truncated"
+        );
+        assert_eq!(
+            error.source().unwrap().downcast_ref(),
+            Some(&CacheError::Truncated)
+        );
+
+        let error = Error::from(io::Error::new(io::ErrorKind::UnexpectedEof, "eof"));
+        assert_eq!(error.to_string(), "i/o error: eof");
+        assert!(error.source().unwrap().is::<io::Error>());
+    }
+}
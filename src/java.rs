@@ -0,0 +1,382 @@
+//! Java types, as they appear in Proguard Mapping Files.
+//!
+//! Mapping files spell types the way they are written in Java source, such
+//! as `int`, `java.lang.String[]` or `java.util.List<java.lang.String>`. This
+//! module parses these into a [`Type`], which formats back into a normalized
+//! type string.
+//!
+//! # Examples
+//!
+//! ```
+//! use proguard::java::{Primitive, Type};
+//!
+//! let ty = Type::parse("java.util.Map<java.lang.String,int[]>[]").unwrap();
+//! assert_eq!(ty.dimensions(), 1);
+//! assert_eq!(ty.class_name(), Some("java.util.Map"));
+//! assert_eq!(ty.to_string(), "java.util.Map<java.lang.String, int[]>[]");
+//!
+//! assert_eq!(Type::parse("long"), Some(Type::Primitive(Primitive::Long)));
+//! ```
+
+use std::fmt;
+
+/// A primitive Java type, including `void`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    /// `boolean`
+    Boolean,
+    /// `byte`
+    Byte,
+    /// `char`
+    Char,
+    /// `short`
+    Short,
+    /// `int`
+    Int,
+    /// `long`
+    Long,
+    /// `float`
+    Float,
+    /// `double`
+    Double,
+    /// `void`
+    Void,
+}
+
+impl Primitive {
+    /// Returns the primitive type named `name`, such as `int`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "boolean" => Primitive::Boolean,
+            "byte" => Primitive::Byte,
+            "char" => Primitive::Char,
+            "short" => Primitive::Short,
+            "int" => Primitive::Int,
+            "long" => Primitive::Long,
+            "float" => Primitive::Float,
+            "double" => Primitive::Double,
+            "void" => Primitive::Void,
+            _ => return None,
+        })
+    }
+
+    /// The name of the primitive type, as written in Java source.
+    pub fn name(self) -> &'static str {
+        match self {
+            Primitive::Boolean => "boolean",
+            Primitive::Byte => "byte",
+            Primitive::Char => "char",
+            Primitive::Short => "short",
+            Primitive::Int => "int",
+            Primitive::Long => "long",
+            Primitive::Float => "float",
+            Primitive::Double => "double",
+            Primitive::Void => "void",
+        }
+    }
+}
+
+impl fmt::Display for Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A type argument of a generic class.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TypeArgument<'s> {
+    /// A concrete type, such as `java.lang.String`.
+    Type(Type<'s>),
+    /// An unbounded wildcard, `?`.
+    Wildcard,
+    /// A wildcard with an upper bound, `? extends T`.
+    Extends(Type<'s>),
+    /// A wildcard with a lower bound, `? super T`.
+    Super(Type<'s>),
+}
+
+impl fmt::Display for TypeArgument<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TypeArgument::Type(ty) => write!(f, "{}", ty),
+            TypeArgument::Wildcard => f.write_str("?"),
+            TypeArgument::Extends(ty) => write!(f, "? extends {}", ty),
+            TypeArgument::Super(ty) => write!(f, "? super {}", ty),
+        }
+    }
+}
+
+/// A Java type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Type<'s> {
+    /// A primitive type, such as `int`.
+    Primitive(Primitive),
+    /// A class, along with its type arguments, such as `java.util.List<T>`.
+    Class {
+        /// The fully-qualified name of the class.
+        name: &'s str,
+        /// The type arguments, which are empty for non-generic classes.
+        arguments: Vec<TypeArgument<'s>>,
+    },
+    /// An array of the inner type.
+    Array(Box<Type<'s>>),
+}
+
+impl<'s> Type<'s> {
+    /// Parses a single type, such as `java.lang.String[]`.
+    ///
+    /// Returns `None` if `ty` is not a valid type, or has trailing content.
+    pub fn parse(ty: &'s str) -> Option<Self> {
+        let mut parser = Parser { rest: ty };
+        let ty = parser.parse_type()?;
+        parser.skip_whitespace();
+        parser.rest.is_empty().then_some(ty)
+    }
+
+    /// Parses a comma-separated list of types, such as the arguments of a method.
+    ///
+    /// An empty string is an empty list.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::java::Type;
+    ///
+    /// let types = Type::parse_list("int,java.util.Map<K,V>").unwrap();
+    /// assert_eq!(types.len(), 2);
+    /// assert_eq!(types[1].to_string(), "java.util.Map<K, V>");
+    /// ```
+    pub fn parse_list(list: &'s str) -> Option<Vec<Self>> {
+        let mut parser = Parser { rest: list };
+        let mut types = Vec::new();
+        parser.skip_whitespace();
+        if parser.rest.is_empty() {
+            return Some(types);
+        }
+        loop {
+            types.push(parser.parse_type()?);
+            parser.skip_whitespace();
+            if parser.rest.is_empty() {
+                return Some(types);
+            }
+            if !parser.eat(",") {
+                return None;
+            }
+        }
+    }
+
+    /// The innermost type of an array, or the type itself.
+    pub fn element_type(&self) -> &Self {
+        match self {
+            Type::Array(inner) => inner.element_type(),
+            ty => ty,
+        }
+    }
+
+    /// The number of array dimensions, which is `0` for non-array types.
+    pub fn dimensions(&self) -> usize {
+        match self {
+            Type::Array(inner) => 1 + inner.dimensions(),
+            _ => 0,
+        }
+    }
+
+    /// The name of the class of this type, or of its elements for arrays.
+    pub fn class_name(&self) -> Option<&'s str> {
+        match self.element_type() {
+            Type::Class { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type<'_> {
+    /// Formats the type as written in Java source.
+    ///
+    /// Type arguments are separated by `, `.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Primitive(primitive) => write!(f, "{}", primitive),
+            Type::Class { name, arguments } => {
+                f.write_str(name)?;
+                if !arguments.is_empty() {
+                    f.write_str("<")?;
+                    for (i, argument) in arguments.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        write!(f, "{}", argument)?;
+                    }
+                    f.write_str(">")?;
+                }
+                Ok(())
+            }
+            Type::Array(inner) => write!(f, "{}[]", inner),
+        }
+    }
+}
+
+/// A recursive descent parser over the remaining input.
+struct Parser<'s> {
+    rest: &'s str,
+}
+
+impl<'s> Parser<'s> {
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    /// Consumes `token`, returning whether it was present.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        match self.rest.strip_prefix(token) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn parse_name(&mut self) -> Option<&'s str> {
+        self.skip_whitespace();
+        let end = self
+            .rest
+            .find(|c: char| c.is_whitespace() || "<>[],?".contains(c))
+            .unwrap_or(self.rest.len());
+        let (name, rest) = self.rest.split_at(end);
+        if name.is_empty() {
+            return None;
+        }
+        self.rest = rest;
+        Some(name)
+    }
+
+    fn parse_type(&mut self) -> Option<Type<'s>> {
+        let name = self.parse_name()?;
+        let mut ty = match Primitive::from_name(name) {
+            Some(primitive) => Type::Primitive(primitive),
+            None => {
+                let mut arguments = Vec::new();
+                if self.eat("<") {
+                    loop {
+                        arguments.push(self.parse_argument()?);
+                        if self.eat(">") {
+                            break;
+                        }
+                        if !self.eat(",") {
+                            return None;
+                        }
+                    }
+                }
+                Type::Class { name, arguments }
+            }
+        };
+        while self.eat("[") {
+            if !self.eat("]") {
+                return None;
+            }
+            ty = Type::Array(Box::new(ty));
+        }
+        Some(ty)
+    }
+
+    fn parse_argument(&mut self) -> Option<TypeArgument<'s>> {
+        if !self.eat("?") {
+            return self.parse_type().map(TypeArgument::Type);
+        }
+        let checkpoint = self.rest;
+        match self.parse_name() {
+            Some("extends") => self.parse_type().map(TypeArgument::Extends),
+            Some("super") => self.parse_type().map(TypeArgument::Super),
+            _ => {
+                self.rest = checkpoint;
+                Some(TypeArgument::Wildcard)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(name: &str) -> Type<'_> {
+        Type::Class {
+            name,
+            arguments: vec![],
+        }
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Type::parse("int"), Some(Type::Primitive(Primitive::Int)));
+        assert_eq!(Type::parse("a.B$C"), Some(class("a.B$C")));
+        assert_eq!(
+            Type::parse("byte[][]"),
+            Some(Type::Array(Box::new(Type::Array(Box::new(
+                Type::Primitive(Primitive::Byte)
+            )))))
+        );
+        assert_eq!(
+            Type::parse("java.util.List< ? extends a.B >"),
+            Some(Type::Class {
+                name: "java.util.List",
+                arguments: vec![TypeArgument::Extends(class("a.B"))],
+            })
+        );
+        assert_eq!(
+            Type::parse("java.util.Map<?,? super a.B[]>"),
+            Some(Type::Class {
+                name: "java.util.Map",
+                arguments: vec![
+                    TypeArgument::Wildcard,
+                    TypeArgument::Super(Type::Array(Box::new(class("a.B")))),
+                ],
+            })
+        );
+
+        for invalid in ["", "int[", "a.B<", "a.B<>", "a.B<C", "a.B]", "a b", "?"] {
+            assert_eq!(Type::parse(invalid), None, "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn parse_list() {
+        assert_eq!(Type::parse_list(""), Some(vec![]));
+        assert_eq!(
+            Type::parse_list("int,a.B<C,D>, long[]").map(|types| types.len()),
+            Some(3)
+        );
+        assert_eq!(Type::parse_list("int,"), None);
+        assert_eq!(Type::parse_list(",int"), None);
+    }
+
+    #[test]
+    fn display() {
+        for (input, output) in [
+            ("void", "void"),
+            ("java.lang.String[]", "java.lang.String[]"),
+            ("java.util.Map<K,V>[]", "java.util.Map<K, V>[]"),
+            (
+                "java.util.List<? extends java.util.Set<?>>",
+                "java.util.List<? extends java.util.Set<?>>",
+            ),
+            ("java.lang.Class< ? super T >", "java.lang.Class<? super T>"),
+        ] {
+            assert_eq!(Type::parse(input).unwrap().to_string(), output);
+        }
+    }
+
+    #[test]
+    fn accessors() {
+        let ty = Type::parse("a.B<C>[][]").unwrap();
+        assert_eq!(ty.dimensions(), 2);
+        assert_eq!(ty.class_name(), Some("a.B"));
+        assert_eq!(ty.element_type().to_string(), "a.B<C>");
+
+        let ty = Type::parse("char[]").unwrap();
+        assert_eq!(ty.class_name(), None);
+        assert_eq!(ty.element_type(), &Type::Primitive(Primitive::Char));
+    }
+}
@@ -5,6 +5,29 @@
 //!
 //! The `uuid` feature also allows getting the UUID of the proguard file.
 //!
+//! The `sqlite` feature allows exporting a mapping as a SQLite database.
+//!
+//! The `compression` feature allows reading gzip and zstd compressed mappings
+//! with size limits, and the `http` feature allows downloading a mapping
+//! using `ProguardMappingBuf::from_url`.
+//!
+//! The `rayon` feature allows remapping batches of frames in parallel using
+//! `ProguardMapper::par_remap_frames`.
+//!
+//! The `tracing` feature emits `tracing` spans and events when parsing a
+//! mapping, building the index of a `ProguardMapper`, loading a
+//! `ProguardCache` and remapping frames.
+//!
+//! The `testutils` feature adds the `testutils` module, which generates
+//! synthetic mappings for tests and benchmarks.
+//!
+//! # Ordering
+//!
+//! All iterators over a mapping file, such as [`ProguardMapping::iter`],
+//! [`ProguardMapping::classes`] and the member iterators of [`ClassMapping`],
+//! yield their items in file order. Iterating the same input twice always
+//! produces identical results, so anything derived from them is stable too.
+//!
 //! # Examples
 //!
 //! ```
@@ -33,15 +56,82 @@
 //! );
 //! ```
 
+#![forbid(unsafe_code)]
 #![warn(missing_docs)]
 
+/// Enters a `tracing` span at `$level` until the end of the enclosing block,
+/// if the `tracing` feature is enabled.
+macro_rules! trace_span {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::span!(tracing::Level::$level, $($args)*).entered();
+    };
+}
+
+/// Emits a `tracing` event at `$level`, if the `tracing` feature is enabled.
+macro_rules! trace_event {
+    ($level:ident, $($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($args)*);
+    };
+}
+
+mod archive;
+mod baseline_profile;
+mod buffer;
+mod cache;
+mod canonical;
+mod classes;
+#[cfg(feature = "compression")]
+mod decompress;
+mod error;
+pub mod java;
+mod lint;
+mod manifest;
 mod mapper;
 mod mapping;
+mod metadata;
+mod obfuscator;
+mod raw;
+mod router;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 mod stacktrace;
+#[cfg(feature = "testutils")]
+pub mod testutils;
+mod text;
+mod view_hierarchy;
+mod visitor;
+mod watch;
+mod writer;
 
-pub use mapper::{ProguardMapper, RemappedFrameIter};
+pub use archive::MappingArchive;
+pub use buffer::{ProguardMappingBuf, SharedProguardMapping};
+pub use cache::{CacheError, CacheFrameIter, ProguardCache, ProguardCacheBuf};
+pub use canonical::CanonicalMapping;
+pub use classes::{
+    ArgumentFilter, ClassHandle, ClassHandleIter, ClassIter, ClassMapping, ClassMatchIter,
+    ClassNameIter, FieldIter, FieldMapping, MethodIter, MethodMapping,
+};
+#[cfg(feature = "compression")]
+pub use decompress::{DecompressError, DecompressLimits};
+pub use error::Error;
+pub use lint::{LintKind, LintWarning};
+pub use manifest::{ManifestMismatch, MappingManifest};
+pub use mapper::{
+    Confidence, ProguardMapper, RemappedFrame, RemappedFrameIter, RetraceOptions, SentryFrame,
+    SourceLocation, TraceCoverage,
+};
 pub use mapping::{
-    LineMapping, MappingSummary, ParseError, ParseErrorKind, ProguardMapping, ProguardRecord,
-    ProguardRecordIter,
+    CompilerVersion, LineMapping, MappingHeaders, MappingSummary, ParseError, ParseErrorKind,
+    ProguardMapping, ProguardRecord, ProguardRecordIter, SegmentIter, SyntaxError, SyntaxErrorKind,
 };
-pub use stacktrace::{StackFrame, StackTrace, Throwable};
+pub use metadata::DebugFileMetadata;
+pub use obfuscator::ProguardObfuscator;
+pub use raw::{RawRecord, RawRecordIter};
+pub use router::RoutingMapper;
+pub use stacktrace::{FrameKey, Retraceable, StackFrame, StackTrace, StackTraceFormat, Throwable};
+pub use view_hierarchy::ViewHierarchyError;
+pub use visitor::{MappingVisitor, PushParser};
+pub use watch::{MappingWatcher, Refresh};
+pub use writer::{MappingWriter, StandardHeaders};
@@ -0,0 +1,320 @@
+//! Linting of Proguard Mapping Files.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::mapping::{ProguardMapping, ProguardRecord};
+
+/// A suspicious piece of content found by [`ProguardMapping::lint`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct LintWarning<'s> {
+    line_number: usize,
+    kind: LintKind<'s>,
+}
+
+impl<'s> LintWarning<'s> {
+    /// The line number the warning refers to, 1-based.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// The kind of the warning.
+    pub fn kind(&self) -> &LintKind<'s> {
+        &self.kind
+    }
+}
+
+impl fmt::Display for LintWarning<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.line_number, self.kind)
+    }
+}
+
+/// The kind of a [`LintWarning`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum LintKind<'s> {
+    /// Two different minified line ranges of the same method overlap.
+    ///
+    /// Identical ranges are fine, as those describe inlined frames. Overloads
+    /// sharing the same obfuscated name are checked separately.
+    OverlappingLineRanges {
+        /// The obfuscated name of the class.
+        class: &'s str,
+        /// The obfuscated name of the method.
+        member: &'s str,
+        /// The previously seen minified line range.
+        first: (usize, usize),
+        /// The minified line range overlapping with `first`.
+        second: (usize, usize),
+    },
+    /// A member with the same obfuscated name and signature was already
+    /// defined in this class.
+    DuplicateMember {
+        /// The obfuscated name of the class.
+        class: &'s str,
+        /// The obfuscated name of the member.
+        member: &'s str,
+    },
+    /// A class does not have any members.
+    EmptyClass {
+        /// The obfuscated name of the class.
+        class: &'s str,
+    },
+    /// A header appears after the first class record.
+    MisplacedHeader {
+        /// The key of the header.
+        key: &'s str,
+    },
+    /// The file ends in the middle of a record, which usually means that it
+    /// was cut off.
+    Truncated,
+}
+
+impl fmt::Display for LintKind<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintKind::OverlappingLineRanges {
+                class,
+                member,
+                first,
+                second,
+            } => write!(
+                f,
+                "line range {}:{} of `{}.{}` overlaps with {}:{}",
+                second.0, second.1, class, member, first.0, first.1
+            ),
+            LintKind::DuplicateMember { class, member } => {
+                write!(f, "duplicate member `{}.{}`", class, member)
+            }
+            LintKind::EmptyClass { class } => write!(f, "class `{}` has no members", class),
+            LintKind::MisplacedHeader { key } => {
+                write!(f, "header `{}` appears after the first class", key)
+            }
+            LintKind::Truncated => write!(f, "file ends in the middle of a record"),
+        }
+    }
+}
+
+/// The obfuscated name, original name and arguments of a method.
+type MethodKey<'s> = (&'s str, &'s str, &'s str);
+
+#[derive(Default)]
+struct ClassState<'s> {
+    line_number: usize,
+    obfuscated: &'s str,
+    member_count: usize,
+    ranges: HashMap<MethodKey<'s>, Vec<(usize, usize)>>,
+    signatures: HashSet<(&'s str, &'s str, Option<&'s str>)>,
+}
+
+pub(crate) fn lint<'s>(mapping: &ProguardMapping<'s>) -> Vec<LintWarning<'s>> {
+    let mut warnings = Vec::new();
+    let mut class: Option<ClassState<'s>> = None;
+    let mut last_line_number = 0;
+
+    let finish_class = |class: Option<ClassState<'s>>, warnings: &mut Vec<LintWarning<'s>>| {
+        if let Some(class) = class {
+            if class.member_count == 0 {
+                warnings.push(LintWarning {
+                    line_number: class.line_number,
+                    kind: LintKind::EmptyClass {
+                        class: class.obfuscated,
+                    },
+                });
+            }
+        }
+    };
+
+    for (line_number, line) in mapping.lines() {
+        last_line_number = line_number;
+        let warn = |kind| LintWarning { line_number, kind };
+        match ProguardRecord::try_parse(line) {
+            Ok(ProguardRecord::Header { key, .. }) => {
+                // R8 emits json metadata as comments within class blocks.
+                if class.is_some() && !key.starts_with('{') {
+                    warnings.push(warn(LintKind::MisplacedHeader { key }));
+                }
+            }
+            Ok(ProguardRecord::Class { obfuscated, .. }) => {
+                finish_class(class.take(), &mut warnings);
+                class = Some(ClassState {
+                    line_number,
+                    obfuscated,
+                    ..Default::default()
+                });
+            }
+            Ok(ProguardRecord::Field { ty, obfuscated, .. }) => {
+                if let Some(class) = class.as_mut() {
+                    class.member_count += 1;
+                    if !class.signatures.insert((obfuscated, ty, None)) {
+                        warnings.push(warn(LintKind::DuplicateMember {
+                            class: class.obfuscated,
+                            member: obfuscated,
+                        }));
+                    }
+                }
+            }
+            Ok(ProguardRecord::Method {
+                ty,
+                original,
+                obfuscated,
+                arguments,
+                line_mapping,
+                ..
+            }) => {
+                let class = match class.as_mut() {
+                    Some(class) => class,
+                    None => continue,
+                };
+                class.member_count += 1;
+
+                // Methods with line mappings are repeated for every range, so
+                // only methods without one are checked for duplicates.
+                let line_mapping = match line_mapping {
+                    Some(line_mapping) => line_mapping,
+                    None => {
+                        if !class.signatures.insert((obfuscated, ty, Some(arguments))) {
+                            warnings.push(warn(LintKind::DuplicateMember {
+                                class: class.obfuscated,
+                                member: obfuscated,
+                            }));
+                        }
+                        continue;
+                    }
+                };
+
+                let range = (line_mapping.startline, line_mapping.endline);
+                let ranges = class
+                    .ranges
+                    .entry((obfuscated, original, arguments))
+                    .or_default();
+                if ranges.contains(&range) {
+                    continue;
+                }
+                if let Some(&first) = ranges
+                    .iter()
+                    .find(|(start, end)| range.0 <= *end && *start <= range.1)
+                {
+                    warnings.push(warn(LintKind::OverlappingLineRanges {
+                        class: class.obfuscated,
+                        member: obfuscated,
+                        first,
+                        second: range,
+                    }));
+                }
+                ranges.push(range);
+            }
+            Err(_) => {}
+        }
+    }
+    finish_class(class, &mut warnings);
+
+    if mapping.is_truncated() {
+        warnings.push(LintWarning {
+            line_number: last_line_number,
+            kind: LintKind::Truncated,
+        });
+    }
+
+    warnings.sort_by_key(|warning| warning.line_number);
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lint_clean() {
+        let mapping = ProguardMapping::new(
+            b"\
+# compiler: R8
+a -> b:
+# {\"id\":\"sourceFile\",\"fileName\":\"A.java\"}
+    int field -> a
+    1:1:void inlined():10:10 -> a
+    1:1:void method():20 -> a
+    2:3:void method():21:22 -> a
+",
+        );
+        assert_eq!(lint(&mapping), vec![]);
+    }
+
+    #[test]
+    fn lint_truncated() {
+        let mapping = ProguardMapping::new(b"a -> b:\n    void method() -> b\n    ");
+        assert_eq!(
+            lint(&mapping),
+            vec![LintWarning {
+                line_number: 3,
+                kind: LintKind::Truncated
+            }]
+        );
+
+        let mapping = ProguardMapping::new(b"a -> b:\n    void method() -> b\nc -> d");
+        assert_eq!(
+            lint(&mapping),
+            vec![LintWarning {
+                line_number: 3,
+                kind: LintKind::Truncated
+            }]
+        );
+    }
+
+    #[test]
+    fn lint_warnings() {
+        let mapping = ProguardMapping::new(
+            b"\
+a -> a:
+b -> b:
+    int field -> a
+    int other -> a
+    long another -> a
+    void method(int) -> b
+    void other(int) -> b
+    void other(long) -> b
+# compiler: R8
+c -> c:
+    1:5:void method():10:14 -> a
+    1:5:void inlined():20:24 -> a
+    5:6:void method():15:16 -> a
+    7:8:void method():17:18 -> a
+",
+        );
+        let kinds: Vec<_> = lint(&mapping)
+            .into_iter()
+            .map(|warning| (warning.line_number(), warning.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (1, LintKind::EmptyClass { class: "a" }),
+                (
+                    4,
+                    LintKind::DuplicateMember {
+                        class: "b",
+                        member: "a"
+                    }
+                ),
+                (
+                    7,
+                    LintKind::DuplicateMember {
+                        class: "b",
+                        member: "b"
+                    }
+                ),
+                (9, LintKind::MisplacedHeader { key: "compiler" }),
+                (
+                    13,
+                    LintKind::OverlappingLineRanges {
+                        class: "c",
+                        member: "a",
+                        first: (1, 5),
+                        second: (5, 6),
+                    }
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Verification of Proguard Mapping Files against a manifest.
+
+use std::fmt;
+
+use crate::mapping::ProguardMapping;
+
+/// Metadata that describes a packaged Proguard Mapping file.
+///
+/// Mappings are commonly shipped alongside a manifest, which records the
+/// properties of the file at the time it was packaged. All fields are
+/// optional, and only the provided ones are verified by
+/// [`ProguardMapping::verify_manifest`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MappingManifest {
+    /// The expected hyphenated UUID of the mapping file, as returned by
+    /// `ProguardMapping::uuid`.
+    ///
+    /// The UUID is verified regardless of the `uuid` feature.
+    pub uuid: Option<String>,
+    /// The expected size of the mapping file in bytes.
+    pub size: Option<u64>,
+    /// The expected hex-encoded SHA-1 checksum of the mapping file.
+    pub sha1: Option<String>,
+}
+
+/// A mismatch between a [`MappingManifest`] and the actual mapping file.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum ManifestMismatch {
+    /// The UUID of the mapping does not match.
+    Uuid {
+        /// The UUID recorded in the manifest.
+        expected: String,
+        /// The UUID of the mapping file.
+        actual: String,
+    },
+    /// The size of the mapping does not match.
+    Size {
+        /// The size recorded in the manifest.
+        expected: u64,
+        /// The size of the mapping file.
+        actual: u64,
+    },
+    /// The SHA-1 checksum of the mapping does not match.
+    Sha1 {
+        /// The checksum recorded in the manifest.
+        expected: String,
+        /// The checksum of the mapping file.
+        actual: String,
+    },
+}
+
+impl fmt::Display for ManifestMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ManifestMismatch::Uuid { expected, actual } => {
+                write!(f, "uuid mismatch: expected {}, got {}", expected, actual)
+            }
+            ManifestMismatch::Size { expected, actual } => {
+                write!(f, "size mismatch: expected {}, got {}", expected, actual)
+            }
+            ManifestMismatch::Sha1 { expected, actual } => {
+                write!(f, "sha1 mismatch: expected {}, got {}", expected, actual)
+            }
+        }
+    }
+}
+
+pub(crate) fn verify(
+    mapping: &ProguardMapping<'_>,
+    manifest: &MappingManifest,
+) -> Vec<ManifestMismatch> {
+    let mut mismatches = Vec::new();
+    let source = mapping.source();
+
+    if let Some(ref expected) = manifest.uuid {
+        let actual = mapping.checksum_uuid_string();
+        if !actual.eq_ignore_ascii_case(expected) {
+            mismatches.push(ManifestMismatch::Uuid {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+
+    if let Some(expected) = manifest.size {
+        let actual = source.len() as u64;
+        if actual != expected {
+            mismatches.push(ManifestMismatch::Size { expected, actual });
+        }
+    }
+
+    if let Some(ref expected) = manifest.sha1 {
+        let actual = sha1_smol::Sha1::from(source).digest().to_string();
+        if !actual.eq_ignore_ascii_case(expected) {
+            mismatches.push(ManifestMismatch::Sha1 {
+                expected: expected.clone(),
+                actual,
+            });
+        }
+    }
+
+    mismatches
+}
@@ -1,55 +1,717 @@
-use std::collections::{BTreeMap, HashMap};
-use std::fmt::{Error as FmtError, Write};
+use std::borrow::Cow;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt::{self, Error as FmtError, Write};
 use std::iter::FusedIterator;
+use std::ops::{Bound, Range};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError, RwLock};
 
-use crate::mapping::{ProguardMapping, ProguardRecord};
-use crate::stacktrace::{self, StackFrame, StackTrace, Throwable};
+use crate::baseline_profile;
+use crate::classes::{ClassMapping, MethodMapping, R8Header};
+use crate::mapping::{
+    find_line_terminator, is_segment_header, parse_mapping, split_line, ProguardMapping,
+    ProguardRecord,
+};
+use crate::stacktrace::{self, FrameKey, Retraceable, StackFrame, StackTrace, Throwable};
+use crate::text;
+use crate::view_hierarchy::{self, ViewHierarchyError};
 
-#[derive(Clone, Debug)]
-struct MemberMapping<'s> {
+/// A byte range within the members block of a class.
+///
+/// Lazily parsed members refer to the mapping via spans instead of borrowed
+/// strings, which keeps [`ProguardMapper`] covariant over its lifetime.
+type Span = (usize, usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct MemberMapping {
     startline: usize,
     endline: usize,
-    original_class: Option<&'s str>,
-    original: &'s str,
+    original_class: Option<Span>,
+    original: Span,
     original_startline: usize,
     original_endline: Option<usize>,
+    /// Whether R8 marked the member as synthesized.
+    synthesized: bool,
 }
 
-#[derive(Clone, Debug)]
-struct ClassMapping<'s> {
-    original: &'s str,
-    obfuscated: &'s str,
-    members: BTreeMap<&'s str, Vec<MemberMapping<'s>>>,
+impl MemberMapping {
+    /// Whether the member matches `line`, which is always the case for
+    /// members without a line mapping.
+    fn matches(&self, line: usize) -> bool {
+        self.endline == 0 || (self.startline..=self.endline).contains(&line)
+    }
+}
+
+/// The members of a class along with their obfuscated name.
+///
+/// These are stored in a single allocation, sorted by obfuscated name and
+/// then by file order.
+type Members = Vec<(Span, MemberMapping)>;
+
+/// Limits the number of classes whose members are indexed to the ones which
+/// are used most often.
+#[derive(Debug)]
+struct IndexLimit {
+    max_classes: usize,
+    /// The obfuscated names of the classes indexed under the limit.
+    ///
+    /// These are owned, so that the mapper stays covariant over `'s`.
+    indexed: Mutex<Vec<Box<str>>>,
+    /// A lower bound of the hits of the least used indexed class, once the
+    /// limit is reached. Classes with fewer hits are not considered for
+    /// promotion, which avoids contending for `indexed`.
+    threshold: AtomicU32,
+}
+
+impl Clone for IndexLimit {
+    fn clone(&self) -> Self {
+        let indexed = self.indexed.lock().unwrap_or_else(PoisonError::into_inner);
+        Self {
+            max_classes: self.max_classes,
+            indexed: Mutex::new(indexed.clone()),
+            threshold: AtomicU32::new(self.threshold.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl IndexLimit {
+    fn new(max_classes: usize) -> Self {
+        Self {
+            max_classes,
+            indexed: Mutex::new(Vec::new()),
+            threshold: AtomicU32::new(0),
+        }
+    }
+
+    /// Returns the members of `entry` named `method`, for a class which is
+    /// not permanently indexed.
+    ///
+    /// Every lookup counts as a hit of the class. Classes are only indexed
+    /// once they are used repeatedly, and if there is room or they have more
+    /// hits than the least used indexed class, which is then evicted. The
+    /// members of all other classes are scanned.
+    fn members<'a>(
+        &self,
+        classes: &HashMap<&str, ClassEntry<'_>>,
+        entry: &'a ClassEntry<'_>,
+        method: &str,
+    ) -> Option<MemberList<'a>> {
+        let hits = entry.hits.fetch_add(1, Ordering::Relaxed).saturating_add(1);
+        if let Some(members) = entry.limited_members(method) {
+            return members;
+        }
+        if hits >= 2
+            && self.max_classes > 0
+            && hits > self.threshold.load(Ordering::Relaxed)
+            && self.promote(classes, entry, hits, true)
+        {
+            if let Some(members) = entry.limited_members(method) {
+                return members;
+            }
+        }
+        entry.scan(method)
+    }
+
+    /// Indexes `entry`, which has been used `hits` times.
+    ///
+    /// If the limit is reached, the least used indexed class is evicted if it
+    /// has fewer hits, and `evict` is set. Returns whether `entry` is indexed.
+    fn promote(
+        &self,
+        classes: &HashMap<&str, ClassEntry<'_>>,
+        entry: &ClassEntry<'_>,
+        hits: u32,
+        evict: bool,
+    ) -> bool {
+        let name = entry.class.obfuscated();
+        let mut indexed = self.indexed.lock().unwrap_or_else(PoisonError::into_inner);
+        if indexed.iter().any(|indexed| &**indexed == name) {
+            return true;
+        }
+
+        let hits_of = |name: &str| {
+            classes
+                .get(name)
+                .map_or(0, |entry| entry.hits.load(Ordering::Relaxed))
+        };
+        if indexed.len() >= self.max_classes {
+            let coldest = indexed
+                .iter()
+                .enumerate()
+                .map(|(index, name)| (index, hits_of(name)))
+                .min_by_key(|&(_, hits)| hits);
+            let (index, coldest_hits) = match coldest {
+                Some(coldest) if evict => coldest,
+                _ => return false,
+            };
+            if hits <= coldest_hits {
+                self.threshold.store(coldest_hits, Ordering::Relaxed);
+                return false;
+            }
+            let evicted = indexed.swap_remove(index);
+            if let Some(evicted) = classes.get(&*evicted) {
+                *evicted
+                    .limited
+                    .write()
+                    .unwrap_or_else(PoisonError::into_inner) = None;
+            }
+        }
+
+        *entry
+            .limited
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Some(entry.build());
+        indexed.push(name.into());
+        if indexed.len() >= self.max_classes {
+            let threshold = indexed.iter().map(|name| hits_of(name)).min();
+            self.threshold
+                .store(threshold.unwrap_or_default(), Ordering::Relaxed);
+        }
+        true
+    }
+}
+
+#[derive(Debug)]
+struct ClassEntry<'s> {
+    class: ClassMapping<'s>,
+    /// The members of the class, which are only parsed on first use.
+    members: OnceLock<Members>,
+    /// The members of the class while it is indexed under an [`IndexLimit`],
+    /// which may evict them again.
+    limited: RwLock<Option<Members>>,
+    /// How often the members of the class were looked up under an
+    /// [`IndexLimit`].
+    hits: AtomicU32,
+}
+
+impl Clone for ClassEntry<'_> {
+    fn clone(&self) -> Self {
+        let limited = self.limited.read().unwrap_or_else(PoisonError::into_inner);
+        Self {
+            class: self.class.clone(),
+            members: self.members.clone(),
+            limited: RwLock::new(limited.clone()),
+            hits: AtomicU32::new(self.hits.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl<'s> ClassEntry<'s> {
+    fn new(class: ClassMapping<'s>) -> Self {
+        Self {
+            class,
+            members: OnceLock::new(),
+            limited: RwLock::new(None),
+            hits: AtomicU32::new(0),
+        }
+    }
+
+    /// Parses the members of the class.
+    fn build(&self) -> Members {
+        trace_span!(
+            DEBUG,
+            "proguard.index_class",
+            class = self.class.obfuscated()
+        );
+        build_members(
+            self.class.members_source(),
+            annotate_methods(self.class.members()),
+        )
+    }
+
+    /// Indexes the members of the class, unless they are indexed already.
+    fn index(&self) -> &Members {
+        self.members.get_or_init(|| self.build())
+    }
+
+    /// Returns the members named `method`, indexing the class if necessary.
+    fn members(&self, method: &str) -> Option<MemberList<'_>> {
+        let source = self.class.members_source();
+        find_members(source, self.index(), method).map(Cow::Borrowed)
+    }
+
+    /// Returns a copy of the members named `method`, or `None` if the class
+    /// is not indexed under an [`IndexLimit`].
+    ///
+    /// The members are copied, as they may be evicted at any time.
+    fn limited_members(&self, method: &str) -> Option<Option<MemberList<'static>>> {
+        let limited = self.limited.read().unwrap_or_else(PoisonError::into_inner);
+        let members = limited.as_ref()?;
+        let source = self.class.members_source();
+        Some(find_members(source, members, method).map(|members| Cow::Owned(members.to_vec())))
+    }
+
+    /// Returns the members named `method` by scanning the members block,
+    /// without indexing the class.
+    fn scan(&self, method: &str) -> Option<MemberList<'_>> {
+        let methods = annotate_methods(self.class.members())
+            .into_iter()
+            .filter(|(m, _)| m.obfuscated == method);
+        let members = build_members(self.class.members_source(), methods);
+        (!members.is_empty()).then_some(Cow::Owned(members))
+    }
 }
 
-type MemberIter<'m> = std::slice::Iter<'m, MemberMapping<'m>>;
+/// Returns the members named `method` within the sorted `members`.
+fn find_members<'a>(
+    source: &[u8],
+    members: &'a [(Span, MemberMapping)],
+    method: &str,
+) -> Option<&'a [(Span, MemberMapping)]> {
+    let start = members.partition_point(|(name, _)| resolve(source, *name) < method);
+    let len = members[start..].partition_point(|(name, _)| resolve(source, *name) == method);
+    (len > 0).then(|| &members[start..start + len])
+}
+
+/// Returns the member to remap a frame without a line number with, if all
+/// `members` map to the same original method.
+///
+/// The member has no line mapping, so the remapped frame has no line either.
+fn unique_method(
+    source: &[u8],
+    members: &[(Span, MemberMapping)],
+) -> Option<(Span, MemberMapping)> {
+    let original = |member: &MemberMapping| {
+        let class = member.original_class.map(|class| resolve(source, class));
+        (class, resolve(source, member.original))
+    };
+    let (&(name, first), rest) = members.split_first()?;
+    if rest
+        .iter()
+        .any(|(_, member)| original(member) != original(&first))
+    {
+        return None;
+    }
+    let member = MemberMapping {
+        startline: 0,
+        endline: 0,
+        original_startline: 0,
+        original_endline: None,
+        ..first
+    };
+    Some((name, member))
+}
+
+/// Resolves a [`Span`] within `source` to a string.
+fn resolve(source: &[u8], span: Span) -> &str {
+    // spans always point to previously parsed, and thus valid utf-8.
+    std::str::from_utf8(&source[span.0..span.1]).unwrap_or_default()
+}
+
+/// Parses the method whose original name is at `span` within `source`.
+fn method_at(source: &[u8], span: Span) -> Option<MethodMapping<'_>> {
+    let start = source[..span.0]
+        .iter()
+        .rposition(|&byte| byte == b'\n' || byte == b'\r')
+        .map_or(0, |pos| pos + 1);
+    let (line, _) = split_line(&source[start..]);
+    MethodMapping::try_from(ProguardRecord::try_parse(line).ok()?).ok()
+}
+
+/// Collects the methods of a class, along with whether R8 marked them as synthesized.
+fn annotate_methods<'a, E>(
+    records: impl Iterator<Item = Result<ProguardRecord<'a>, E>>,
+) -> Vec<(MethodMapping<'a>, bool)> {
+    let mut methods = Vec::new();
+    let mut follows_method = false;
+    for record in records {
+        match record {
+            Ok(ProguardRecord::Header { key, value }) => {
+                if follows_method && R8Header::parse(key, value) == Some(R8Header::Synthesized) {
+                    if let Some((_, synthesized)) = methods.last_mut() {
+                        *synthesized = true;
+                    }
+                }
+                continue;
+            }
+            Ok(record) => match MethodMapping::try_from(record).ok() {
+                Some(method) => {
+                    methods.push((method, false));
+                    follows_method = true;
+                }
+                None => follows_method = false,
+            },
+            Err(_) => follows_method = false,
+        }
+    }
+    methods
+}
+
+/// Builds the [`Members`] of a class from its methods, which all borrow from `source`.
+fn build_members<'a>(
+    source: &[u8],
+    methods: impl IntoIterator<Item = (MethodMapping<'a>, bool)>,
+) -> Members {
+    let span = |s: &str| {
+        let start = s.as_ptr() as usize - source.as_ptr() as usize;
+        (start, start + s.len())
+    };
+
+    let mut members: Vec<_> = methods
+        .into_iter()
+        .map(|(method, synthesized)| {
+            // in case the mapping has no line records, we use `0` here.
+            let (startline, endline) =
+                method.line_mapping.as_ref().map_or((0, 0), |line_mapping| {
+                    (line_mapping.startline, line_mapping.endline)
+                });
+            let (original_startline, original_endline) =
+                method.line_mapping.map_or((0, None), |line_mapping| {
+                    match line_mapping.original_startline {
+                        Some(original_startline) => {
+                            (original_startline, line_mapping.original_endline)
+                        }
+                        None => (line_mapping.startline, Some(line_mapping.endline)),
+                    }
+                });
+            let member = MemberMapping {
+                startline,
+                endline,
+                original_class: method.original_class.map(span),
+                original: span(method.original),
+                original_startline,
+                original_endline,
+                synthesized,
+            };
+            (method.obfuscated, member)
+        })
+        .collect();
+    // this is a stable sort, so members with the same name stay in file order.
+    members.sort_by(|a, b| a.0.cmp(b.0));
+
+    members
+        .into_iter()
+        .map(|(name, member)| (span(name), member))
+        .collect()
+}
+
+/// Counts the lines which might be class lines.
+fn count_class_lines(mut slice: &[u8]) -> usize {
+    let mut count = 0;
+    while !slice.is_empty() {
+        let (line, rest) = split_line(slice);
+        slice = rest;
+        if !matches!(line.first(), None | Some(b' ') | Some(b'\t') | Some(b'#')) {
+            count += 1;
+        }
+    }
+    count
+}
+
+/// The minimum length in bytes of a chunk indexed by [`ProguardMapper::new_parallel`].
+const MIN_CHUNK_LEN: usize = 1 << 20;
+
+/// Splits `source` into at most `chunks` ranges of about the same length.
+///
+/// Each range but the first starts at a class line, so every class and its
+/// members are within a single range. Returns the boundaries of the ranges,
+/// starting with `0` and ending with the length of `source`.
+fn class_boundaries(source: &[u8], text: &str, chunks: usize) -> Vec<usize> {
+    let mut boundaries = vec![0];
+    for i in 1..chunks {
+        let target = source.len() / chunks * i;
+        let mut offset = *boundaries.last().unwrap_or(&0);
+        if offset >= target {
+            continue;
+        }
+
+        // skip to the start of the first line at or after `target`.
+        offset = match source[target - 1] {
+            b'\n' | b'\r' => target,
+            _ => match find_line_terminator(&source[target..]) {
+                Some(pos) => target + pos + 1,
+                None => break,
+            },
+        };
+
+        // and then to the next line which is a class line.
+        let mut slice = &source[offset..];
+        while !slice.is_empty() {
+            let start = source.len() - slice.len();
+            let (line, rest) = split_line(slice);
+            slice = rest;
+            if !matches!(line.first(), None | Some(b' ') | Some(b'\t') | Some(b'#'))
+                && matches!(
+                    parse_mapping(&text[start..start + line.len()]),
+                    Ok(ProguardRecord::Class { .. })
+                )
+            {
+                boundaries.push(start);
+                break;
+            }
+        }
+    }
+    boundaries.push(source.len());
+    boundaries.dedup();
+    boundaries
+}
+
+/// Indexes the classes whose class lines lie within `range` of `source`.
+///
+/// `text` is `source` validated as utf-8. Returns whether any member has line info.
+fn index_range<'s>(
+    classes: &mut HashMap<&'s str, ClassEntry<'s>>,
+    source: &'s [u8],
+    text: &'s str,
+    range: Range<usize>,
+) -> bool {
+    let mut methods = Vec::new();
+    let mut current = None;
+    // whether the previous line was a method, which R8 metadata applies to.
+    let mut follows_method = false;
+    let mut has_line_info = false;
+
+    let mut slice = &source[range.clone()];
+    while !slice.is_empty() {
+        let start = range.end - slice.len();
+        let (line, rest) = split_line(slice);
+        slice = rest;
+
+        // the whole file is valid utf-8, and lines are split at ascii characters.
+        let line = &text[start..start + line.len()];
+        match line.as_bytes().first() {
+            None => {}
+            Some(b'#') => {
+                if let Ok(ProguardRecord::Header { key, value }) = parse_mapping(line) {
+                    if follows_method && R8Header::parse(key, value) == Some(R8Header::Synthesized)
+                    {
+                        if let Some((_, synthesized)) = methods.last_mut() {
+                            *synthesized = true;
+                        }
+                    }
+                }
+                // the headers of a concatenated mapping are not part of the last class.
+                if current.is_some() && is_segment_header(line.as_bytes()) {
+                    if let Some(class) = current.take() {
+                        insert_eager(classes, source, class, start, &mut methods);
+                    }
+                    follows_method = false;
+                }
+            }
+            Some(b' ') | Some(b'\t') => {
+                if current.is_none() {
+                    continue;
+                }
+                let method = parse_mapping(line)
+                    .ok()
+                    .and_then(|record| MethodMapping::try_from(record).ok());
+                follows_method = method.is_some();
+                if let Some(method) = method {
+                    has_line_info |= method.line_mapping.is_some();
+                    methods.push((method, false));
+                }
+            }
+            Some(_) => {
+                if let Ok(ProguardRecord::Class {
+                    original,
+                    obfuscated,
+                }) = parse_mapping(line)
+                {
+                    if let Some(class) = current.take() {
+                        insert_eager(classes, source, class, start, &mut methods);
+                    }
+                    current = Some((original, obfuscated, start + line.len()));
+                }
+                follows_method = false;
+            }
+        }
+    }
+    if let Some(class) = current {
+        insert_eager(classes, source, class, range.end, &mut methods);
+    }
+    has_line_info
+}
+
+/// Inserts a fully indexed class, whose members end at `end`, into `classes`.
+fn insert_eager<'s>(
+    classes: &mut HashMap<&'s str, ClassEntry<'s>>,
+    source: &'s [u8],
+    (original, obfuscated, start): (&'s str, &'s str, usize),
+    end: usize,
+    methods: &mut Vec<(MethodMapping<'s>, bool)>,
+) {
+    let class = ClassMapping::new(original, obfuscated, &source[start..end]);
+    let members = build_members(class.members_source(), methods.drain(..));
+    classes.insert(
+        obfuscated,
+        ClassEntry {
+            class,
+            members: OnceLock::from(members),
+            limited: RwLock::new(None),
+            hits: AtomicU32::new(0),
+        },
+    );
+}
+
+/// How trustworthy a remapped frame is.
+///
+/// Levels are ordered from the least to the most trustworthy, so they can be
+/// compared against a threshold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// The class of the frame was guessed, see [`ProguardMapper::remap_frame_by_method`].
+    Heuristic,
+    /// The member matches any line, as it has no line mapping or a catch-all
+    /// range such as `0:65535`.
+    CatchAll,
+    /// The line falls within a range of minified lines.
+    Range,
+    /// The member maps exactly the line of the frame.
+    Exact,
+}
+
+impl Confidence {
+    /// The confidence of a match with the minified line range `startline:endline`.
+    pub(crate) fn of_range(startline: usize, endline: usize) -> Self {
+        if endline == 0 || (startline == 0 && endline >= 65535) {
+            Confidence::CatchAll
+        } else if startline == endline {
+            Confidence::Exact
+        } else {
+            Confidence::Range
+        }
+    }
+}
+
+/// The members matching a frame, which are either indexed or scanned.
+type MemberList<'m> = Cow<'m, [(Span, MemberMapping)]>;
+
+/// Picks one of the candidate methods of an ambiguous frame, see
+/// [`ProguardMapper::with_disambiguator`].
+type Disambiguator = dyn Fn(&StackFrame<'_>, &[MethodMapping<'_>]) -> Option<usize> + Send + Sync;
+
+/// A shared [`Disambiguator`], which keeps [`ProguardMapper`] cloneable.
+#[derive(Clone)]
+struct DisambiguatorFn(Arc<Disambiguator>);
+
+impl fmt::Debug for DisambiguatorFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("DisambiguatorFn(..)")
+    }
+}
 
 /// An Iterator over remapped StackFrames.
 #[derive(Clone, Debug, Default)]
 pub struct RemappedFrameIter<'m> {
-    inner: Option<(StackFrame<'m>, MemberIter<'m>)>,
+    /// The frame, the source of the members, the members, and the span of
+    /// the narrowest line range matching the frame.
+    inner: Option<(StackFrame<'m>, &'m [u8], MemberList<'m>, Option<usize>)>,
+    /// The position of the next member.
+    position: usize,
+    /// Whether the class of the frame was guessed.
+    heuristic: bool,
 }
 
 impl<'m> RemappedFrameIter<'m> {
     fn empty() -> Self {
-        Self { inner: None }
+        Self::default()
     }
-    fn members(frame: StackFrame<'m>, members: MemberIter<'m>) -> Self {
+    fn members(frame: StackFrame<'m>, source: &'m [u8], mut members: MemberList<'m>) -> Self {
+        if frame.line == 0 && !members.iter().any(|(_, member)| member.matches(0)) {
+            if let Some(member) = unique_method(source, &members) {
+                members = Cow::Owned(vec![member]);
+            }
+        }
+        // catch-all ranges like `0:65535` match every line, so prefer the
+        // most specific range if more than one matches.
+        let narrowest = members
+            .iter()
+            .filter(|(_, member)| member.matches(frame.line) && member.endline > 0)
+            .map(|(_, member)| member.endline - member.startline)
+            .min();
         Self {
-            inner: Some((frame, members)),
+            inner: Some((frame, source, members, narrowest)),
+            position: 0,
+            heuristic: false,
+        }
+    }
+
+    /// Narrows the remapped frames down to the alternative picked by `pick`.
+    ///
+    /// Each alternative is an inlined call stack sharing the same minified
+    /// line range, and is represented by its outermost method. The frames are
+    /// left as-is unless there is more than one alternative, and `pick`
+    /// returns the index of one of them.
+    fn disambiguate(mut self, frame: &StackFrame<'_>, pick: &Disambiguator) -> Self {
+        let mut alternatives: Vec<Vec<usize>> = Vec::new();
+        let mut range = None;
+        let mut remapped = self.clone();
+        while let Some((_, member)) = remapped.next_member() {
+            let member_range = (member.startline, member.endline);
+            if member.endline == 0 || range != Some(member_range) {
+                alternatives.push(Vec::new());
+            }
+            range = Some(member_range);
+            if let Some(alternative) = alternatives.last_mut() {
+                alternative.push(remapped.position - 1);
+            }
+        }
+        if alternatives.len() < 2 {
+            return self;
         }
+
+        let (_, source, members, _) = match self.inner.as_mut() {
+            Some(inner) => inner,
+            None => return self,
+        };
+        let candidates: Vec<_> = alternatives
+            .iter()
+            .filter_map(|alternative| {
+                let (_, member) = members[*alternative.last()?];
+                method_at(source, member.original)
+            })
+            .collect();
+        if candidates.len() != alternatives.len() {
+            return self;
+        }
+        if let Some(alternative) = pick(frame, &candidates).and_then(|i| alternatives.get(i)) {
+            let picked = alternative.iter().map(|&index| members[index]).collect();
+            *members = Cow::Owned(picked);
+        }
+        self
+    }
+
+    /// Returns the next remapped frame, along with how trustworthy it is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{Confidence, ProguardMapper, StackFrame};
+    ///
+    /// let mapper = ProguardMapper::from(
+    ///     "com.example.Foo -> a:\n    1:1:void bar():10 -> a\n    2:5:void baz():20:23 -> a",
+    /// );
+    ///
+    /// let mut remapped = mapper.remap_frame(&StackFrame::new("a", "a", 3));
+    /// assert_eq!(
+    ///     remapped.next_with_confidence(),
+    ///     Some((StackFrame::new("com.example.Foo", "baz", 21), Confidence::Range))
+    /// );
+    /// ```
+    pub fn next_with_confidence(&mut self) -> Option<(StackFrame<'m>, Confidence)> {
+        let (frame, member) = self.next_member()?;
+        let confidence = if self.heuristic {
+            Confidence::Heuristic
+        } else {
+            Confidence::of_range(member.startline, member.endline)
+        };
+        Some((frame, confidence))
     }
 }
 
-impl<'m> Iterator for RemappedFrameIter<'m> {
-    type Item = StackFrame<'m>;
-    fn next(&mut self) -> Option<Self::Item> {
-        let (frame, ref mut members) = self.inner.as_mut()?;
+impl<'m> RemappedFrameIter<'m> {
+    /// Returns the next remapped frame, along with the member it was remapped with.
+    fn next_member(&mut self) -> Option<(StackFrame<'m>, MemberMapping)> {
+        let (frame, source, members, narrowest) = self.inner.as_mut()?;
 
-        for member in members {
+        while let Some(&(_, member)) = members.get(self.position) {
+            self.position += 1;
             // skip any members which do not match our the frames line
-            if member.endline > 0 && (frame.line < member.startline || frame.line > member.endline)
-            {
+            if !member.matches(frame.line) {
+                continue;
+            }
+            if member.endline > 0 && Some(member.endline - member.startline) != *narrowest {
                 continue;
             }
             // parents of inlined frames don’t have an `endline`, and
@@ -67,30 +729,213 @@ impl<'m> Iterator for RemappedFrameIter<'m> {
                 frame.file
             };
             let class = match member.original_class {
-                Some(class) => class,
+                Some(class) => resolve(source, class),
                 _ => frame.class,
             };
-            return Some(StackFrame {
+            let remapped = StackFrame {
                 class,
-                method: member.original,
+                method: resolve(source, member.original),
                 file,
                 line,
-            });
+                module: frame.module,
+            };
+            return Some((remapped, member));
         }
 
         None
     }
 }
 
+impl<'m> Iterator for RemappedFrameIter<'m> {
+    type Item = StackFrame<'m>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_member().map(|(frame, _)| frame)
+    }
+}
+
 impl FusedIterator for RemappedFrameIter<'_> {}
 
+/// A remapped frame along with details about how it was remapped.
+///
+/// Created by [`ProguardMapper::remap_frame_detailed`]. Contrary to the
+/// formatted output of [`ProguardMapper::remap_stacktrace`], this can be
+/// converted into other event models without parsing it again.
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct RemappedFrame<'s> {
+    /// The original class of the frame.
+    pub class: &'s str,
+    /// The original method of the frame.
+    pub method: &'s str,
+    /// The file of the frame, which is not known for methods that were
+    /// inlined from another class.
+    pub file: Option<&'s str>,
+    /// The original line of the frame, or `0` if it is not known.
+    pub line: usize,
+    /// The remapped frame this frame was inlined into, which is its caller.
+    ///
+    /// This is `None` for the outermost frame of an inlined call stack, which
+    /// is the method that the obfuscated frame actually belongs to.
+    pub inlined_into: Option<StackFrame<'s>>,
+    /// How trustworthy the remapped frame is.
+    pub confidence: Confidence,
+}
+
+/// A frame in the shape of a Sentry Java SDK event payload.
+///
+/// See [`ProguardMapper::remap_sentry_frame`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct SentryFrame<'s> {
+    /// The fully-qualified class, which is the `module` of the payload.
+    pub module: &'s str,
+    /// The method, which is the `function` of the payload.
+    pub function: &'s str,
+    /// The source file of the class, if it is known from R8 metadata.
+    pub filename: Option<&'s str>,
+    /// The line number, or `None` if it is not known.
+    pub lineno: Option<usize>,
+}
+
+/// Options for [`ProguardMapper::remap_stacktrace_with_options`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RetraceOptions {
+    /// Mimic the output of the R8 `retrace` tool.
+    ///
+    /// This differs from the default output in these ways:
+    ///
+    /// - Frames of members which R8 marked as synthesized are omitted from an
+    ///   inlined call stack, unless all of its frames are synthesized.
+    /// - If a frame is ambiguous, each alternative is listed, with all but the
+    ///   first prefixed with `<OR> `.
+    /// - File names are taken from R8 `sourceFile` metadata, or inferred from
+    ///   the outermost class name, unless they are `Unknown Source`,
+    ///   `Native Method` or `Unknown`.
+    /// - Frames without a line number are written as `(File.java)`, instead of
+    ///   with a line number of `0`.
+    pub r8_compat: bool,
+    /// Strip compiler generated suffixes from method names which can not be
+    /// remapped.
+    ///
+    /// This turns names such as `$r8$backportedMethods$utility$Objects$1$hashCode`
+    /// into `hashCode`. Accessors such as `access$000` are kept, as their
+    /// suffix tells them apart. Methods which are remapped are written as
+    /// they appear in the mapping.
+    pub strip_synthetic_suffixes: bool,
+    /// Append the obfuscated frame to every line it was remapped to.
+    ///
+    /// Remapped lines end in a comment such as `// was a.b.c(SourceFile:1)`,
+    /// which helps debugging which members of the mapping a frame resolves to.
+    /// Frames which can not be remapped are written unchanged.
+    pub annotate_obfuscated: bool,
+    /// Mark frames which can not be remapped.
+    ///
+    /// Frames whose class or method is not part of the mapping are written
+    /// unchanged, and end in a `// not found in mapping` comment, so they can
+    /// be told apart from frames which were remapped. This includes frames of
+    /// classes which were never obfuscated, such as those of the JDK.
+    pub annotate_unmapped: bool,
+    /// Replace the file names of remapped frames.
+    ///
+    /// Obfuscators commonly rewrite the `SourceFile` attribute, for example
+    /// with `-renamesourcefileattribute SourceFile`, so the file name of an
+    /// obfuscated frame is meaningless. With this option, remapped frames take
+    /// their file name from R8 `sourceFile` metadata, or infer it from the
+    /// outermost class name, just like in [`r8_compat`](Self::r8_compat) mode.
+    ///
+    /// This is disabled by default, so that
+    /// [`ProguardMapper::remap_stacktrace`] keeps writing the file names of
+    /// the input, as it always did.
+    pub infer_file_names: bool,
+    /// Resolve class names which were cut off, as logcat truncates long lines.
+    ///
+    /// A class which is not part of the mapping is treated as the prefix of an
+    /// obfuscated class name, and remapped if exactly one class matches it.
+    /// Frames whose line was cut off after the class are remapped as far as
+    /// they go, such as `at a.b.c(SourceFi`.
+    pub match_truncated_classes: bool,
+    /// Remap obfuscated class names wherever they occur in the input.
+    ///
+    /// By default, only the classes of frames and exceptions are remapped.
+    /// With this option, exception messages and all other lines are remapped
+    /// like [`ProguardMapper::remap_text`] as well, such as the class in
+    /// `- locked <0x0f3c> (a a.b.c)`.
+    pub remap_class_names: bool,
+    /// How the source location of remapped frames is written.
+    ///
+    /// Frames which can not be remapped are written unchanged.
+    pub source_location: SourceLocation,
+    /// Remap lines of `adb logcat` output, or of the Android Studio logcat window.
+    ///
+    /// Logcat prefixes every line with metadata such as a timestamp, the
+    /// process and thread ids, the log level and the tag. With this option,
+    /// such a prefix is split off before the line is remapped, and written
+    /// unchanged before every line that it is remapped to.
+    pub logcat_prefixes: bool,
+}
+
+/// How [`ProguardMapper::remap_stacktrace_with_options`] writes the source
+/// location of remapped frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SourceLocation {
+    /// The file and line, such as `(Foo.java:123)`.
+    ///
+    /// In [`r8_compat`](RetraceOptions::r8_compat) mode, unknown lines are
+    /// omitted, such as `(Foo.java)`.
+    #[default]
+    FileAndLine,
+    /// Only the file, such as `(Foo.kt)`.
+    File,
+    /// `(Unknown Source)`, as the JVM writes frames without debug info.
+    UnknownSource,
+    /// No source location at all, such as `at com.example.Foo.bar`.
+    Omitted,
+}
+
+/// Reports which parts of a [`StackTrace`] can be remapped.
+///
+/// Created by [`ProguardMapper::coverage`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TraceCoverage<'t> {
+    /// Frames that resolve to at least one original frame.
+    pub resolved: Vec<StackFrame<'t>>,
+    /// Frames that can not be remapped.
+    pub unresolved: Vec<StackFrame<'t>>,
+    /// Classes of unresolved frames which do not appear in the mapping at
+    /// all, in order of first occurrence.
+    pub missing_classes: Vec<&'t str>,
+}
+
+impl TraceCoverage<'_> {
+    /// Whether every frame of the trace can be remapped.
+    pub fn is_complete(&self) -> bool {
+        self.unresolved.is_empty()
+    }
+}
+
 /// A Proguard Remapper.
 ///
 /// This can remap class names, stack frames one at a time, or the complete
 /// raw stacktrace.
 #[derive(Clone, Debug)]
 pub struct ProguardMapper<'s> {
-    classes: HashMap<&'s str, ClassMapping<'s>>,
+    classes: HashMap<&'s str, ClassEntry<'s>>,
+    /// The class defining each obfuscated method name, or `None` if more than
+    /// one class does. Only built by [`with_method_index`](Self::with_method_index).
+    methods: Option<HashMap<&'s str, Option<&'s str>>>,
+    /// Limits how many classes have their members indexed. Only set by
+    /// [`with_hot_index_limit`](Self::with_hot_index_limit).
+    index_limit: Option<IndexLimit>,
+    /// Picks one of the alternatives of ambiguous frames. Only set by
+    /// [`with_disambiguator`](Self::with_disambiguator).
+    disambiguator: Option<DisambiguatorFn>,
+    /// Whether any member has line info, which is known up-front if the
+    /// mapper was created by [`new_eager`](Self::new_eager).
+    has_line_info: OnceLock<bool>,
+    /// The obfuscated class names in sorted order, which are searched for
+    /// truncated class names. Only built for the first truncated class, and
+    /// owned so that the mapper stays covariant over `'s`.
+    sorted_classes: OnceLock<BTreeSet<Box<str>>>,
 }
 
 impl<'s> From<&'s str> for ProguardMapper<'s> {
@@ -102,102 +947,742 @@ impl<'s> From<&'s str> for ProguardMapper<'s> {
 
 impl<'s> ProguardMapper<'s> {
     /// Create a new ProguardMapper.
+    ///
+    /// This only indexes the class names up-front. The members of a class are
+    /// parsed the first time the class is used for remapping a frame.
     pub fn new(mapping: ProguardMapping<'s>) -> Self {
-        let mut classes = HashMap::new();
-        let mut class = ClassMapping {
-            original: "",
-            obfuscated: "",
-            members: BTreeMap::new(),
+        Self::from_classes(mapping.classes())
+    }
+
+    /// Creates a ProguardMapper from `classes`, which are indexed lazily like
+    /// in [`new`](Self::new).
+    pub(crate) fn from_classes<I: IntoIterator<Item = ClassMapping<'s>>>(classes: I) -> Self {
+        trace_span!(DEBUG, "proguard.index");
+        let classes: HashMap<_, _> = classes
+            .into_iter()
+            .map(|class| (class.obfuscated(), ClassEntry::new(class)))
+            .collect();
+        trace_event!(DEBUG, classes = classes.len(), "indexed class names");
+
+        Self {
+            classes,
+            methods: None,
+            index_limit: None,
+            disambiguator: None,
+            has_line_info: OnceLock::new(),
+            sorted_classes: OnceLock::new(),
+        }
+    }
+
+    /// Create a new ProguardMapper, eagerly indexing all classes and members.
+    ///
+    /// Contrary to [`new`](Self::new), this parses the complete file up-front.
+    /// It is optimized for very large mappings, from which many classes are
+    /// queried: A first pass counts the classes so the index is allocated only
+    /// once, and the members of each class are stored in a single allocation.
+    /// The file is also validated as utf-8 only once, instead of line by line.
+    pub fn new_eager(mapping: ProguardMapping<'s>) -> Self {
+        trace_span!(DEBUG, "proguard.index_eager");
+        let source = mapping.source();
+        let text = match std::str::from_utf8(source) {
+            Ok(text) => text,
+            Err(_) => {
+                // fall back to validating, and skipping invalid lines, one by one.
+                let mapper = Self::new(mapping);
+                for entry in mapper.classes.values() {
+                    entry.index();
+                }
+                return mapper;
+            }
         };
 
-        for record in mapping.iter().filter_map(Result::ok) {
-            match record {
-                ProguardRecord::Class {
-                    original,
-                    obfuscated,
-                } => {
-                    if !class.original.is_empty() {
-                        classes.insert(class.obfuscated, class);
-                    }
-                    class = ClassMapping {
-                        original,
-                        obfuscated,
-                        members: BTreeMap::new(),
-                    }
+        let mut classes = HashMap::with_capacity(count_class_lines(source));
+        let has_line_info = index_range(&mut classes, source, text, 0..source.len());
+        trace_event!(
+            DEBUG,
+            classes = classes.len(),
+            "indexed classes and members"
+        );
+
+        Self {
+            classes,
+            methods: None,
+            index_limit: None,
+            disambiguator: None,
+            has_line_info: OnceLock::from(has_line_info),
+            sorted_classes: OnceLock::new(),
+        }
+    }
+
+    /// Create a new ProguardMapper, eagerly indexing all classes and members
+    /// on all available cores.
+    ///
+    /// This is the same as [`new_eager`](Self::new_eager), except that the
+    /// file is split into chunks at class lines, which are indexed in parallel
+    /// and then merged. Small mappings are indexed on a single thread, as
+    /// spawning threads would outweigh the gains.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, ProguardMapping, StackFrame};
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    1:1:void run():10 -> a");
+    /// let mapper = ProguardMapper::new_parallel(mapping);
+    /// let frame = StackFrame::new("a", "a", 1);
+    /// assert_eq!(mapper.remap_frame(&frame).next().unwrap().method(), "run");
+    /// ```
+    pub fn new_parallel(mapping: ProguardMapping<'s>) -> Self {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunks = threads.min(mapping.source().len() / MIN_CHUNK_LEN);
+        Self::new_chunked(mapping, chunks)
+    }
+
+    /// Indexes `mapping` like [`new_eager`](Self::new_eager), split into at
+    /// most `chunks` chunks.
+    ///
+    /// The chunks are indexed on at most as many threads as there are cores,
+    /// each of which indexes a run of consecutive chunks.
+    fn new_chunked(mapping: ProguardMapping<'s>, chunks: usize) -> Self {
+        let source = mapping.source();
+        let text = match std::str::from_utf8(source) {
+            Ok(text) if chunks > 1 => text,
+            _ => return Self::new_eager(mapping),
+        };
+
+        trace_span!(DEBUG, "proguard.index_parallel", chunks);
+        let boundaries = class_boundaries(source, text, chunks);
+        let ranges: Vec<_> = boundaries.windows(2).map(|w| w[0]..w[1]).collect();
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let per_thread = ranges.len().div_ceil(threads.min(ranges.len()));
+        let indexed: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = ranges
+                .chunks(per_thread)
+                .map(|ranges| {
+                    scope.spawn(move || {
+                        ranges
+                            .iter()
+                            .map(|range| {
+                                let mut classes = HashMap::with_capacity(count_class_lines(
+                                    &source[range.clone()],
+                                ));
+                                let has_line_info =
+                                    index_range(&mut classes, source, text, range.clone());
+                                (classes, has_line_info)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("indexing thread panicked"))
+                .collect()
+        });
+
+        // merge in file order, so later duplicates of a class win as in `new_eager`.
+        let mut classes = HashMap::with_capacity(indexed.iter().map(|(c, _)| c.len()).sum());
+        let mut has_line_info = false;
+        for (chunk, chunk_has_line_info) in indexed {
+            classes.extend(chunk);
+            has_line_info |= chunk_has_line_info;
+        }
+        trace_event!(
+            DEBUG,
+            classes = classes.len(),
+            "indexed classes and members"
+        );
+
+        Self {
+            classes,
+            methods: None,
+            index_limit: None,
+            disambiguator: None,
+            has_line_info: OnceLock::from(has_line_info),
+            sorted_classes: OnceLock::new(),
+        }
+    }
+
+    /// Whether any member of a class has line info.
+    ///
+    /// This is the same as [`ProguardMapping::has_line_info`], but it is known
+    /// without scanning the mapping again if the mapper was created by
+    /// [`new_eager`](Self::new_eager). Otherwise, it is computed on first use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, ProguardMapping};
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    1:1:void run():10 -> a");
+    /// assert!(ProguardMapper::new_eager(mapping).has_line_info());
+    /// ```
+    pub fn has_line_info(&self) -> bool {
+        *self.has_line_info.get_or_init(|| {
+            self.classes.values().any(|entry| {
+                entry
+                    .class
+                    .methods()
+                    .any(|method| method.line_mapping.is_some())
+            })
+        })
+    }
+
+    /// Indexes the obfuscated method names across all classes.
+    ///
+    /// This parses the members of every class, and enables guessing the class
+    /// of frames whose class is missing from the mapping, see
+    /// [`remap_frame_by_method`](Self::remap_frame_by_method). Methods which
+    /// are not renamed are not indexed.
+    ///
+    /// Stack traces are remapped using the index as well, and every line of a
+    /// frame remapped this way ends in a `// guessed by method name` comment.
+    pub fn with_method_index(mut self) -> Self {
+        let mut methods = HashMap::new();
+        for (obfuscated, entry) in &self.classes {
+            for method in entry.class.methods() {
+                if method.obfuscated == method.original {
+                    continue;
                 }
-                ProguardRecord::Method {
-                    original,
-                    obfuscated,
-                    original_class,
-                    line_mapping,
-                    ..
-                } => {
-                    // in case the mapping has no line records, we use `0` here.
-                    let (startline, endline) =
-                        line_mapping.as_ref().map_or((0, 0), |line_mapping| {
-                            (line_mapping.startline, line_mapping.endline)
-                        });
-                    let (original_startline, original_endline) =
-                        line_mapping.map_or((0, None), |line_mapping| {
-                            match line_mapping.original_startline {
-                                Some(original_startline) => {
-                                    (original_startline, line_mapping.original_endline)
-                                }
-                                None => (line_mapping.startline, Some(line_mapping.endline)),
-                            }
-                        });
-                    let members = class.members.entry(obfuscated).or_insert_with(Vec::new);
-                    members.push(MemberMapping {
-                        startline,
-                        endline,
-                        original_class,
-                        original,
-                        original_startline,
-                        original_endline,
-                    });
+                methods
+                    .entry(method.obfuscated)
+                    .and_modify(|class: &mut Option<&str>| {
+                        if *class != Some(*obfuscated) {
+                            *class = None;
+                        }
+                    })
+                    .or_insert(Some(*obfuscated));
+            }
+        }
+        self.methods = Some(methods);
+        self
+    }
+
+    /// Limits the index to the members of the `max_classes` classes which
+    /// are used most often.
+    ///
+    /// By default, the members of every class used for remapping are parsed
+    /// once and kept in memory. With a limit, the mapper counts how often
+    /// each class is used, and only classes which are used repeatedly are
+    /// indexed. Once `max_classes` classes are indexed, a class is only
+    /// indexed if it has been used more often than the least used indexed
+    /// class, which is evicted in turn. The members of all other classes
+    /// are scanned directly in the mapping on every lookup, which is slower
+    /// but does not retain any memory.
+    ///
+    /// Remapping results are the same with or without a limit. Classes that
+    /// are already indexed, such as by [`new_eager`](Self::new_eager), are
+    /// never evicted, and count towards the limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, StackFrame};
+    ///
+    /// let mapper = ProguardMapper::from("a.A -> a:\n    1:1:void run():10:10 -> b")
+    ///     .with_hot_index_limit(0);
+    ///
+    /// let frame = StackFrame::new("a", "b", 1);
+    /// let remapped = mapper.remap_frame(&frame).next().unwrap();
+    /// assert_eq!((remapped.class(), remapped.method()), ("a.A", "run"));
+    /// ```
+    pub fn with_hot_index_limit(mut self, max_classes: usize) -> Self {
+        let mut indexed = 0;
+        for entry in self.classes.values_mut() {
+            if entry.members.get().is_some() {
+                indexed += 1;
+            }
+            *entry.hits.get_mut() = 0;
+            *entry
+                .limited
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner) = None;
+        }
+        self.index_limit = Some(IndexLimit::new(max_classes.saturating_sub(indexed)));
+        self
+    }
+
+    /// Resolves ambiguous frames using `pick`.
+    ///
+    /// A frame is ambiguous if it matches more than one method of its class,
+    /// such as overloads without line info which share an obfuscated name.
+    /// Whenever a frame is remapped, `pick` is called with the obfuscated
+    /// frame and the candidate methods, in file order. For inlined call
+    /// stacks, the candidate is the outermost method.
+    ///
+    /// If `pick` returns the index of a candidate, only the frames of that
+    /// candidate are remapped. If it returns `None`, all candidates are
+    /// remapped, as they are without a disambiguator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, StackFrame};
+    ///
+    /// let mapper = ProguardMapper::from(
+    ///     "com.example.Foo -> a:\n    void bar(int) -> a\n    void baz(long) -> a",
+    /// )
+    /// .with_disambiguator(|_frame, candidates| {
+    ///     candidates.iter().position(|method| method.arguments == "long")
+    /// });
+    ///
+    /// let remapped: Vec<_> = mapper.remap_frame(&StackFrame::new("a", "a", 0)).collect();
+    /// assert_eq!(remapped, vec![StackFrame::new("com.example.Foo", "baz", 0)]);
+    /// ```
+    pub fn with_disambiguator<F>(mut self, pick: F) -> Self
+    where
+        F: Fn(&StackFrame<'_>, &[MethodMapping<'_>]) -> Option<usize> + Send + Sync + 'static,
+    {
+        self.disambiguator = Some(DisambiguatorFn(Arc::new(pick)));
+        self
+    }
+
+    /// Indexes the members of `classes` up-front.
+    ///
+    /// The members of a class are otherwise parsed the first time it is used
+    /// for remapping, which adds latency to that lookup. Warming the classes
+    /// which are known to be hot, such as those of the most frequent crashes,
+    /// avoids these spikes after a mapper was created. With an
+    /// [index limit](Self::with_hot_index_limit), warmed classes are indexed
+    /// without having to be used repeatedly first, as long as the limit is
+    /// not reached. They count towards the limit, and are evicted like any
+    /// other class once classes which are used more often come along.
+    ///
+    /// Returns the number of `classes` which were found in the mapping.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapper;
+    ///
+    /// let mapper = ProguardMapper::from("a.A -> a:\n    1:1:void run():10:10 -> b\nb.B -> b:");
+    /// assert_eq!(mapper.warm(&["a", "b", "c"]), 2);
+    /// ```
+    pub fn warm(&self, classes: &[&str]) -> usize {
+        let mut found = 0;
+        for class in classes {
+            let entry = match self.classes.get(class) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            found += 1;
+            match self.index_limit {
+                Some(ref index_limit) if entry.members.get().is_none() => {
+                    index_limit.promote(&self.classes, entry, 0, false);
+                }
+                _ => {
+                    entry.index();
+                }
+            }
+        }
+        found
+    }
+
+    /// Remaps an obfuscated Class.
+    ///
+    /// This works on the fully-qualified name of the class, with its complete
+    /// module prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = r#"android.arch.core.executor.ArchTaskExecutor -> a.a.a.a.c:"#;
+    /// let mapper = proguard::ProguardMapper::from(mapping);
+    ///
+    /// let mapped = mapper.remap_class("a.a.a.a.c");
+    /// assert_eq!(mapped, Some("android.arch.core.executor.ArchTaskExecutor"));
+    /// ```
+    pub fn remap_class(&'s self, class: &str) -> Option<&'s str> {
+        self.classes.get(class).map(|entry| entry.class.original())
+    }
+
+    /// Remaps a single Stackframe.
+    ///
+    /// Returns zero or more [`StackFrame`]s, based on the information in
+    /// the proguard mapping. This can return more than one frame in the case
+    /// of inlined functions. In that case, frames are sorted top to bottom,
+    /// which is the order in which they appear in the mapping file.
+    pub fn remap_frame(&'s self, frame: &StackFrame<'s>) -> RemappedFrameIter<'s> {
+        trace_span!(
+            TRACE,
+            "proguard.remap_frame",
+            class = frame.class,
+            method = frame.method
+        );
+        if let Some(entry) = self.classes.get(frame.class) {
+            let members = match self.index_limit {
+                Some(ref index_limit) if entry.members.get().is_none() => {
+                    index_limit.members(&self.classes, entry, frame.method)
                 }
-                _ => {}
+                _ => entry.members(frame.method),
+            };
+            if let Some(members) = members {
+                let original = StackFrame {
+                    class: entry.class.original(),
+                    ..frame.clone()
+                };
+                let remapped =
+                    RemappedFrameIter::members(original, entry.class.members_source(), members);
+                return match self.disambiguator {
+                    Some(DisambiguatorFn(ref pick)) => remapped.disambiguate(frame, pick.as_ref()),
+                    None => remapped,
+                };
+            }
+        }
+        trace_event!(TRACE, "no mapping for frame");
+        RemappedFrameIter::empty()
+    }
+
+    /// Remaps a batch of frames in parallel, on the global rayon thread pool.
+    ///
+    /// This returns the same frames as [`remap_frame`](Self::remap_frame) for
+    /// each of `frames`, in the same order. It is meant for bulk jobs which
+    /// remap many stored events at once. Classes are still indexed lazily,
+    /// by whichever thread first needs them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, StackFrame};
+    ///
+    /// let mapper = ProguardMapper::from("a.A -> a:\n    1:1:void run():10 -> a");
+    /// let frames = [StackFrame::new("a", "a", 1), StackFrame::new("b", "a", 1)];
+    ///
+    /// let remapped = mapper.par_remap_frames(&frames);
+    /// assert_eq!(remapped[0], vec![StackFrame::new("a.A", "run", 10)]);
+    /// assert!(remapped[1].is_empty());
+    /// ```
+    #[cfg(feature = "rayon")]
+    pub fn par_remap_frames(&'s self, frames: &[StackFrame<'s>]) -> Vec<Vec<StackFrame<'s>>> {
+        use rayon::prelude::*;
+
+        frames
+            .par_iter()
+            .map(|frame| self.remap_frame(frame).collect())
+            .collect()
+    }
+
+    /// Remaps a frame identified by a [`FrameKey`], such as a tuple of class,
+    /// method and line number.
+    ///
+    /// This is the same as [`remap_frame`](Self::remap_frame), without having
+    /// to construct a [`StackFrame`] first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, StackFrame};
+    ///
+    /// let mapper = ProguardMapper::from("com.example.Foo -> a.a.a:\n    1:1:void bar():42 -> b");
+    /// let expected = vec![StackFrame::new("com.example.Foo", "bar", 42)];
+    ///
+    /// assert_eq!(mapper.lookup(("a.a.a", "b", Some(1))).collect::<Vec<_>>(), expected);
+    /// assert_eq!(mapper.lookup(("a.a.a", "b", 1)).collect::<Vec<_>>(), expected);
+    /// assert_eq!(mapper.lookup(("a.a.a", "c")).count(), 0);
+    /// ```
+    pub fn lookup<K: FrameKey<'s>>(&'s self, key: K) -> RemappedFrameIter<'s> {
+        self.remap_frame(&key.into_frame())
+    }
+
+    /// Remaps a single Stackframe into [`RemappedFrame`]s.
+    ///
+    /// This yields the same frames as [`remap_frame`](Self::remap_frame),
+    /// along with whether they were inlined and how trustworthy they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{Confidence, ProguardMapper, StackFrame};
+    ///
+    /// let mapper = ProguardMapper::from(
+    ///     r#"com.example.MainFragment -> a.a:
+    ///     1:1:void com.example.Rocket.fly():83:83 -> a
+    ///     1:1:void onClick(android.view.View):65 -> a"#,
+    /// );
+    ///
+    /// let obfuscated = StackFrame::with_file("a.a", "a", 1, "SourceFile");
+    /// let remapped = mapper.remap_frame_detailed(&obfuscated);
+    ///
+    /// assert_eq!(remapped.len(), 2);
+    /// assert_eq!(remapped[0].class, "com.example.Rocket");
+    /// assert_eq!(remapped[0].file, None);
+    /// assert_eq!(
+    ///     remapped[0].inlined_into,
+    ///     Some(StackFrame::with_file("com.example.MainFragment", "onClick", 65, "SourceFile"))
+    /// );
+    /// assert_eq!(remapped[1].method, "onClick");
+    /// assert_eq!(remapped[1].file, Some("SourceFile"));
+    /// assert_eq!(remapped[1].inlined_into, None);
+    /// assert_eq!(remapped[1].confidence, Confidence::Exact);
+    /// ```
+    pub fn remap_frame_detailed(&'s self, frame: &StackFrame<'s>) -> Vec<RemappedFrame<'s>> {
+        let mut frames = Vec::new();
+        let mut iter = self.remap_frame(frame);
+        while let Some(remapped) = iter.next_member() {
+            frames.push(remapped);
+        }
+
+        // frames are inlined into the following frame sharing their line range.
+        let mut remapped = Vec::with_capacity(frames.len());
+        for (index, (frame, member)) in frames.iter().enumerate() {
+            let inlined_into = frames.get(index + 1).filter(|(_, outer)| {
+                member.endline > 0
+                    && (member.startline, member.endline) == (outer.startline, outer.endline)
+            });
+            remapped.push(RemappedFrame {
+                class: frame.class,
+                method: frame.method,
+                file: frame.file,
+                line: frame.line,
+                inlined_into: inlined_into.map(|(outer, _)| outer.clone()),
+                confidence: Confidence::of_range(member.startline, member.endline),
+            });
+        }
+        remapped
+    }
+
+    /// Remaps a frame given as the `module`, `function` and `lineno` fields of
+    /// a Sentry Java SDK event payload.
+    ///
+    /// Inlined frames are expanded, and all frames are returned in payload
+    /// order, which is the outermost caller first, so they can replace the
+    /// obfuscated frame in the `frames` list as is. If the class is known but
+    /// the method can not be remapped, only the module is remapped. A frame
+    /// of an unknown class is returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapper;
+    ///
+    /// let mapper = ProguardMapper::from(
+    ///     r#"com.example.MainFragment -> a.a:
+    ///     1:1:void com.example.Rocket.fly():83:83 -> a
+    ///     1:1:void onClick(android.view.View):65 -> a"#,
+    /// );
+    ///
+    /// let frames = mapper.remap_sentry_frame("a.a", "a", Some(1));
+    /// assert_eq!(frames.len(), 2);
+    /// assert_eq!(
+    ///     (frames[0].module, frames[0].function, frames[0].lineno),
+    ///     ("com.example.MainFragment", "onClick", Some(65))
+    /// );
+    /// assert_eq!(
+    ///     (frames[1].module, frames[1].function, frames[1].lineno),
+    ///     ("com.example.Rocket", "fly", Some(83))
+    /// );
+    ///
+    /// let frames = mapper.remap_sentry_frame("a.a", "b", Some(1));
+    /// assert_eq!(
+    ///     (frames[0].module, frames[0].function),
+    ///     ("com.example.MainFragment", "b")
+    /// );
+    ///
+    /// let frames = mapper.remap_sentry_frame("b.b", "b", None);
+    /// assert_eq!((frames[0].module, frames[0].function), ("b.b", "b"));
+    /// ```
+    pub fn remap_sentry_frame(
+        &'s self,
+        module: &'s str,
+        function: &'s str,
+        lineno: Option<usize>,
+    ) -> Vec<SentryFrame<'s>> {
+        let frame = StackFrame::new(module, function, lineno.unwrap_or(0));
+        let class = self.classes.get(module).map(|entry| &entry.class);
+        let mut frames: Vec<_> = self
+            .remap_frame(&frame)
+            .map(|remapped| SentryFrame {
+                module: remapped.class,
+                function: remapped.method,
+                filename: class
+                    .filter(|class| class.original() == remapped.class)
+                    .and_then(|class| class.source_file()),
+                lineno: Some(remapped.line).filter(|line| *line > 0),
+            })
+            .collect();
+
+        if frames.is_empty() {
+            frames.push(SentryFrame {
+                module: class.map_or(module, |class| class.original()),
+                function,
+                filename: class.and_then(|class| class.source_file()),
+                lineno,
+            });
+        }
+        frames.reverse();
+        frames
+    }
+
+    /// Remaps a custom frame in place.
+    ///
+    /// The frame is set to the first frame [`remap_frame`](Self::remap_frame)
+    /// yields, which is the innermost one if the method was inlined. The
+    /// frames it was inlined into, or alternatives of an ambiguous frame, are
+    /// returned as copies of `frame`, so they can be inserted after it.
+    /// Returns `None` and leaves `frame` unchanged if it can not be remapped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, Retraceable};
+    ///
+    /// #[derive(Clone, Debug, PartialEq)]
+    /// struct Frame {
+    ///     class: String,
+    ///     method: String,
+    ///     line: u32,
+    /// }
+    ///
+    /// impl Retraceable for Frame {
+    ///     fn class(&self) -> &str {
+    ///         &self.class
+    ///     }
+    ///     fn method(&self) -> &str {
+    ///         &self.method
+    ///     }
+    ///     fn line(&self) -> Option<usize> {
+    ///         Some(self.line as usize).filter(|line| *line > 0)
+    ///     }
+    ///     fn set_class(&mut self, class: &str) {
+    ///         self.class = class.to_owned();
+    ///     }
+    ///     fn set_method(&mut self, method: &str) {
+    ///         self.method = method.to_owned();
+    ///     }
+    ///     fn set_line(&mut self, line: Option<usize>) {
+    ///         self.line = line.unwrap_or(0) as u32;
+    ///     }
+    /// }
+    ///
+    /// let mapper = ProguardMapper::from(
+    ///     "com.example.MainFragment -> a.a:
+    ///     1:1:void com.example.Rocket.fly():83:83 -> a
+    ///     1:1:void onClick(android.view.View):65 -> a",
+    /// );
+    ///
+    /// let mut frame = Frame { class: "a.a".into(), method: "a".into(), line: 1 };
+    /// let callers = mapper.retrace(&mut frame).unwrap();
+    /// assert_eq!((frame.class.as_str(), frame.method.as_str(), frame.line), ("com.example.Rocket", "fly", 83));
+    /// assert_eq!(callers[0].method, "onClick");
+    ///
+    /// let mut frame = Frame { class: "b.b".into(), method: "b".into(), line: 1 };
+    /// assert_eq!(mapper.retrace(&mut frame), None);
+    /// assert_eq!(frame.class, "b.b");
+    /// ```
+    pub fn retrace<T: Retraceable + Clone>(&self, frame: &mut T) -> Option<Vec<T>> {
+        let mut remapped: Vec<_> = {
+            let obfuscated =
+                StackFrame::new(frame.class(), frame.method(), frame.line().unwrap_or(0));
+            self.remap_frame(&obfuscated)
+                .map(|remapped| {
+                    let line = Some(remapped.line).filter(|line| *line > 0);
+                    let mut copy = frame.clone();
+                    copy.set_class(remapped.class);
+                    copy.set_method(remapped.method);
+                    copy.set_line(line);
+                    copy
+                })
+                .collect()
+        };
+        if remapped.is_empty() {
+            return None;
+        }
+        *frame = remapped.remove(0);
+        Some(remapped)
+    }
+
+    /// Remaps a single Stackframe whose class is missing from the mapping.
+    ///
+    /// This is a heuristic, which is less reliable than [`remap_frame`](Self::remap_frame):
+    /// If exactly one class of the mapping defines the obfuscated method name
+    /// of `frame`, the frame is remapped as if it belonged to that class. It
+    /// requires the index built by [`with_method_index`](Self::with_method_index),
+    /// and yields no frames for classes which are part of the mapping.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, StackFrame};
+    ///
+    /// let mapper = ProguardMapper::from("com.example.Foo -> a:\n    1:1:void bar():10 -> zza")
+    ///     .with_method_index();
+    ///
+    /// let frame = StackFrame::new("b", "zza", 1);
+    /// let mut remapped = mapper.remap_frame_by_method(&frame);
+    /// assert_eq!(remapped.next(), Some(StackFrame::new("com.example.Foo", "bar", 10)));
+    /// ```
+    pub fn remap_frame_by_method(&'s self, frame: &StackFrame<'s>) -> RemappedFrameIter<'s> {
+        match self.method_class(frame) {
+            Some(class) => {
+                let mut remapped = self.remap_frame(&StackFrame {
+                    class,
+                    ..frame.clone()
+                });
+                remapped.heuristic = true;
+                remapped
             }
+            None => RemappedFrameIter::empty(),
         }
-        if !class.original.is_empty() {
-            classes.insert(class.obfuscated, class);
-        }
+    }
 
-        Self { classes }
+    /// Looks up the only class defining the method of `frame`, if its own class is missing.
+    fn method_class(&self, frame: &StackFrame<'_>) -> Option<&'s str> {
+        if self.classes.contains_key(frame.class) {
+            return None;
+        }
+        *self.methods.as_ref()?.get(frame.method)?
     }
 
-    /// Remaps an obfuscated Class.
+    /// Checks which frames of `trace`, including those of its causes, can be
+    /// remapped.
     ///
-    /// This works on the fully-qualified name of the class, with its complete
-    /// module prefix.
+    /// This does not perform the remapping itself, and can be used to check
+    /// whether a mapping file fits a stack trace before symbolicating it.
     ///
     /// # Examples
     ///
     /// ```
-    /// let mapping = r#"android.arch.core.executor.ArchTaskExecutor -> a.a.a.a.c:"#;
-    /// let mapper = proguard::ProguardMapper::from(mapping);
+    /// use proguard::{ProguardMapper, StackFrame, StackTrace};
     ///
-    /// let mapped = mapper.remap_class("a.a.a.a.c");
-    /// assert_eq!(mapped, Some("android.arch.core.executor.ArchTaskExecutor"));
+    /// let mapper = ProguardMapper::from("com.example.Foo -> a:\n    1:1:void bar():10 -> a");
+    /// let trace = StackTrace::new(
+    ///     None,
+    ///     vec![
+    ///         StackFrame::new("a", "a", 1),
+    ///         StackFrame::new("a", "b", 1),
+    ///         StackFrame::new("b", "a", 1),
+    ///     ],
+    /// );
+    ///
+    /// let coverage = mapper.coverage(&trace);
+    /// assert!(!coverage.is_complete());
+    /// assert_eq!(coverage.resolved, vec![StackFrame::new("a", "a", 1)]);
+    /// assert_eq!(coverage.unresolved.len(), 2);
+    /// assert_eq!(coverage.missing_classes, vec!["b"]);
     /// ```
-    pub fn remap_class(&'s self, class: &str) -> Option<&'s str> {
-        self.classes.get(class).map(|class| class.original)
-    }
+    pub fn coverage<'t>(&self, trace: &StackTrace<'t>) -> TraceCoverage<'t> {
+        let mut coverage = TraceCoverage::default();
+        // traces are visited in the order they are printed in.
+        let mut pending = vec![trace];
 
-    /// Remaps a single Stackframe.
-    ///
-    /// Returns zero or more [`StackFrame`]s, based on the information in
-    /// the proguard mapping. This can return more than one frame in the case
-    /// of inlined functions. In that case, frames are sorted top to bottom.
-    pub fn remap_frame(&'s self, frame: &StackFrame<'s>) -> RemappedFrameIter<'s> {
-        if let Some(class) = self.classes.get(frame.class) {
-            if let Some(members) = class.members.get(frame.method) {
-                let mut frame = frame.clone();
-                frame.class = class.original;
-                return RemappedFrameIter::members(frame, members.iter());
+        while let Some(trace) = pending.pop() {
+            for frame in &trace.frames {
+                if self.remap_frame(frame).next().is_some() {
+                    coverage.resolved.push(frame.clone());
+                    continue;
+                }
+                if !self.classes.contains_key(frame.class)
+                    && !coverage.missing_classes.contains(&frame.class)
+                {
+                    coverage.missing_classes.push(frame.class);
+                }
+                coverage.unresolved.push(frame.clone());
             }
+            pending.extend(trace.cause.as_deref());
+            pending.extend(trace.suppressed.iter().rev());
         }
-        RemappedFrameIter::empty()
+
+        coverage
     }
 
     /// Remaps a throwable which is the first line of a full stacktrace.
@@ -219,49 +1704,252 @@ impl<'s> ProguardMapper<'s> {
     /// );
     /// ```
     pub fn remap_throwable<'a>(&'a self, throwable: &Throwable<'a>) -> Option<Throwable<'a>> {
-        if let Some(class) = self.remap_class(throwable.class) {
-            Some(Throwable {
-                class,
-                message: throwable.message,
-            })
-        } else {
-            None
-        }
+        self.remap_class(throwable.class).map(|class| Throwable {
+            class,
+            message: throwable.message,
+        })
     }
 
     /// Remaps a complete Java StackTrace, similar to [`Self::remap_stacktrace`] but instead works on
     /// strings as input and output.
-    pub fn remap_stacktrace(&self, input: &str) -> Result<String, std::fmt::Error> {
-        let mut stacktrace = String::new();
-        let mut lines = input.lines();
-
-        if let Some(line) = lines.next() {
-            match stacktrace::parse_throwable(line) {
-                None => match stacktrace::parse_frame(line) {
-                    None => writeln!(&mut stacktrace, "{}", line)?,
-                    Some(frame) => format_frames(&mut stacktrace, line, self.remap_frame(&frame))?,
-                },
-                Some(throwable) => {
-                    format_throwable(&mut stacktrace, line, self.remap_throwable(&throwable))?
-                }
+    ///
+    /// Lines which are neither an exception nor a frame, such as log output,
+    /// blank lines or `... 3 more` markers, are passed through unchanged and
+    /// in order. Every line keeps its `\n`, `\r\n` or `\r` terminator.
+    pub fn remap_stacktrace(&self, input: &str) -> Result<String, FmtError> {
+        self.remap_stacktrace_with_options(input, &RetraceOptions::default())
+    }
+
+    /// Remaps a complete Java StackTrace given as a string, according to `options`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapper, RetraceOptions};
+    ///
+    /// let mapper = ProguardMapper::from(
+    ///     "com.example.Foo -> a:\n    void bar() -> a\n    void baz() -> a",
+    /// );
+    /// let options = RetraceOptions {
+    ///     r8_compat: true,
+    ///     ..Default::default()
+    /// };
+    ///
+    /// let remapped = mapper
+    ///     .remap_stacktrace_with_options("    at a.a(SourceFile)", &options)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     remapped,
+    ///     "    at com.example.Foo.bar(Foo.java)\n    <OR> at com.example.Foo.baz(Foo.java)\n"
+    /// );
+    /// ```
+    pub fn remap_stacktrace_with_options(
+        &self,
+        input: &str,
+        options: &RetraceOptions,
+    ) -> Result<String, FmtError> {
+        remap_stacktrace_routed(input, options, |_| self)
+    }
+
+    /// Completes a truncated obfuscated class name, if it matches exactly one class.
+    fn complete_class<'a>(&'a self, class: &'a str) -> &'a str {
+        if class.is_empty() || self.classes.contains_key(class) {
+            return class;
+        }
+        let sorted = self
+            .sorted_classes
+            .get_or_init(|| self.classes.keys().map(|&class| class.into()).collect());
+        let mut matches = sorted
+            .range::<str, _>((Bound::Included(class), Bound::Unbounded))
+            .take_while(|obfuscated| obfuscated.starts_with(class));
+        match (matches.next(), matches.next()) {
+            (Some(obfuscated), None) => obfuscated,
+            _ => class,
+        }
+    }
+
+    /// Remaps a frame `line` whose tail was cut off, as logcat truncates long
+    /// lines, such as `at a.b.c(SourceFi` or `at a.b`.
+    ///
+    /// The class is completed if it was cut off, and the method if it matches
+    /// exactly one original method. Whatever remains of the source location
+    /// is kept as-is.
+    fn remap_truncated_frame(&self, line: &str) -> Option<String> {
+        let target = line.trim().strip_prefix("at ")?;
+        if target.ends_with(')') || target.contains(char::is_whitespace) {
+            return None;
+        }
+        let (name, location) = target.split_at(target.find('(').unwrap_or(target.len()));
+        let (class, method) = match name.rsplit_once('.') {
+            Some((class, method)) if self.classes.contains_key(class) => (class, Some(method)),
+            // the line was cut off within the class name.
+            _ => (self.complete_class(name), None),
+        };
+        let original_class = self.classes.get(class)?.class.original();
+        let method = match method {
+            Some(method) => method,
+            None => return Some(format!("{}at {}", indent(line), original_class)),
+        };
+
+        // the method name is complete if the location follows it.
+        let originals: BTreeSet<_> = self.classes[class]
+            .class
+            .methods()
+            .filter(|candidate| match location {
+                "" => candidate.obfuscated.starts_with(method),
+                _ => candidate.obfuscated == method,
+            })
+            .map(|candidate| {
+                let class = candidate.original_class.unwrap_or(original_class);
+                (class, candidate.original)
+            })
+            .collect();
+        let (class, method) = match originals.len() {
+            1 => originals.into_iter().next()?,
+            _ => (original_class, method),
+        };
+        Some(format!(
+            "{}at {}.{}{}",
+            indent(line),
+            class,
+            method,
+            location
+        ))
+    }
+
+    fn format_frame(
+        &self,
+        stacktrace: &mut impl Write,
+        line: &str,
+        frame: &StackFrame<'_>,
+        options: &RetraceOptions,
+    ) -> Result<(), FmtError> {
+        if !options.annotate_obfuscated {
+            return self.format_remapped(stacktrace, line, frame, options);
+        }
+
+        let mut output = String::new();
+        self.format_remapped(&mut output, line, frame, options)?;
+        if output.strip_suffix('\n') == Some(line) {
+            return stacktrace.write_str(&output);
+        }
+        let obfuscated = line.trim();
+        let obfuscated = obfuscated.strip_prefix("at ").unwrap_or(obfuscated);
+        for remapped in output.lines() {
+            writeln!(stacktrace, "{} // was {}", remapped, obfuscated)?;
+        }
+        Ok(())
+    }
+
+    fn format_remapped(
+        &self,
+        stacktrace: &mut impl Write,
+        line: &str,
+        frame: &StackFrame<'_>,
+        options: &RetraceOptions,
+    ) -> Result<(), FmtError> {
+        if options.strip_synthetic_suffixes && self.remap_frame(frame).next().is_none() {
+            let method = strip_synthetic_suffix(frame.method);
+            if method != frame.method {
+                let frame = StackFrame {
+                    class: self.remap_class(frame.class).unwrap_or(frame.class),
+                    method,
+                    ..frame.clone()
+                };
+                return writeln!(
+                    stacktrace,
+                    "{}{}",
+                    indent(line),
+                    FormatFrame(&frame, options)
+                );
+            }
+        }
+        if !options.r8_compat {
+            let entry = match self.classes.get(frame.class) {
+                Some(entry) if options.infer_file_names => entry,
+                _ => return format_frames(stacktrace, line, self.remap_frame(frame), options),
+            };
+            let source_file = entry.class.source_file();
+            let mut remapped = self.remap_frame(frame).peekable();
+            if remapped.peek().is_none() {
+                return writeln!(stacktrace, "{}", line);
+            }
+            for remapped in remapped {
+                let own_class = remapped.class == entry.class.original();
+                let file = r8_file_name(
+                    remapped.class,
+                    frame.file,
+                    source_file.filter(|_| own_class),
+                );
+                let remapped = StackFrame {
+                    file: Some(&file),
+                    ..remapped
+                };
+                writeln!(
+                    stacktrace,
+                    "{}{}",
+                    indent(line),
+                    FormatFrame(&remapped, options)
+                )?;
             }
+            return Ok(());
         }
+        let entry = match self.classes.get(frame.class) {
+            Some(entry) => entry,
+            None => return writeln!(stacktrace, "{}", line),
+        };
 
-        for line in lines {
-            match stacktrace::parse_frame(line) {
-                None => match line
-                    .strip_prefix("Caused by: ")
-                    .and_then(stacktrace::parse_throwable)
-                {
-                    None => writeln!(&mut stacktrace, "{}", line)?,
-                    Some(cause) => {
-                        format_cause(&mut stacktrace, line, self.remap_throwable(&cause))?
-                    }
-                },
-                Some(frame) => format_frames(&mut stacktrace, line, self.remap_frame(&frame))?,
+        // Split the remapped frames into alternatives, each of which is an
+        // inlined call stack sharing the same minified line range.
+        let mut alternatives: Vec<Vec<(StackFrame<'_>, bool)>> = Vec::new();
+        let mut range = None;
+        let mut remapped = self.remap_frame(frame);
+        while let Some((remapped, member)) = remapped.next_member() {
+            let member_range = (member.startline, member.endline);
+            if member.endline == 0 || range != Some(member_range) {
+                alternatives.push(Vec::new());
+            }
+            range = Some(member_range);
+            if let Some(alternative) = alternatives.last_mut() {
+                alternative.push((remapped, member.synthesized));
+            }
+        }
+        if alternatives.is_empty() {
+            return writeln!(stacktrace, "{}", line);
+        }
+
+        let source_file = entry.class.source_file();
+        let mut written = Vec::new();
+        for alternative in alternatives {
+            let all_synthesized = alternative.iter().all(|(_, synthesized)| *synthesized);
+            let mut output = String::new();
+            for (remapped, synthesized) in alternative {
+                if synthesized && !all_synthesized {
+                    continue;
+                }
+                let own_class = remapped.class == entry.class.original();
+                let file = r8_file_name(
+                    remapped.class,
+                    frame.file,
+                    source_file.filter(|_| own_class),
+                );
+                let remapped = StackFrame {
+                    file: Some(&file),
+                    ..remapped
+                };
+                writeln!(output, "{}", FormatFrame(&remapped, options))?;
             }
+            if written.contains(&output) {
+                continue;
+            }
+            let prefix = if written.is_empty() { "" } else { "<OR> " };
+            for remapped in output.lines() {
+                writeln!(stacktrace, "{}{}{}", indent(line), prefix, remapped)?;
+            }
+            written.push(output);
         }
-        Ok(stacktrace)
+
+        Ok(())
     }
 
     /// Remaps a complete Java StackTrace.
@@ -291,52 +1979,381 @@ impl<'s> ProguardMapper<'s> {
             .as_ref()
             .map(|c| Box::new(self.remap_stacktrace_typed(c)));
 
+        let suppressed = trace
+            .suppressed
+            .iter()
+            .map(|s| self.remap_stacktrace_typed(s))
+            .collect();
+
         StackTrace {
             exception,
             frames,
             cause,
+            suppressed,
+        }
+    }
+
+    /// Remaps the class names in a view hierarchy JSON document.
+    ///
+    /// The `type` field of every node in the document, which holds the class
+    /// name of its view, is replaced by the original class name. Everything
+    /// else is copied verbatim.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = r#"com.example.CheckoutButton -> a.b:"#;
+    /// let mapper = proguard::ProguardMapper::from(mapping);
+    ///
+    /// let mapped = mapper
+    ///     .remap_view_hierarchy(r#"{"windows": [{"type": "a.b", "children": []}]}"#)
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     mapped,
+    ///     r#"{"windows": [{"type": "com.example.CheckoutButton", "children": []}]}"#
+    /// );
+    /// ```
+    pub fn remap_view_hierarchy(&self, input: &str) -> Result<String, ViewHierarchyError> {
+        view_hierarchy::remap_types(input, |class| {
+            self.classes.get(class).map(|entry| entry.class.original())
+        })
+    }
+
+    /// Remaps the classes and methods of an ART baseline profile.
+    ///
+    /// This translates an obfuscated `baseline-prof.txt`, as rewritten by R8,
+    /// back to original names. Methods are matched by their name and argument
+    /// types, so overloads are told apart. Everything that can not be
+    /// remapped, including wildcard rules, is kept as-is. Use
+    /// [`ProguardObfuscator::obfuscate_baseline_profile`](crate::ProguardObfuscator::obfuscate_baseline_profile)
+    /// to translate in the other direction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = r#"com.example.Foo -> a.a:
+    ///     void run(com.example.Foo) -> a
+    ///     void run(int) -> b"#;
+    /// let mapper = proguard::ProguardMapper::from(mapping);
+    ///
+    /// let profile = "HSPLa/a;->a(La/a;)V\nPLa/a;->b(I)V\n";
+    /// assert_eq!(
+    ///     mapper.remap_baseline_profile(profile),
+    ///     "HSPLcom/example/Foo;->run(Lcom/example/Foo;)V\nPLcom/example/Foo;->run(I)V\n"
+    /// );
+    /// ```
+    pub fn remap_baseline_profile(&self, input: &str) -> String {
+        baseline_profile::translate(
+            input,
+            |class| self.classes.get(class).map(|entry| entry.class.original()),
+            |class, method, _, arguments| {
+                let entry = self.classes.get(class)?;
+                entry
+                    .class
+                    .methods()
+                    .find(|m| {
+                        m.obfuscated == method
+                            && m.original_class.is_none()
+                            && baseline_profile::same_arguments(m.arguments, arguments)
+                    })
+                    .map(|m| m.original)
+            },
+        )
+    }
+
+    /// Remaps the class names in arbitrary text, such as log messages.
+    ///
+    /// Every word that is a fully-qualified obfuscated class name is replaced
+    /// by the original class name. Words that merely start with a class name,
+    /// such as a method reference or a nested class missing from the mapping,
+    /// have their longest known class prefix replaced. Names without a package
+    /// are left untouched, as they can not be told apart from ordinary words.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = r#"com.example.CheckoutButton -> a.b:"#;
+    /// let mapper = proguard::ProguardMapper::from(mapping);
+    ///
+    /// let mapped = mapper.remap_text("Clicked a.b, handled by a.b.onClick");
+    /// assert_eq!(
+    ///     mapped,
+    ///     "Clicked com.example.CheckoutButton, handled by com.example.CheckoutButton.onClick"
+    /// );
+    /// ```
+    pub fn remap_text(&self, input: &str) -> String {
+        text::remap_tokens(input, |class| {
+            self.classes.get(class).map(|entry| entry.class.original())
+        })
+    }
+
+    /// Remaps a dotted path of obfuscated field names, starting at `class`.
+    ///
+    /// Serialization libraries like Gson, Moshi or kotlinx.serialization
+    /// report errors with paths such as `$.a.b[0].c`, relative to an obfuscated
+    /// class. Each field is looked up in the class declaring it, and the
+    /// following field in the class of its type. An optional leading `$` and
+    /// array indices are kept as-is, as are fields that can not be resolved.
+    ///
+    /// Types are resolved using a linear scan over all classes, so this is
+    /// meant for occasional use on error messages.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = r#"com.example.User -> a.c:
+    ///     com.example.Address[] addresses -> a
+    /// com.example.Address -> a.d:
+    ///     java.lang.String street -> b"#;
+    /// let mapper = proguard::ProguardMapper::from(mapping);
+    ///
+    /// assert_eq!(mapper.remap_field_path("a.c", "$.a[0].b"), "$.addresses[0].street");
+    /// ```
+    pub fn remap_field_path(&self, class: &str, path: &str) -> String {
+        let mut class = self.classes.get(class).map(|entry| &entry.class);
+        let mut output = String::with_capacity(path.len());
+        for (i, segment) in path.split('.').enumerate() {
+            if i > 0 {
+                output.push('.');
+            } else if segment == "$" {
+                output.push('$');
+                continue;
+            }
+
+            let (name, indices) = segment.split_at(segment.find('[').unwrap_or(segment.len()));
+            let field = class.and_then(|class| class.fields().find(|f| f.obfuscated == name));
+            output.push_str(field.as_ref().map_or(name, |field| field.name()));
+            output.push_str(indices);
+
+            class = field.and_then(|field| {
+                let ty = field.java_type()?.class_name()?;
+                self.classes
+                    .values()
+                    .map(|entry| &entry.class)
+                    .find(|class| class.original() == ty)
+            });
+        }
+        output
+    }
+}
+
+/// Remaps a complete Java StackTrace given as a string, according to `options`.
+///
+/// Every frame and exception is remapped by the mapper that `route` returns
+/// for its obfuscated class, and all other lines by the one for `""`.
+pub(crate) fn remap_stacktrace_routed<'m>(
+    input: &str,
+    options: &RetraceOptions,
+    route: impl Fn(&str) -> &'m ProguardMapper<'m>,
+) -> Result<String, FmtError> {
+    let mut stacktrace = String::new();
+    let mut output = String::new();
+    // the exception may be preceded by blank lines or other log output.
+    let mut seen_frame = false;
+
+    for (line, terminator) in stacktrace::split_lines(input) {
+        let terminator = if terminator.is_empty() {
+            "\n"
+        } else {
+            terminator
+        };
+        output.clear();
+        let (prefix, line) = if options.logcat_prefixes {
+            stacktrace::split_logcat_prefix(line)
+        } else {
+            ("", line)
+        };
+        if let Some(mut frame) = stacktrace::parse_frame(line) {
+            seen_frame = true;
+            let mapper = route(frame.class);
+            if options.match_truncated_classes {
+                frame.class = mapper.complete_class(frame.class);
+            }
+            let class = mapper.method_class(&frame);
+            let unmapped = options.annotate_unmapped
+                && mapper
+                    .remap_frame(&StackFrame {
+                        class: class.unwrap_or(frame.class),
+                        ..frame.clone()
+                    })
+                    .next()
+                    .is_none();
+            match class {
+                Some(class) => {
+                    let mut guessed = String::new();
+                    mapper.format_frame(
+                        &mut guessed,
+                        line,
+                        &StackFrame { class, ..frame },
+                        options,
+                    )?;
+                    for remapped in guessed.lines() {
+                        if remapped == line {
+                            writeln!(&mut output, "{}", remapped)?;
+                        } else {
+                            writeln!(&mut output, "{} // guessed by method name", remapped)?;
+                        }
+                    }
+                }
+                None => mapper.format_frame(&mut output, line, &frame, options)?,
+            }
+            if unmapped {
+                output.pop();
+                output.push_str(" // not found in mapping\n");
+            }
+        } else if let Some(remapped) = options
+            .match_truncated_classes
+            .then(|| {
+                let target = line.trim_start();
+                route(target.strip_prefix("at ").unwrap_or(target)).remap_truncated_frame(line)
+            })
+            .flatten()
+        {
+            writeln!(&mut output, "{}", remapped)?;
+        } else {
+            let throwable = if seen_frame {
+                None
+            } else {
+                stacktrace::parse_headline(line)
+            };
+            match throwable.or_else(|| stacktrace::parse_cause(line)) {
+                Some((prefix, mut throwable)) => {
+                    let mapper = route(throwable.class);
+                    if options.match_truncated_classes {
+                        throwable.class = mapper.complete_class(throwable.class);
+                    }
+                    let message = throwable
+                        .message
+                        .filter(|_| options.remap_class_names)
+                        .map(|message| mapper.remap_text(message))
+                        .filter(|message| Some(message.as_str()) != throwable.message);
+                    let throwable = Throwable {
+                        message: message.as_deref().or(throwable.message),
+                        ..throwable
+                    };
+                    let remapped = match mapper.remap_throwable(&throwable) {
+                        None if message.is_some() => Some(throwable),
+                        remapped => remapped,
+                    };
+                    format_throwable(&mut output, line, prefix, remapped)?;
+                }
+                None if options.remap_class_names => {
+                    writeln!(&mut output, "{}", route("").remap_text(line))?
+                }
+                None => writeln!(&mut output, "{}", line)?,
+            }
+        }
+
+        // lines keep their original logcat prefix and line terminator.
+        for remapped in output.split_terminator('\n') {
+            stacktrace.push_str(prefix);
+            stacktrace.push_str(remapped);
+            stacktrace.push_str(terminator);
         }
     }
+    Ok(stacktrace)
 }
 
-fn format_throwable(
+pub(crate) fn format_throwable(
     stacktrace: &mut impl Write,
     line: &str,
+    prefix: &str,
     throwable: Option<Throwable<'_>>,
 ) -> Result<(), FmtError> {
     if let Some(throwable) = throwable {
-        writeln!(stacktrace, "{}", throwable)
+        writeln!(stacktrace, "{}{}", prefix, throwable)
     } else {
         writeln!(stacktrace, "{}", line)
     }
 }
 
+/// Strips compiler generated suffixes from a method name.
+fn strip_synthetic_suffix(method: &str) -> &str {
+    if let Some((name, suffix)) = method.split_once("$r8$") {
+        if !name.is_empty() {
+            return name;
+        }
+        return suffix.rsplit('$').next().unwrap_or(method);
+    }
+    method
+}
+
+/// Determines the file name of a remapped frame the way R8 `retrace` does.
+fn r8_file_name<'a>(
+    class: &str,
+    obfuscated_file: Option<&'a str>,
+    source_file: Option<&'a str>,
+) -> std::borrow::Cow<'a, str> {
+    if let Some(file @ ("Unknown Source" | "Native Method" | "Unknown")) = obfuscated_file {
+        return file.into();
+    }
+    if let Some(source_file) = source_file {
+        return source_file.into();
+    }
+    let simple_name = class.rsplit('.').next().unwrap_or(class);
+    let outer_name = simple_name.split('$').next().unwrap_or(simple_name);
+    let extension = obfuscated_file
+        .and_then(|file| file.rsplit_once('.'))
+        .map_or("java", |(_, extension)| extension);
+    format!("{}.{}", outer_name, extension).into()
+}
+
 fn format_frames<'s>(
     stacktrace: &mut impl Write,
     line: &str,
     remapped: impl Iterator<Item = StackFrame<'s>>,
+    options: &RetraceOptions,
 ) -> Result<(), FmtError> {
     let mut remapped = remapped.peekable();
 
     if remapped.peek().is_none() {
         return writeln!(stacktrace, "{}", line);
     }
-    for line in remapped {
-        writeln!(stacktrace, "    {}", line)?;
+    for remapped in remapped {
+        writeln!(
+            stacktrace,
+            "{}{}",
+            indent(line),
+            FormatFrame(&remapped, options)
+        )?;
     }
 
     Ok(())
 }
 
-fn format_cause(
-    stacktrace: &mut impl Write,
-    line: &str,
-    cause: Option<Throwable<'_>>,
-) -> Result<(), FmtError> {
-    if let Some(cause) = cause {
-        writeln!(stacktrace, "Caused by: {}", cause)
+/// Returns the leading whitespace of a frame `line`, which remapped frames
+/// are indented with, or four spaces if the line is not indented.
+pub(crate) fn indent(line: &str) -> &str {
+    let indent = &line[..line.len() - line.trim_start().len()];
+    if indent.is_empty() {
+        "    "
     } else {
-        writeln!(stacktrace, "{}", line)
+        indent
+    }
+}
+
+/// Formats a remapped frame, with its source location as configured.
+struct FormatFrame<'a, 's>(&'a StackFrame<'s>, &'a RetraceOptions);
+
+impl fmt::Display for FormatFrame<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let FormatFrame(frame, options) = self;
+        f.write_str("at ")?;
+        if let Some(module) = frame.module {
+            write!(f, "{}/", module)?;
+        }
+        write!(f, "{}.{}", frame.class, frame.method)?;
+
+        let file = frame.file.unwrap_or("<unknown>");
+        match options.source_location {
+            SourceLocation::FileAndLine if options.r8_compat && frame.line == 0 => {
+                write!(f, "({})", file)
+            }
+            SourceLocation::FileAndLine => write!(f, "({}:{})", file, frame.line),
+            SourceLocation::File => write!(f, "({})", file),
+            SourceLocation::UnknownSource => f.write_str("(Unknown Source)"),
+            SourceLocation::Omitted => Ok(()),
+        }
     }
 }
 
@@ -344,6 +2361,197 @@ fn format_cause(
 mod tests {
     use super::*;
 
+    #[test]
+    fn lazy_members() {
+        let mapping = "\
+some.Class -> a:
+    1:1:void method():10:10 -> a
+other.Class -> b:
+    1:1:void other():20:20 -> a
+";
+        let mapper = ProguardMapper::from(mapping);
+        assert!(mapper.classes.values().all(|c| c.members.get().is_none()));
+
+        let frame = StackFrame::new("a", "a", 1);
+        assert_eq!(
+            mapper.remap_frame(&frame).collect::<Vec<_>>(),
+            vec![StackFrame::new("some.Class", "method", 10)]
+        );
+        assert!(mapper.classes["a"].members.get().is_some());
+        assert!(mapper.classes["b"].members.get().is_none());
+    }
+
+    /// The classes of `mapper` which are indexed under its index limit.
+    fn limited(mapper: &ProguardMapper<'_>) -> Vec<String> {
+        let mut indexed: Vec<_> = mapper
+            .classes
+            .iter()
+            .filter(|(_, c)| c.limited.read().unwrap().is_some())
+            .map(|(name, _)| name.to_string())
+            .collect();
+        indexed.sort();
+        indexed
+    }
+
+    #[test]
+    fn hot_index_limit() {
+        let mapping = "\
+some.Class -> a:
+    1:1:void method():10:10 -> a
+other.Class -> b:
+    1:1:void other():20:20 -> a
+third.Class -> c:
+    1:1:void third():30:30 -> a
+";
+        let mapper = ProguardMapper::from(mapping).with_hot_index_limit(1);
+        let remap = |class| {
+            let frame = StackFrame::new(class, "a", 1);
+            mapper.remap_frame(&frame).collect::<Vec<_>>()
+        };
+        let expected_b = vec![StackFrame::new("other.Class", "other", 20)];
+        let expected_c = vec![StackFrame::new("third.Class", "third", 30)];
+
+        // classes used once are scanned, and not indexed
+        assert_eq!(remap("b"), expected_b);
+        assert!(limited(&mapper).is_empty());
+
+        assert_eq!(remap("b"), expected_b);
+        assert_eq!(limited(&mapper), vec!["b"]);
+        assert!(mapper.classes.values().all(|c| c.members.get().is_none()));
+
+        // the limit is reached, so classes used less often are scanned
+        assert_eq!(remap("c"), expected_c);
+        assert_eq!(remap("c"), expected_c);
+        assert_eq!(limited(&mapper), vec!["b"]);
+
+        // until they are used more often than the indexed class
+        assert_eq!(remap("c"), expected_c);
+        assert_eq!(limited(&mapper), vec!["c"]);
+        assert_eq!(remap("c"), expected_c);
+        assert_eq!(remap("b"), expected_b);
+        assert_eq!(limited(&mapper), vec!["c"]);
+        assert_eq!(mapper.remap_frame(&StackFrame::new("c", "b", 1)).count(), 0);
+
+        // a limit of zero never indexes anything
+        let mapper = ProguardMapper::from(mapping).with_hot_index_limit(0);
+        for _ in 0..3 {
+            assert_eq!(
+                mapper
+                    .remap_frame(&StackFrame::new("b", "a", 1))
+                    .collect::<Vec<_>>(),
+                expected_b
+            );
+        }
+        assert!(limited(&mapper).is_empty());
+    }
+
+    #[test]
+    fn hot_index_limit_eager() {
+        let mapping = "\
+some.Class -> a:
+    1:1:void method():10:10 -> a
+other.Class -> b:
+    1:1:void other():20:20 -> a
+";
+        let mapper = ProguardMapper::new_eager(ProguardMapping::new(mapping.as_bytes()))
+            .with_hot_index_limit(3);
+        assert_eq!(mapper.index_limit.as_ref().unwrap().max_classes, 1);
+
+        // permanently indexed classes are not counted, nor evicted.
+        let frame = StackFrame::new("a", "a", 1);
+        for _ in 0..3 {
+            assert_eq!(
+                mapper.remap_frame(&frame).collect::<Vec<_>>(),
+                vec![StackFrame::new("some.Class", "method", 10)]
+            );
+        }
+        assert!(limited(&mapper).is_empty());
+        assert_eq!(mapper.classes["a"].hits.load(Ordering::Relaxed), 0);
+    }
+
+    #[test]
+    fn warm() {
+        let mapping = "\
+some.Class -> a:
+    1:1:void method():10:10 -> a
+other.Class -> b:
+    1:1:void other():20:20 -> a
+";
+        let mapper = ProguardMapper::from(mapping).with_hot_index_limit(1);
+        assert_eq!(mapper.warm(&["b", "b", "missing", "a"]), 3);
+        assert_eq!(limited(&mapper), vec!["b"]);
+        assert!(mapper.classes.values().all(|c| c.members.get().is_none()));
+
+        // the warmed class is evicted once another class is used more often.
+        let frame = StackFrame::new("a", "a", 1);
+        assert_eq!(
+            mapper.remap_frame(&frame).collect::<Vec<_>>(),
+            vec![StackFrame::new("some.Class", "method", 10)]
+        );
+        assert_eq!(limited(&mapper), vec!["b"]);
+        assert_eq!(
+            mapper.remap_frame(&frame).collect::<Vec<_>>(),
+            vec![StackFrame::new("some.Class", "method", 10)]
+        );
+        assert_eq!(limited(&mapper), vec!["a"]);
+
+        let mapper = ProguardMapper::from(mapping);
+        assert_eq!(mapper.warm(&["b"]), 1);
+        assert!(mapper.classes["b"].members.get().is_some());
+    }
+
+    #[test]
+    fn chunked() {
+        let source = include_bytes!("../tests/res/mapping-r8.txt");
+        let windows = String::from_utf8_lossy(source).replace('\n', "\r\n");
+
+        for source in [&source[..], windows.as_bytes()] {
+            let text = std::str::from_utf8(source).unwrap();
+            let eager = ProguardMapper::new_eager(ProguardMapping::new(source));
+
+            for chunks in [2, 3, 7, 16] {
+                let boundaries = class_boundaries(source, text, chunks);
+                assert_eq!(boundaries.first(), Some(&0));
+                assert_eq!(boundaries.last(), Some(&source.len()));
+                for &boundary in &boundaries[1..boundaries.len() - 1] {
+                    assert_eq!(source[boundary - 1], b'\n');
+                    assert_ne!(source[boundary], b' ');
+                }
+
+                let chunked = ProguardMapper::new_chunked(ProguardMapping::new(source), chunks);
+                assert_eq!(chunked.classes.len(), eager.classes.len());
+                assert_eq!(chunked.has_line_info(), eager.has_line_info());
+                for (name, entry) in &eager.classes {
+                    let other = &chunked.classes[name];
+                    assert_eq!(other.class.original(), entry.class.original());
+                    assert_eq!(other.members.get(), entry.members.get());
+                }
+            }
+        }
+
+        // there is no class line to split at.
+        let source = b"a.A -> a:\n    void run() -> a\n    void stop() -> b\n";
+        let text = std::str::from_utf8(source).unwrap();
+        assert_eq!(class_boundaries(source, text, 4), vec![0, source.len()]);
+    }
+
+    #[test]
+    fn eager_invalid_utf8() {
+        let mapping = b"\
+some.Class -> a:
+    1:1:void method():10:10 -> a
+    1:1:void \xff():10:10 -> b
+";
+        let mapper = ProguardMapper::new_eager(ProguardMapping::new(mapping));
+        assert!(mapper.classes["a"].members.get().is_some());
+
+        let frame = StackFrame::new("a", "a", 1);
+        assert_eq!(
+            mapper.remap_frame(&frame).collect::<Vec<_>>(),
+            vec![StackFrame::new("some.Class", "method", 10)]
+        );
+    }
+
     #[test]
     fn stacktrace() {
         let mapping = "\
@@ -367,12 +2575,14 @@ com.example.MainFragment$onActivityCreated$4 -> com.example.MainFragment$g:
                     method: "onClick",
                     line: 2,
                     file: Some("SourceFile"),
+                    module: None,
                 },
                 StackFrame {
                     class: "android.view.View",
                     method: "performClick",
                     line: 7393,
                     file: Some("View.java"),
+                    module: None,
                 },
             ],
             cause: Some(Box::new(StackTrace {
@@ -385,9 +2595,12 @@ com.example.MainFragment$onActivityCreated$4 -> com.example.MainFragment$g:
                     method: "onClick",
                     line: 1,
                     file: Some("SourceFile"),
+                    module: None,
                 }],
                 cause: None,
+                suppressed: vec![],
             })),
+            suppressed: vec![],
         };
         let expect = "\
 com.example.MainFragment$RocketException: Crash!
@@ -407,6 +2620,39 @@ Caused by: com.example.MainFragment$EngineFailureException: Engines overheating
         );
     }
 
+    #[test]
+    fn synthetic_suffixes() {
+        assert_eq!(
+            strip_synthetic_suffix("$r8$backportedMethods$utility$Objects$1$hashCode"),
+            "hashCode"
+        );
+        assert_eq!(strip_synthetic_suffix("run$r8$lambda$0"), "run");
+        assert_eq!(strip_synthetic_suffix("access$000"), "access$000");
+        assert_eq!(strip_synthetic_suffix("access$getFoo"), "access$getFoo");
+        assert_eq!(strip_synthetic_suffix("lambda$run$0"), "lambda$run$0");
+
+        let mapper =
+            ProguardMapper::from("com.example.Foo -> a:\n    1:1:void access$000():10 -> a$r8$b");
+        let options = RetraceOptions {
+            strip_synthetic_suffixes: true,
+            ..Default::default()
+        };
+        let stacktrace = "    at a.a$r8$b(SourceFile:1)
+    at a.access$100(SourceFile:2)
+\tat b.$r8$backportedMethods$utility$Objects$1$hashCode(SourceFile:3)
+    at b.c(SourceFile:4)";
+        let expect = "    at com.example.Foo.access$000(SourceFile:10)
+    at a.access$100(SourceFile:2)
+\tat b.hashCode(SourceFile:3)
+    at b.c(SourceFile:4)\n";
+        assert_eq!(
+            mapper
+                .remap_stacktrace_with_options(stacktrace, &options)
+                .unwrap(),
+            expect
+        );
+    }
+
     #[test]
     fn stacktrace_str() {
         let mapping = "\
@@ -4,7 +4,20 @@
 //! [here](https://www.guardsquare.com/en/products/proguard/manual/retrace).
 
 use std::fmt;
-use std::str;
+use std::iter::FusedIterator;
+use std::ops::Range;
+use std::str::{self, FromStr};
+
+use crate::canonical::CanonicalMapping;
+use crate::classes::{
+    parse_class_line, ClassHandleIter, ClassIter, ClassMapping, ClassMatchIter, ClassNameIter,
+    FieldMapping, MethodMapping,
+};
+use crate::lint::{self, LintWarning};
+use crate::manifest::{self, ManifestMismatch, MappingManifest};
+use crate::metadata::DebugFileMetadata;
+use crate::raw::RawRecordIter;
+use crate::visitor::{self, MappingVisitor};
 
 #[cfg(feature = "uuid")]
 use uuid_::Uuid;
@@ -20,8 +33,14 @@ pub struct ParseError<'s> {
 }
 
 impl<'s> ParseError<'s> {
+    pub(crate) fn new(line: &'s [u8], kind: ParseErrorKind) -> Self {
+        Self { line, kind }
+    }
+
     /// The offending line that caused the error.
-    pub fn line(&self) -> &[u8] {
+    ///
+    /// This is the raw line of the mapping file, without its line terminator.
+    pub fn line(&self) -> &'s [u8] {
         self.line
     }
 
@@ -58,6 +77,139 @@ pub enum ParseErrorKind {
     ParseError(&'static str),
 }
 
+/// The reason a line failed to parse, as reported by [`ProguardMapping::parse_strict`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SyntaxErrorKind {
+    /// The line is not valid utf-8.
+    InvalidUtf8,
+    /// The ` -> ` separator between original and obfuscated name is missing.
+    MissingSeparator,
+    /// A class line does not end with a `:`.
+    MissingClassTerminator,
+    /// A line number is not a valid number.
+    InvalidLineNumber,
+    /// The method argument list is not closed by a `)`.
+    UnclosedArguments,
+    /// The line ended before the record was complete.
+    UnexpectedEndOfLine,
+}
+
+impl SyntaxErrorKind {
+    fn description(self) -> &'static str {
+        match self {
+            SyntaxErrorKind::InvalidUtf8 => "invalid utf-8",
+            SyntaxErrorKind::MissingSeparator => "expected ` -> ` separator",
+            SyntaxErrorKind::MissingClassTerminator => "class line must end with `:`",
+            SyntaxErrorKind::InvalidLineNumber => "invalid line number",
+            SyntaxErrorKind::UnclosedArguments => "unclosed argument list",
+            SyntaxErrorKind::UnexpectedEndOfLine => "unexpected end of line",
+        }
+    }
+}
+
+impl fmt::Display for SyntaxErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+/// Error returned by [`ProguardMapping::parse_strict`].
+///
+/// This points to the exact location of the first malformed record.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SyntaxError<'s> {
+    line: &'s [u8],
+    line_number: usize,
+    column: usize,
+    kind: SyntaxErrorKind,
+}
+
+impl<'s> SyntaxError<'s> {
+    /// The offending line that caused the error.
+    pub fn line(&self) -> &'s [u8] {
+        self.line
+    }
+
+    /// The line number of the offending line, 1-based.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    /// The column at which the error was detected, as a 1-based byte offset.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// The reason for the error.
+    pub fn kind(&self) -> SyntaxErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for SyntaxError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line_number, self.column, self.kind)
+    }
+}
+
+impl std::error::Error for SyntaxError<'_> {}
+
+/// A compiler version, as found in the `# compiler_version: 8.3.37` header.
+///
+/// Versions compare by their numeric components. Missing minor or patch
+/// components are `0`, and suffixes such as `-dev` are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::CompilerVersion;
+///
+/// let version = CompilerVersion::parse("8.3.37-dev").unwrap();
+/// assert_eq!(version, CompilerVersion::new(8, 3, 37));
+/// assert!(version >= CompilerVersion::new(8, 2, 0));
+/// assert_eq!(CompilerVersion::parse("main"), None);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CompilerVersion {
+    /// The major version.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+    /// The patch version.
+    pub patch: u32,
+}
+
+impl CompilerVersion {
+    /// Create a new CompilerVersion.
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses a version such as `8.3.37`.
+    ///
+    /// Returns `None` if the version does not start with a number.
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim();
+        let version = version.split(['-', '+', ' ']).next().unwrap_or_default();
+        let mut components = version.split('.').map(str::parse::<u32>);
+        let major = components.next()?.ok()?;
+        let minor = components.next().transpose().ok()?.unwrap_or(0);
+        let patch = components.next().transpose().ok()?.unwrap_or(0);
+        Some(Self::new(major, minor, patch))
+    }
+}
+
+impl fmt::Display for CompilerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
 /// Summary of a mapping file.
 pub struct MappingSummary<'s> {
     compiler: Option<&'s str>,
@@ -114,6 +266,11 @@ impl<'s> MappingSummary<'s> {
         self.compiler_version
     }
 
+    /// Returns the version of the compiler, parsed into a comparable [`CompilerVersion`].
+    pub fn compiler_version_typed(&self) -> Option<CompilerVersion> {
+        self.compiler_version.and_then(CompilerVersion::parse)
+    }
+
     /// Returns the min-api value.
     pub fn min_api(&self) -> Option<u32> {
         self.min_api
@@ -130,6 +287,85 @@ impl<'s> MappingSummary<'s> {
     }
 }
 
+/// The `# key: value` headers preceding the first class of a mapping file.
+///
+/// Besides the headers written by R8, such as `# compiler: R8`, build
+/// pipelines can stamp custom provenance headers into a mapping using a
+/// [`MappingWriter`](crate::MappingWriter). R8 JSON metadata is not included.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::ProguardMapping;
+///
+/// let mapping = ProguardMapping::new(
+///     b"# compiler: R8\n# application_id: com.example\n# version_code: 42\na -> b:",
+/// );
+/// let headers = mapping.headers();
+///
+/// assert_eq!(headers.get("compiler"), Some("R8"));
+/// assert_eq!(headers.application_id(), Some("com.example"));
+/// assert_eq!(headers.version_code(), Some(42));
+/// assert_eq!(headers.build_fingerprint(), None);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MappingHeaders<'s> {
+    headers: Vec<(&'s str, Option<&'s str>)>,
+}
+
+impl<'s> MappingHeaders<'s> {
+    fn new(mapping: &ProguardMapping<'s>) -> Self {
+        let headers = mapping
+            .leading_headers()
+            .filter(|(key, _)| !key.starts_with('{'))
+            .collect();
+        Self { headers }
+    }
+
+    /// Returns the value of the header `key`.
+    ///
+    /// If the header is repeated, the last value is returned.
+    pub fn get(&self, key: &str) -> Option<&'s str> {
+        self.headers
+            .iter()
+            .rev()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, value)| *value)
+    }
+
+    /// Returns the value of the header `key`, parsed into `T`.
+    ///
+    /// Returns `None` if the header is missing or can not be parsed.
+    pub fn get_parsed<T: FromStr>(&self, key: &str) -> Option<T> {
+        self.get(key)?.trim().parse().ok()
+    }
+
+    /// Returns the `# application_id` header, the package name of the app.
+    pub fn application_id(&self) -> Option<&'s str> {
+        self.get("application_id")
+    }
+
+    /// Returns the `# version_code` header.
+    pub fn version_code(&self) -> Option<u64> {
+        self.get_parsed("version_code")
+    }
+
+    /// Returns the `# min_api` header, the minimum API level of the app.
+    pub fn min_api(&self) -> Option<u32> {
+        self.get_parsed("min_api")
+    }
+
+    /// Returns the `# build_fingerprint` header, which identifies the build.
+    pub fn build_fingerprint(&self) -> Option<&'s str> {
+        self.get("build_fingerprint")
+    }
+
+    /// Iterates over all headers, in file order.
+    pub fn iter(&self) -> impl Iterator<Item = (&'s str, Option<&'s str>)> + '_ {
+        self.headers.iter().copied()
+    }
+}
+
 /// A Proguard Mapping file.
 #[derive(Clone, Default)]
 pub struct ProguardMapping<'s> {
@@ -148,106 +384,816 @@ impl<'s> ProguardMapping<'s> {
         Self { source }
     }
 
-    /// Whether the mapping file is indeed valid.
+    /// The raw bytes of the mapping file.
+    pub(crate) fn source(&self) -> &'s [u8] {
+        self.source
+    }
+
+    /// Whether the mapping file is indeed valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let valid = ProguardMapping::new(b"a -> b:\n    void method() -> b");
+    /// assert_eq!(valid.is_valid(), true);
+    ///
+    /// let invalid = ProguardMapping::new(
+    ///     br#"
+    /// # looks: like
+    /// a -> proguard:
+    ///   mapping but is -> not
+    /// "#,
+    /// );
+    /// assert_eq!(invalid.is_valid(), false);
+    ///
+    /// let truncated = ProguardMapping::new(b"a -> b:\n    void method() -> b\n    void meth");
+    /// assert_eq!(truncated.is_valid(), false);
+    /// ```
+    pub fn is_valid(&self) -> bool {
+        if self.is_truncated() {
+            return false;
+        }
+        // In order to not parse the whole file, we look for a class followed by
+        // a member in the first 50 lines, which is a good heuristic.
+        let mut has_class_line = false;
+        for record in self.iter().take(50) {
+            match record {
+                Ok(ProguardRecord::Class { .. }) => {
+                    has_class_line = true;
+                }
+                Ok(ProguardRecord::Field { .. }) | Ok(ProguardRecord::Method { .. })
+                    if has_class_line =>
+                {
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Whether the mapping file appears to be cut off in the middle of a record.
+    ///
+    /// A file that ends with a line terminator is never considered truncated.
+    /// Otherwise, the trailing line is checked for being a dangling indent or
+    /// an incomplete record.
+    pub(crate) fn is_truncated(&self) -> bool {
+        let last_line = match self.source.iter().rposition(|c| *c == b'\n' || *c == b'\r') {
+            Some(pos) => &self.source[pos + 1..],
+            None => self.source,
+        };
+        if last_line.is_empty() {
+            return false;
+        }
+        if last_line.iter().all(u8::is_ascii_whitespace) {
+            return true;
+        }
+        match ProguardRecord::try_parse(last_line) {
+            Ok(ProguardRecord::Field { obfuscated, .. })
+            | Ok(ProguardRecord::Method { obfuscated, .. }) => obfuscated.is_empty(),
+            Ok(_) => false,
+            Err(_) => true,
+        }
+    }
+
+    /// Returns a summary of the file.
+    pub fn summary(&self) -> MappingSummary<'_> {
+        MappingSummary::new(self)
+    }
+
+    /// Returns the headers preceding the first class.
+    ///
+    /// Contrary to [`summary`](Self::summary), this does not read the whole file.
+    pub fn headers(&self) -> MappingHeaders<'s> {
+        MappingHeaders::new(self)
+    }
+
+    /// Whether the mapping file contains line info.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let with = ProguardMapping::new(b"a -> b:\n    1:1:void method() -> a");
+    /// assert_eq!(with.has_line_info(), true);
+    ///
+    /// let without = ProguardMapping::new(b"a -> b:\n    void method() -> b");
+    /// assert_eq!(without.has_line_info(), false);
+    /// ```
+    pub fn has_line_info(&self) -> bool {
+        for record in self.iter() {
+            if let Ok(ProguardRecord::Method { line_mapping, .. }) = record {
+                if line_mapping.is_some() {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    /// Calculates the UUID of the mapping file.
+    ///
+    /// The UUID is generated from a file checksum, so it changes whenever the
+    /// file does, including when a `# uuid` header is added. A mapping that
+    /// has such a header is instead identified by its
+    /// [`header_uuid`](Self::header_uuid) in a
+    /// [`MappingArchive`](crate::MappingArchive), a
+    /// [`ProguardCache`](crate::ProguardCache) and its
+    /// [`DebugFileMetadata`](crate::DebugFileMetadata).
+    #[cfg(feature = "uuid")]
+    pub fn uuid(&self) -> Uuid {
+        self.checksum_uuid()
+    }
+
+    /// Returns the UUID of a `# uuid: <value>` header preceding the first class.
+    ///
+    /// The value must be a hyphenated UUID. Such a header can be added with
+    /// [`ProguardMappingBuf::insert_uuid_header`](crate::ProguardMappingBuf::insert_uuid_header)
+    /// or [`MappingWriter::write_uuid_header`](crate::MappingWriter::write_uuid_header),
+    /// and is read without reading the rest of the file. Contrary to
+    /// [`uuid`](Self::uuid), the value is not derived from the contents of the
+    /// file, so it must only be trusted for files from a trusted source.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(
+    ///     b"# compiler: R8\n# uuid: 5cd8e873-1127-5276-81b7-8ff25043ecfd\na -> b:",
+    /// );
+    /// assert_eq!(
+    ///     mapping.header_uuid().unwrap().to_string(),
+    ///     "5cd8e873-1127-5276-81b7-8ff25043ecfd"
+    /// );
+    /// assert_ne!(mapping.uuid(), mapping.header_uuid().unwrap());
+    /// ```
+    #[cfg(feature = "uuid")]
+    pub fn header_uuid(&self) -> Option<Uuid> {
+        self.header_uuid_string()?.parse().ok()
+    }
+
+    /// The length in bytes of the header block preceding the first class.
+    ///
+    /// The header block consists of the leading `#` comment lines, along with
+    /// any empty lines between them, including their line terminators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let source = b"# compiler: R8\n# min_api: 24\n\na.A -> b:\n";
+    /// let mapping = proguard::ProguardMapping::new(source);
+    /// assert_eq!(mapping.header_len(), 30);
+    /// ```
+    pub fn header_len(&self) -> usize {
+        let mut slice = self.source;
+        while !slice.is_empty() {
+            let (line, rest) = split_line(slice);
+            if !line.is_empty() && !line.starts_with(b"#") {
+                break;
+            }
+            slice = rest;
+        }
+        self.source.len() - slice.len()
+    }
+
+    /// The mapping without its leading header block.
+    ///
+    /// This is useful to hash or compare mappings independent of headers
+    /// such as `# compiler_version:` or `# pg_map_hash:`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = proguard::ProguardMapping::new(b"# pg_map_id: 1\na.A -> b:\n");
+    /// let body = mapping.body();
+    /// assert_eq!(body.header_len(), 0);
+    /// assert_eq!(body.classes().count(), 1);
+    /// ```
+    pub fn body(&self) -> ProguardMapping<'s> {
+        ProguardMapping::new(&self.source[self.header_len()..])
+    }
+
+    /// Returns the mapping in canonical form, along with a stable content hash.
+    ///
+    /// Mappings which only differ in line endings, indentation, empty lines or
+    /// the order of their leading headers have the same canonical form. See
+    /// [`CanonicalMapping`] for the details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let a = ProguardMapping::new(b"# min_api: 24\n# compiler: R8\na.A -> a:\n    void m() -> b\n");
+    /// let b = ProguardMapping::new(b"# compiler: R8\r\n# min_api: 24\r\n\r\na.A -> a:\r\n\tvoid m() -> b");
+    ///
+    /// let canonical = a.canonicalize();
+    /// assert_eq!(canonical.sha256(), b.canonicalize().sha256());
+    /// assert_eq!(
+    ///     canonical.as_bytes(),
+    ///     b"# compiler: R8\n# min_api: 24\na.A -> a:\n    void m() -> b\n"
+    /// );
+    /// ```
+    pub fn canonicalize(&self) -> CanonicalMapping {
+        CanonicalMapping::new(self)
+    }
+
+    /// Iterates over the headers preceding the first class.
+    pub(crate) fn leading_headers(&self) -> impl Iterator<Item = (&'s str, Option<&'s str>)> {
+        ProguardMapping::new(&self.source[..self.header_len()])
+            .lines()
+            .filter_map(|(_, line)| match ProguardRecord::try_parse(line) {
+                Ok(ProguardRecord::Header { key, value }) => Some((key, value)),
+                _ => None,
+            })
+    }
+
+    /// Returns the lowercase value of a leading `# uuid` header, if it is a
+    /// hyphenated UUID.
+    ///
+    /// This does not depend on the `uuid` feature.
+    pub(crate) fn header_uuid_string(&self) -> Option<String> {
+        self.leading_headers()
+            .find_map(|(key, value)| match (key, value) {
+                ("uuid", Some(value)) if is_hyphenated_uuid(value) => Some(value),
+                _ => None,
+            })
+            .map(str::to_ascii_lowercase)
+    }
+
+    /// The UUID identifying the mapping file.
+    ///
+    /// This is the [`header_uuid`](Self::header_uuid) if there is one, and the
+    /// checksum-based [`uuid`](Self::uuid) otherwise.
+    #[cfg(feature = "uuid")]
+    pub(crate) fn identifier_uuid(&self) -> Uuid {
+        self.header_uuid().unwrap_or_else(|| self.checksum_uuid())
+    }
+
+    /// The hyphenated UUID identifying the mapping file.
+    ///
+    /// This is the same UUID as [`identifier_uuid`](Self::identifier_uuid),
+    /// but does not depend on the `uuid` feature.
+    pub(crate) fn identifier_uuid_string(&self) -> String {
+        self.header_uuid_string()
+            .unwrap_or_else(|| self.checksum_uuid_string())
+    }
+
+    /// Calculates the UUID of the mapping file from its checksum.
+    #[cfg(feature = "uuid")]
+    pub(crate) fn checksum_uuid(&self) -> Uuid {
+        lazy_static::lazy_static! {
+            static ref NAMESPACE: Uuid = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"guardsquare.com");
+        }
+        // this internally only operates on bytes, so this is safe to do
+        Uuid::new_v5(&NAMESPACE, self.source)
+    }
+
+    /// Calculates the hyphenated UUID of the mapping file from its checksum.
+    ///
+    /// This is the same UUID as [`checksum_uuid`](Self::checksum_uuid), but
+    /// does not depend on the `uuid` feature.
+    pub(crate) fn checksum_uuid_string(&self) -> String {
+        // the version 5 UUID of `guardsquare.com` in the DNS namespace.
+        const NAMESPACE: [u8; 16] = [
+            0x4f, 0x44, 0xf3, 0x0f, 0x24, 0xbe, 0x53, 0xd0, 0xba, 0xb6, 0xf4, 0x7c, 0x71, 0x20,
+            0xad, 0x6c,
+        ];
+        let mut hasher = sha1_smol::Sha1::new();
+        hasher.update(&NAMESPACE);
+        hasher.update(self.source);
+        let mut bytes = hasher.digest().bytes();
+        bytes[6] = (bytes[6] & 0x0f) | 0x50;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+        let mut uuid = String::with_capacity(36);
+        for (index, byte) in bytes[..16].iter().enumerate() {
+            if let 4 | 6 | 8 | 10 = index {
+                uuid.push('-');
+            }
+            uuid.push_str(&format!("{:02x}", byte));
+        }
+        uuid
+    }
+
+    /// Writes the mapping as a SQLite database to `path`.
+    ///
+    /// The database is created with indexed `classes`, `members` and `lines`
+    /// tables, which allows ad-hoc analysis of a mapping using SQL. Fails if
+    /// the database at `path` already contains any of these tables.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    1:1:void method():10 -> a");
+    /// let path = std::env::temp_dir().join("proguard-export-doctest.db");
+    /// # let _ = std::fs::remove_file(&path);
+    /// mapping.export_sqlite(&path).unwrap();
+    /// # std::fs::remove_file(&path).unwrap();
+    /// ```
+    #[cfg(feature = "sqlite")]
+    pub fn export_sqlite<P: AsRef<std::path::Path>>(&self, path: P) -> rusqlite::Result<()> {
+        crate::sqlite::export(self, path)
+    }
+
+    /// Verifies the mapping file against the metadata in `manifest`.
+    ///
+    /// Returns all the mismatches between the manifest and the mapping file,
+    /// which is empty if the mapping matches.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ManifestMismatch, MappingManifest, ProguardMapping};
+    ///
+    /// let mapping = ProguardMapping::new(b"a -> b:\n    void method() -> b\n");
+    /// let manifest = MappingManifest {
+    ///     size: Some(100),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(
+    ///     mapping.verify_manifest(&manifest),
+    ///     vec![ManifestMismatch::Size {
+    ///         expected: 100,
+    ///         actual: 31
+    ///     }]
+    /// );
+    /// ```
+    pub fn verify_manifest(&self, manifest: &MappingManifest) -> Vec<ManifestMismatch> {
+        manifest::verify(self, manifest)
+    }
+
+    /// Returns the debug file metadata of the mapping file.
+    ///
+    /// This reads and hashes the whole file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a -> b:\n    1:1:void method() -> a\n");
+    /// let metadata = mapping.debug_file_metadata();
+    /// assert!(metadata.has_line_info);
+    /// assert_eq!(metadata.size, 35);
+    /// assert!(metadata.to_json().contains(r#""object_type":"proguard""#));
+    /// ```
+    pub fn debug_file_metadata(&self) -> DebugFileMetadata {
+        DebugFileMetadata::new(self)
+    }
+
+    /// Parses the complete mapping file, stopping at the first malformed record.
+    ///
+    /// Contrary to [`iter`](Self::iter), which yields errors alongside
+    /// records, this fails on the first invalid line, and reports its exact
+    /// location. This is useful to reject invalid mapping files outright.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapping, SyntaxErrorKind};
+    ///
+    /// let mapping = ProguardMapping::new(b"a -> b:\n    void method() -> b\n");
+    /// assert_eq!(mapping.parse_strict().unwrap().len(), 2);
+    ///
+    /// let mapping = ProguardMapping::new(b"a -> b:\n    1:x:void method() -> b\n");
+    /// let error = mapping.parse_strict().unwrap_err();
+    /// assert_eq!(error.line_number(), 2);
+    /// assert_eq!(error.column(), 7);
+    /// assert_eq!(error.kind(), SyntaxErrorKind::InvalidLineNumber);
+    /// ```
+    pub fn parse_strict(&self) -> Result<Vec<ProguardRecord<'s>>, SyntaxError<'s>> {
+        trace_span!(DEBUG, "proguard.parse", len = self.source.len());
+        self.lines()
+            .map(|(line_number, line)| parse_line(line_number, line))
+            .collect()
+    }
+
+    /// Parses the complete mapping file, collecting all malformed records.
+    ///
+    /// This returns all valid records, along with a [`SyntaxError`] for each
+    /// invalid line, in file order. Contrary to
+    /// [`parse_strict`](Self::parse_strict), parsing continues after an
+    /// invalid line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapping, SyntaxErrorKind};
+    ///
+    /// let mapping = ProguardMapping::new(b"a -> b:\n    1:x:void method() -> b\n    int c -> d\nx");
+    /// let (records, errors) = mapping.parse_all();
+    /// assert_eq!(records.len(), 2);
+    /// assert_eq!(
+    ///     errors
+    ///         .iter()
+    ///         .map(|e| (e.line_number(), e.kind()))
+    ///         .collect::<Vec<_>>(),
+    ///     vec![
+    ///         (2, SyntaxErrorKind::InvalidLineNumber),
+    ///         (4, SyntaxErrorKind::MissingSeparator)
+    ///     ]
+    /// );
+    /// ```
+    pub fn parse_all(&self) -> (Vec<ProguardRecord<'s>>, Vec<SyntaxError<'s>>) {
+        trace_span!(DEBUG, "proguard.parse", len = self.source.len());
+        let mut records = Vec::new();
+        let mut errors = Vec::new();
+
+        for (line_number, line) in self.lines() {
+            match parse_line(line_number, line) {
+                Ok(record) => records.push(record),
+                Err(error) => errors.push(error),
+            }
+        }
+        trace_event!(
+            DEBUG,
+            records = records.len(),
+            errors = errors.len(),
+            "parsed mapping"
+        );
+
+        (records, errors)
+    }
+
+    /// Checks the mapping file for suspicious content.
+    ///
+    /// This reports issues which do not make the file invalid, but which
+    /// likely point to a problem with the tool that produced it:
+    /// overlapping minified line ranges, duplicate members, classes without
+    /// members, headers after the first class, and files that appear to be
+    /// truncated.
+    ///
+    /// Warnings are returned in file order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{LintKind, ProguardMapping};
+    ///
+    /// let mapping = ProguardMapping::new(b"a -> b:\nc -> d:\n    void method() -> b");
+    /// let warnings = mapping.lint();
+    ///
+    /// assert_eq!(warnings.len(), 1);
+    /// assert_eq!(warnings[0].line_number(), 1);
+    /// assert_eq!(warnings[0].kind(), &LintKind::EmptyClass { class: "b" });
+    /// ```
+    pub fn lint(&self) -> Vec<LintWarning<'s>> {
+        lint::lint(self)
+    }
+
+    /// Create an Iterator over the [`ClassMapping`](crate::ClassMapping)s of this file.
+    ///
+    /// Classes are yielded in file order. Only class lines are parsed
+    /// up-front, the members of each class are parsed on demand.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    void method() -> b\na.B -> b:");
+    /// let classes: Vec<_> = mapping
+    ///     .classes()
+    ///     .map(|class| (class.original(), class.obfuscated()))
+    ///     .collect();
+    ///
+    /// assert_eq!(classes, vec![("a.A", "a"), ("a.B", "b")]);
+    /// ```
+    pub fn classes(&self) -> ClassIter<'s> {
+        ClassIter::new(self)
+    }
+
+    /// Create an Iterator over the segments of concatenated mapping files.
+    ///
+    /// Some build pipelines concatenate the mappings of multiple modules into
+    /// a single file, each starting with its own header block. A new segment
+    /// starts at every header which follows a class, except for R8 metadata
+    /// of classes and members. A file which was not concatenated consists of
+    /// a single segment, and an empty file has no segments.
+    ///
+    /// Iterating over classes and remapping works across all segments, so
+    /// this is only needed to look at the headers of each segment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(
+    ///     b"# pg_map_id: 1\na.A -> a:\n    void m() -> a\n# pg_map_id: 2\nb.B -> b:\n",
+    /// );
+    /// let ids: Vec<_> = mapping
+    ///     .segments()
+    ///     .map(|segment| segment.headers().get("pg_map_id"))
+    ///     .collect();
+    /// assert_eq!(ids, vec![Some("1"), Some("2")]);
+    /// assert_eq!(mapping.classes().count(), 2);
+    /// ```
+    pub fn segments(&self) -> SegmentIter<'s> {
+        SegmentIter {
+            source: self.source,
+            lines: self.lines(),
+            start: 0,
+        }
+    }
+
+    /// Create an Iterator over the classes whose original name matches `pattern`.
+    ///
+    /// If `pattern` contains `*` or `?`, it is a glob which needs to match the
+    /// whole name, where `*` matches any number of characters, including `.`
+    /// and `$`, and `?` matches a single character. Otherwise, it matches any
+    /// part of the name. Matching ignores ASCII case, and classes are yielded
+    /// in file order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(
+    ///     b"com.example.MainActivity -> a:\ncom.example.MainFragment -> b:\ncom.other.Main -> c:",
+    /// );
+    /// let names = |pattern| {
+    ///     mapping
+    ///         .find_classes_matching(pattern)
+    ///         .map(|class| class.obfuscated())
+    ///         .collect::<Vec<_>>()
+    /// };
+    ///
+    /// assert_eq!(names("fragment"), vec!["b"]);
+    /// assert_eq!(names("com.example.*"), vec!["a", "b"]);
+    /// assert_eq!(names("*.Main"), vec!["c"]);
+    /// ```
+    pub fn find_classes_matching<'p>(&self, pattern: &'p str) -> ClassMatchIter<'s, 'p> {
+        ClassMatchIter::new(self, pattern)
+    }
+
+    /// Create an Iterator over the `(original, obfuscated)` names of all classes.
+    ///
+    /// This only parses class lines and skips over all other records, which
+    /// makes it much cheaper than [`iter`](Self::iter) for building rename
+    /// tables. Classes are yielded in file order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    void method() -> b\na.B -> b:");
+    /// let names: Vec<_> = mapping.class_names().collect();
+    ///
+    /// assert_eq!(names, vec![("a.A", "a"), ("a.B", "b")]);
+    /// ```
+    pub fn class_names(&self) -> ClassNameIter<'s> {
+        ClassNameIter::new(self)
+    }
+
+    /// Returns the number of classes in the mapping.
+    ///
+    /// This only parses class lines, just like [`class_names`](Self::class_names),
+    /// which makes it much cheaper than [`summary`](Self::summary).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    void method() -> b\na.B -> b:");
+    /// assert_eq!(mapping.class_count(), 2);
+    /// ```
+    pub fn class_count(&self) -> usize {
+        self.class_names().count()
+    }
+
+    /// Create an Iterator over the [`ClassHandle`](crate::ClassHandle)s of this file.
+    ///
+    /// Handles record the byte range of each class block, and can be used to
+    /// open the class later on using [`class_at`](Self::class_at). Classes are
+    /// yielded in file order.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    void method() -> b\na.B -> b:");
+    /// let handles: Vec<_> = mapping.class_handles().collect();
+    /// assert_eq!(handles[1].name, "b");
+    /// assert_eq!(handles[1].original, "a.B");
+    /// assert_eq!(handles[1].byte_range, 33..42);
+    ///
+    /// let class = mapping.class_at(handles[0].byte_range.clone()).unwrap();
+    /// assert_eq!(class.original(), "a.A");
+    /// assert_eq!(class.methods().count(), 1);
+    /// ```
+    pub fn class_handles(&self) -> ClassHandleIter<'s> {
+        ClassHandleIter::new(self)
+    }
+
+    /// Returns the class whose block occupies `byte_range` of the file.
+    ///
+    /// The range is usually taken from a [`ClassHandle`](crate::ClassHandle)
+    /// of the same file. Returns `None` if the range is out of bounds, or
+    /// does not start with a class line.
+    pub fn class_at(&self, byte_range: Range<usize>) -> Option<ClassMapping<'s>> {
+        let block = self.source.get(byte_range)?;
+        let (line, _) = split_line(block);
+        let (original, obfuscated) = parse_class_line(line)?;
+        Some(ClassMapping::new(
+            original,
+            obfuscated,
+            &block[line.len()..],
+        ))
+    }
+
+    /// Returns the raw text block of the class with the obfuscated name `obfuscated`.
+    ///
+    /// The block consists of the class line and all of its members, including
+    /// their line terminators, exactly as they appear in the file. It is a
+    /// valid mapping file on its own, so it can be stored or transmitted
+    /// instead of the whole mapping. If the mapping contains the class more
+    /// than once, the last block is returned, which is the one that
+    /// [`ProguardMapper`](crate::ProguardMapper) uses.
     ///
     /// # Examples
     ///
     /// ```
     /// use proguard::ProguardMapping;
     ///
-    /// let valid = ProguardMapping::new(b"a -> b:\n    void method() -> b");
-    /// assert_eq!(valid.is_valid(), true);
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    void method() -> b\na.B -> b:\n");
+    /// assert_eq!(mapping.extract_class("b"), Some(&b"a.B -> b:\n"[..]));
+    /// assert_eq!(mapping.extract_class("c"), None);
     ///
-    /// let invalid = ProguardMapping::new(
-    ///     br#"
-    /// # looks: like
-    /// a -> proguard:
-    ///   mapping but(is) -> not
-    /// "#,
-    /// );
-    /// assert_eq!(invalid.is_valid(), false);
+    /// let block = mapping.extract_class("a").unwrap();
+    /// assert_eq!(block, b"a.A -> a:\n    void method() -> b\n");
+    /// assert_eq!(ProguardMapping::new(block).classes().count(), 1);
     /// ```
-    pub fn is_valid(&self) -> bool {
-        // In order to not parse the whole file, we look for a class followed by
-        // a member in the first 50 lines, which is a good heuristic.
-        let mut has_class_line = false;
-        for record in self.iter().take(50) {
-            match record {
-                Ok(ProguardRecord::Class { .. }) => {
-                    has_class_line = true;
-                }
-                Ok(ProguardRecord::Field { .. }) | Ok(ProguardRecord::Method { .. })
-                    if has_class_line =>
-                {
-                    return true;
-                }
-                _ => {}
-            }
-        }
-        false
+    pub fn extract_class(&self, obfuscated: &str) -> Option<&'s [u8]> {
+        let handle = self
+            .class_handles()
+            .filter(|handle| handle.name == obfuscated)
+            .last()?;
+        self.source.get(handle.byte_range)
     }
 
-    /// Returns a summary of the file.
-    pub fn summary(&self) -> MappingSummary<'_> {
-        MappingSummary::new(self)
+    /// Create an Iterator over the non-empty lines, along with their line number.
+    pub(crate) fn lines(&self) -> LineIter<'s> {
+        LineIter {
+            slice: self.source,
+            line_number: 0,
+        }
     }
 
-    /// Whether the mapping file contains line info.
+    /// Create an Iterator over [`RawRecord`](crate::RawRecord)s.
+    ///
+    /// Contrary to [`iter`](Self::iter), this also parses lines which are not
+    /// valid utf-8. Records are yielded in file order.
     ///
     /// # Examples
     ///
     /// ```
     /// use proguard::ProguardMapping;
     ///
-    /// let with = ProguardMapping::new(b"a -> b:\n    1:1:void method() -> a");
-    /// assert_eq!(with.has_line_info(), true);
+    /// let mapping = ProguardMapping::new(b"a.\xffoo -> a:\n    void m\xfe() -> b");
+    /// let names: Vec<_> = mapping
+    ///     .iter_raw()
+    ///     .filter_map(|record| record.ok()?.original_lossy())
+    ///     .collect();
     ///
-    /// let without = ProguardMapping::new(b"a -> b:\n    void method() -> b");
-    /// assert_eq!(without.has_line_info(), false);
+    /// assert_eq!(names, vec!["a.\u{fffd}oo", "m\u{fffd}"]);
     /// ```
-    pub fn has_line_info(&self) -> bool {
-        for record in self.iter() {
-            if let Ok(ProguardRecord::Method { line_mapping, .. }) = record {
-                if line_mapping.is_some() {
-                    return true;
-                }
-            }
-        }
-        false
-    }
-
-    /// Calculates the UUID of the mapping file.
-    ///
-    /// The UUID is generated from a file checksum.
-    #[cfg(feature = "uuid")]
-    pub fn uuid(&self) -> Uuid {
-        lazy_static::lazy_static! {
-            static ref NAMESPACE: Uuid = Uuid::new_v5(&Uuid::NAMESPACE_DNS, b"guardsquare.com");
-        }
-        // this internally only operates on bytes, so this is safe to do
-        Uuid::new_v5(&NAMESPACE, self.source)
+    pub fn iter_raw(&self) -> RawRecordIter<'s> {
+        RawRecordIter::new(self)
     }
 
     /// Create an Iterator over [`ProguardRecord`]s.
     ///
+    /// Records are yielded in file order.
+    ///
     /// [`ProguardRecord`]: enum.ProguardRecord.html
     pub fn iter(&self) -> ProguardRecordIter<'s> {
         ProguardRecordIter { slice: self.source }
     }
+
+    /// Create an Iterator over [`ProguardRecord`]s.
+    ///
+    /// This is the same as [`iter`](Self::iter). A `&ProguardMapping` can
+    /// also be used directly in a `for` loop.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{ProguardMapping, ProguardRecord};
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    int field -> a");
+    /// assert_eq!(mapping.records().count(), 2);
+    ///
+    /// let mut fields = 0;
+    /// for record in &mapping {
+    ///     if let Ok(ProguardRecord::Field { .. }) = record {
+    ///         fields += 1;
+    ///     }
+    /// }
+    /// assert_eq!(fields, 1);
+    /// ```
+    pub fn records(&self) -> ProguardRecordIter<'s> {
+        self.iter()
+    }
+
+    /// Drives a [`MappingVisitor`] over all records in a single pass.
+    ///
+    /// Contrary to [`iter`](Self::iter), the visitor is called with the
+    /// contents of each record directly, so consumers building their own
+    /// index don't have to match on every [`ProguardRecord`]. Records are
+    /// visited in file order, and malformed lines are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{MappingVisitor, MethodMapping, ProguardMapping};
+    ///
+    /// #[derive(Default)]
+    /// struct MethodCounter<'s> {
+    ///     counts: Vec<(&'s str, usize)>,
+    /// }
+    ///
+    /// impl<'s> MappingVisitor<'s> for MethodCounter<'s> {
+    ///     fn visit_class(&mut self, original: &'s str, _obfuscated: &'s str) {
+    ///         self.counts.push((original, 0));
+    ///     }
+    ///
+    ///     fn visit_method(&mut self, _method: &MethodMapping<'s>) {
+    ///         if let Some((_, count)) = self.counts.last_mut() {
+    ///             *count += 1;
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let mapping = ProguardMapping::new(b"a.A -> a:\n    void a() -> a\n    void b() -> b\na.B -> b:");
+    /// let mut counter = MethodCounter::default();
+    /// mapping.visit(&mut counter);
+    ///
+    /// assert_eq!(counter.counts, vec![("a.A", 2), ("a.B", 0)]);
+    /// ```
+    pub fn visit<V>(&self, visitor: &mut V)
+    where
+        V: MappingVisitor<'s> + ?Sized,
+    {
+        visitor::visit(self.source, visitor)
+    }
 }
 
 /// Split the input `slice` on line terminators.
 ///
-/// This is basically [`str::lines`], except it works on a byte slice.
-/// Also NOTE that it does not treat `\r\n` as a single line ending.
-fn split_line(slice: &[u8]) -> (&[u8], &[u8]) {
-    let pos = slice.iter().position(|c| *c == b'\n' || *c == b'\r');
-    match pos {
+/// This is basically [`str::lines`], except it works on a byte slice and also
+/// splits on a lone `\r`.
+pub(crate) fn split_line(slice: &[u8]) -> (&[u8], &[u8]) {
+    match find_line_terminator(slice) {
+        Some(pos) if slice[pos..].starts_with(b"\r\n") => (&slice[0..pos], &slice[pos + 2..]),
         Some(pos) => (&slice[0..pos], &slice[pos + 1..]),
         None => (slice, &[]),
     }
 }
 
+/// Returns the position of the first `\n` or `\r` in `slice`.
+///
+/// This is a hot path when iterating over large files, so it uses the
+/// vectorized search of `memchr`.
+pub(crate) fn find_line_terminator(slice: &[u8]) -> Option<usize> {
+    memchr::memchr2(b'\n', b'\r', slice)
+}
+
+/// An Iterator yielding non-empty lines along with their 1-based line number.
+#[derive(Clone, Debug)]
+pub(crate) struct LineIter<'s> {
+    slice: &'s [u8],
+    line_number: usize,
+}
+
+impl<'s> Iterator for LineIter<'s> {
+    type Item = (usize, &'s [u8]);
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.slice.is_empty() {
+            let (line, rest) = split_line(self.slice);
+            self.slice = rest;
+            self.line_number += 1;
+
+            if !line.is_empty() {
+                return Some((self.line_number, line));
+            }
+        }
+        None
+    }
+}
+
 /// An Iterator yielding [`ProguardRecord`]s, created by [`ProguardMapping::iter`].
 ///
 /// [`ProguardRecord`]: enum.ProguardRecord.html
@@ -263,23 +1209,139 @@ impl<'s> fmt::Debug for ProguardRecordIter<'s> {
     }
 }
 
-impl<'s> Iterator for ProguardRecordIter<'s> {
-    type Item = Result<ProguardRecord<'s>, ParseError<'s>>;
-    fn next(&mut self) -> Option<Self::Item> {
-        // We loop here, ignoring empty lines, which is important also because
-        // `split_line` above would output an empty line for each `\r\n`.
+impl<'s> ProguardRecordIter<'s> {
+    /// Returns the next non-empty line.
+    pub(crate) fn next_line(&mut self) -> Option<&'s [u8]> {
+        // We loop here, ignoring empty lines.
         loop {
             let (line, rest) = split_line(self.slice);
             self.slice = rest;
 
             if !line.is_empty() {
-                return Some(ProguardRecord::try_parse(line));
+                return Some(line);
             }
             if rest.is_empty() {
                 return None;
             };
         }
     }
+
+    /// Returns the next line, including its line terminator, along with the
+    /// record parsed from it.
+    ///
+    /// Contrary to iterating over the records, empty lines are returned too,
+    /// without a record. Concatenating all lines reproduces the input exactly,
+    /// so rewriting tools can keep the lines of the records they do not
+    /// change, which the `Display` implementation of a record only does for
+    /// canonically formatted lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardMapping;
+    ///
+    /// let mapping = ProguardMapping::new(b"a -> b:\r\n\n\tint c -> d");
+    /// let mut iter = mapping.iter();
+    ///
+    /// let (line, record) = iter.next_with_line().unwrap();
+    /// assert_eq!(line, b"a -> b:\r\n");
+    /// assert_eq!(record.unwrap().unwrap().to_string(), "a -> b:");
+    ///
+    /// let (line, record) = iter.next_with_line().unwrap();
+    /// assert_eq!(line, b"\n");
+    /// assert!(record.is_none());
+    ///
+    /// let (line, record) = iter.next_with_line().unwrap();
+    /// assert_eq!(line, b"\tint c -> d");
+    /// assert_eq!(record.unwrap().unwrap().to_string(), "    int c -> d");
+    /// assert!(iter.next_with_line().is_none());
+    /// ```
+    pub fn next_with_line(&mut self) -> Option<(&'s [u8], Option<<Self as Iterator>::Item>)> {
+        if self.slice.is_empty() {
+            return None;
+        }
+        let (line, rest) = split_line(self.slice);
+        let raw = &self.slice[..self.slice.len() - rest.len()];
+        self.slice = rest;
+        Some((
+            raw,
+            (!line.is_empty()).then(|| ProguardRecord::try_parse(line)),
+        ))
+    }
+}
+
+impl<'s> Iterator for ProguardRecordIter<'s> {
+    type Item = Result<ProguardRecord<'s>, ParseError<'s>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_line().map(ProguardRecord::try_parse)
+    }
+}
+
+impl FusedIterator for ProguardRecordIter<'_> {}
+
+/// Whether `line` is a header that starts a new segment if it follows a class.
+///
+/// These are all headers except R8 metadata, which follows a class or member,
+/// but including the `com.android.tools.r8.mapping` metadata, which R8 writes
+/// at the top of a mapping file.
+pub(crate) fn is_segment_header(line: &[u8]) -> bool {
+    if !line.starts_with(b"#") {
+        return false;
+    }
+    match ProguardRecord::try_parse(line) {
+        Ok(ProguardRecord::Header { key, value }) => {
+            !key.starts_with('{')
+                || value.is_some_and(|value| value.starts_with("\"com.android.tools.r8.mapping\""))
+        }
+        _ => false,
+    }
+}
+
+/// An Iterator yielding the segments of concatenated mapping files, created
+/// by [`ProguardMapping::segments`].
+#[derive(Clone, Debug)]
+pub struct SegmentIter<'s> {
+    source: &'s [u8],
+    lines: LineIter<'s>,
+    /// The start of the current segment.
+    start: usize,
+}
+
+impl<'s> Iterator for SegmentIter<'s> {
+    type Item = ProguardMapping<'s>;
+    fn next(&mut self) -> Option<Self::Item> {
+        // whether the current segment has any classes or members.
+        let mut in_body = false;
+        for (_, line) in self.lines.by_ref() {
+            if !line.starts_with(b"#") {
+                in_body = true;
+                continue;
+            }
+            if in_body && is_segment_header(line) {
+                let end = line.as_ptr() as usize - self.source.as_ptr() as usize;
+                let segment = &self.source[self.start..end];
+                self.start = end;
+                return Some(ProguardMapping::new(segment));
+            }
+        }
+        if self.start < self.source.len() {
+            let segment = &self.source[self.start..];
+            self.start = self.source.len();
+            return Some(ProguardMapping::new(segment));
+        }
+        None
+    }
+}
+
+impl FusedIterator for SegmentIter<'_> {}
+
+impl<'s> IntoIterator for &ProguardMapping<'s> {
+    type Item = Result<ProguardRecord<'s>, ParseError<'s>>;
+    type IntoIter = ProguardRecordIter<'s>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
 }
 
 /// A proguard line mapping.
@@ -426,111 +1488,389 @@ impl<'s> ProguardRecord<'s> {
             line,
             kind: ParseErrorKind::Utf8Error(e),
         })?;
-        parse_mapping(line).ok_or_else(|| ParseError {
+        parse_mapping(line).map_err(|_| ParseError {
             line: line.as_ref(),
             kind: ParseErrorKind::ParseError("line is not a valid proguard record"),
         })
     }
+
+    /// Parses a line from a proguard mapping file, along with its trailing comment.
+    ///
+    /// Post-processing tools sometimes annotate records with a trailing
+    /// ` # comment`. [`try_parse`](Self::try_parse) ignores those comments,
+    /// whereas this also returns them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardRecord;
+    ///
+    /// let parsed = ProguardRecord::try_parse_with_comment(b"com.example.Foo -> a: # kept by rule X");
+    /// assert_eq!(
+    ///     parsed,
+    ///     Ok((
+    ///         ProguardRecord::Class {
+    ///             original: "com.example.Foo",
+    ///             obfuscated: "a",
+    ///         },
+    ///         Some("kept by rule X")
+    ///     ))
+    /// );
+    /// ```
+    pub fn try_parse_with_comment(
+        line: &'s [u8],
+    ) -> Result<(Self, Option<&'s str>), ParseError<'s>> {
+        let record = Self::try_parse(line)?;
+        // `try_parse` succeeded, so the line is valid utf-8.
+        let comment = str::from_utf8(line)
+            .ok()
+            .and_then(|line| split_comment(line).1);
+        Ok((record, comment))
+    }
+}
+
+impl fmt::Display for LineMapping {
+    /// Formats the minified line range as `startline:endline`.
+    ///
+    /// The original lines are written as part of the surrounding
+    /// [`ProguardRecord::Method`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.startline, self.endline)
+    }
+}
+
+impl fmt::Display for ProguardRecord<'_> {
+    /// Formats the record using the syntax of a mapping file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::ProguardRecord;
+    ///
+    /// let line = "    14:15:void a.Foo.bar(int,long):20:21 -> c";
+    /// let record = ProguardRecord::try_parse(line.as_bytes()).unwrap();
+    /// assert_eq!(record.to_string(), line);
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProguardRecord::Header { key, value } => match value {
+                // R8 metadata is JSON, which is split at its first `:`.
+                Some(value) if key.starts_with('{') => write!(f, "# {}:{}", key, value),
+                Some(value) => write!(f, "# {}: {}", key, value),
+                None => write!(f, "# {}", key),
+            },
+            ProguardRecord::Class {
+                original,
+                obfuscated,
+            } => write!(f, "{} -> {}:", original, obfuscated),
+            ProguardRecord::Field {
+                ty,
+                original,
+                obfuscated,
+            } => write!(f, "    {} {} -> {}", ty, original, obfuscated),
+            ProguardRecord::Method {
+                ty,
+                original,
+                obfuscated,
+                arguments,
+                original_class,
+                line_mapping,
+            } => {
+                f.write_str("    ")?;
+                if let Some(line_mapping) = line_mapping {
+                    write!(f, "{}:", line_mapping)?;
+                }
+                write!(f, "{} ", ty)?;
+                if let Some(original_class) = original_class {
+                    write!(f, "{}.", original_class)?;
+                }
+                write!(f, "{}({})", original, arguments)?;
+                if let Some(line_mapping) = line_mapping {
+                    if let Some(startline) = line_mapping.original_startline {
+                        write!(f, ":{}", startline)?;
+                    }
+                    if let Some(endline) = line_mapping.original_endline {
+                        write!(f, ":{}", endline)?;
+                    }
+                }
+                write!(f, " -> {}", obfuscated)
+            }
+        }
+    }
+}
+
+/// Parses a single line, reporting the location of a syntax error.
+fn parse_line(line_number: usize, line: &[u8]) -> Result<ProguardRecord<'_>, SyntaxError<'_>> {
+    let error = |column: usize, kind| SyntaxError {
+        line,
+        line_number,
+        column: column + 1,
+        kind,
+    };
+    let line_str =
+        str::from_utf8(line).map_err(|e| error(e.valid_up_to(), SyntaxErrorKind::InvalidUtf8))?;
+    parse_mapping(line_str).map_err(|(column, kind)| error(column, kind))
+}
+
+/// Splits off a trailing ` # comment` from a non-header line.
+///
+/// Returns the remaining line without trailing whitespace, and the trimmed
+/// comment.
+fn split_comment(line: &str) -> (&str, Option<&str>) {
+    if line.starts_with('#') {
+        return (line, None);
+    }
+    let pos = line
+        .match_indices('#')
+        .map(|(pos, _)| pos)
+        .find(|pos| line[..*pos].ends_with([' ', '\t']));
+    match pos {
+        Some(pos) => (line[..pos].trim_end(), Some(line[pos + 1..].trim())),
+        None => (line, None),
+    }
+}
+
+/// Whether `value` is a UUID in its hyphenated form, like `5cd8e873-1127-5276-81b7-8ff25043ecfd`.
+fn is_hyphenated_uuid(value: &str) -> bool {
+    value.len() == 36
+        && value.bytes().enumerate().all(|(index, byte)| match index {
+            8 | 13 | 18 | 23 => byte == b'-',
+            _ => byte.is_ascii_hexdigit(),
+        })
 }
 
 /// Parses a single line from a Proguard File.
 ///
-/// Returns `None` if the line could not be parsed.
-// TODO: this function is private here, but in the future it would be nice to
-// better elaborate parse errors.
-fn parse_mapping(mut line: &str) -> Option<ProguardRecord> {
-    if let Some(line) = line.strip_prefix('#') {
-        let mut split = line.splitn(2, ':');
-        let key = split.next()?.trim();
-        let value = split.next().map(|s| s.trim());
-        return Some(ProguardRecord::Header { key, value });
-    }
-    if !line.starts_with("    ") {
-        // class line: `originalclassname -> obfuscatedclassname:`
-        let mut split = line.splitn(3, ' ');
-        let original = split.next()?;
-        if split.next()? != "->" || !line.ends_with(':') {
-            return None;
-        }
-        let mut obfuscated = split.next()?;
-        obfuscated = &obfuscated[..obfuscated.len() - 1];
-        return Some(ProguardRecord::Class {
+/// Returns the byte offset into `line` and the reason if the line could not be
+/// parsed.
+pub(crate) fn parse_mapping(line: &str) -> Result<ProguardRecord<'_>, (usize, SyntaxErrorKind)> {
+    if let Some(header) = line.strip_prefix('#') {
+        let (key, value) = parse_header(header);
+        return Ok(ProguardRecord::Header { key, value });
+    }
+    // member lines are indented by any amount of spaces or tabs.
+    if !line.starts_with([' ', '\t']) {
+        let (original, obfuscated) = parse_class(line)?;
+        return Ok(ProguardRecord::Class {
             original,
             obfuscated,
         });
     }
+    Ok(match parse_member(line)? {
+        Member::Field(field) => field.into(),
+        Member::Method(method) => method.into(),
+    })
+}
+
+/// Parses the `key: value` of a header line, following its leading `#`.
+pub(crate) fn parse_header(header: &str) -> (&str, Option<&str>) {
+    let mut split = header.splitn(2, ':');
+    let key = split.next().unwrap_or_default().trim();
+    let value = split.next().map(|s| s.trim());
+    (key, value)
+}
+
+/// Parses a class line, returning its original and obfuscated name.
+///
+/// Returns the byte offset into `line` and the reason if the line could not be
+/// parsed.
+pub(crate) fn parse_class(line: &str) -> Result<(&str, &str), (usize, SyntaxErrorKind)> {
+    let full = line;
+    let offset = |s: &str| s.as_ptr() as usize - full.as_ptr() as usize;
+    let (line, _) = split_comment(line);
+    let end = line.len();
+
+    // class line: `originalclassname -> obfuscatedclassname:`
+    let mut split = line.splitn(3, ' ');
+    let original = split.next().unwrap_or_default();
+    match split.next() {
+        Some("->") => {}
+        Some(token) => return Err((offset(token), SyntaxErrorKind::MissingSeparator)),
+        None => return Err((end, SyntaxErrorKind::MissingSeparator)),
+    }
+    if !line.ends_with(':') {
+        return Err((end, SyntaxErrorKind::MissingClassTerminator));
+    }
+    let obfuscated = split
+        .next()
+        .ok_or((end, SyntaxErrorKind::UnexpectedEndOfLine))?;
+    Ok((original, &obfuscated[..obfuscated.len() - 1]))
+}
+
+/// A field or method, as parsed from an indented member line.
+pub(crate) enum Member<'s> {
+    Field(FieldMapping<'s>),
+    Method(MethodMapping<'s>),
+}
+
+/// Parses an indented member line.
+///
+/// Returns the byte offset into `line` and the reason if the line could not be
+/// parsed.
+pub(crate) fn parse_member(line: &str) -> Result<Member<'_>, (usize, SyntaxErrorKind)> {
+    let full = line;
+    let offset = |s: &str| s.as_ptr() as usize - full.as_ptr() as usize;
+    let (line, _) = split_comment(line);
+    let end = line.len();
+
     // field line or method line:
     // `originalfieldtype originalfieldname -> obfuscatedfieldname`
     // `[startline:endline:]originalreturntype [originalclassname.]originalmethodname(originalargumenttype,...)[:originalstartline[:originalendline]] -> obfuscatedmethodname`
-    line = &line[4..];
+    let mut line = line.trim_start_matches([' ', '\t']);
     let mut line_mapping = LineMapping {
         startline: 0,
         endline: 0,
         original_startline: None,
         original_endline: None,
     };
+    let parse_number = |n: &str| {
+        n.parse()
+            .map_err(|_| (offset(n), SyntaxErrorKind::InvalidLineNumber))
+    };
 
     // leading line mapping
     if line.starts_with(char::is_numeric) {
         let mut nums = line.splitn(3, ':');
-        line_mapping.startline = nums.next()?.parse().ok()?;
-        line_mapping.endline = nums.next()?.parse().ok()?;
-        line = nums.next()?;
+        line_mapping.startline = parse_number(nums.next().unwrap_or_default())?;
+        line_mapping.endline = parse_number(
+            nums.next()
+                .ok_or((end, SyntaxErrorKind::UnexpectedEndOfLine))?,
+        )?;
+        line = nums
+            .next()
+            .ok_or((end, SyntaxErrorKind::UnexpectedEndOfLine))?;
     }
 
     // split the type, name and obfuscated name
     let mut split = line.splitn(4, ' ');
-    let ty = split.next()?;
-    let mut original = split.next()?;
-    if split.next()? != "->" {
-        return None;
+    let ty = split.next().unwrap_or_default();
+    let mut original = split
+        .next()
+        .ok_or((end, SyntaxErrorKind::UnexpectedEndOfLine))?;
+    match split.next() {
+        Some("->") => {}
+        Some(token) => return Err((offset(token), SyntaxErrorKind::MissingSeparator)),
+        None => return Err((end, SyntaxErrorKind::MissingSeparator)),
     }
-    let obfuscated = split.next()?;
+    let obfuscated = split
+        .next()
+        .ok_or((end, SyntaxErrorKind::UnexpectedEndOfLine))?;
 
     // split off trailing line mappings
     let mut nums = original.splitn(3, ':');
-    original = nums.next()?;
-    line_mapping.original_startline = match nums.next() {
-        Some(n) => Some(n.parse().ok()?),
-        _ => None,
-    };
-    line_mapping.original_endline = match nums.next() {
-        Some(n) => Some(n.parse().ok()?),
-        _ => None,
-    };
+    original = nums.next().unwrap_or_default();
+    line_mapping.original_startline = nums.next().map(parse_number).transpose()?;
+    line_mapping.original_endline = nums.next().map(parse_number).transpose()?;
+
+    // some toolchains emit inverted ranges, which are normalized.
+    if line_mapping.startline > line_mapping.endline {
+        std::mem::swap(&mut line_mapping.startline, &mut line_mapping.endline);
+    }
+    if let (Some(start), Some(end)) = (
+        &mut line_mapping.original_startline,
+        &mut line_mapping.original_endline,
+    ) {
+        if start > end {
+            std::mem::swap(start, end);
+        }
+    }
 
     // split off the arguments
     let mut args = original.splitn(2, '(');
-    original = args.next()?;
+    original = args.next().unwrap_or_default();
 
-    Some(match args.next() {
-        None => ProguardRecord::Field {
+    Ok(match args.next() {
+        None => Member::Field(FieldMapping {
             ty,
             original,
             obfuscated,
-        },
+        }),
         Some(args) => {
             if !args.ends_with(')') {
-                return None;
+                return Err((offset(args) - 1, SyntaxErrorKind::UnclosedArguments));
             }
             let arguments = &args[..args.len() - 1];
 
-            let mut split_class = original.rsplitn(2, '.');
-            original = split_class.next()?;
-            let original_class = split_class.next();
+            let (original_class, original) = match original.rsplit_once('.') {
+                Some((class, method)) => (Some(class), method),
+                None => (None, original),
+            };
 
-            ProguardRecord::Method {
+            Member::Method(MethodMapping {
                 ty,
                 original,
                 obfuscated,
                 arguments,
                 original_class,
-                line_mapping: if line_mapping.startline > 0 {
+                // `0:0` denotes a missing line mapping, whereas catch-all
+                // ranges like `0:65535` start at `0`.
+                line_mapping: if line_mapping.endline > 0 {
                     Some(line_mapping)
                 } else {
                     None
                 },
-            }
+            })
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_terminator() {
+        let naive = |slice: &[u8]| slice.iter().position(|c| *c == b'\n' || *c == b'\r');
+
+        let mut input = b"0123456789abcdef0123456789abcdef".to_vec();
+        assert_eq!(find_line_terminator(&input), None);
+        for terminator in [b'\n', b'\r'] {
+            for pos in 0..input.len() {
+                let mut input = input.clone();
+                input[pos] = terminator;
+                assert_eq!(find_line_terminator(&input), Some(pos));
+                assert_eq!(find_line_terminator(&input[..pos]), None);
+            }
+        }
+
+        input.extend_from_slice("\u{80}\u{10a0a}\u{0d0d}\n".as_bytes());
+        assert_eq!(find_line_terminator(&input), naive(&input));
+    }
+
+    #[test]
+    fn compiler_version() {
+        let parse = |version| CompilerVersion::parse(version).map(|v| v.to_string());
+        assert_eq!(parse("8.3.37").as_deref(), Some("8.3.37"));
+        assert_eq!(parse(" 3.3.28-dev").as_deref(), Some("3.3.28"));
+        assert_eq!(parse("8.2").as_deref(), Some("8.2.0"));
+        assert_eq!(parse("9").as_deref(), Some("9.0.0"));
+        assert_eq!(parse("main"), None);
+        assert_eq!(parse("8.x.1"), None);
+        assert_eq!(parse(""), None);
+
+        assert!(CompilerVersion::new(8, 10, 0) > CompilerVersion::new(8, 9, 99));
+        assert!(CompilerVersion::new(1, 3, 49) < CompilerVersion::new(8, 0, 0));
+    }
+
+    #[test]
+    fn split_lines() {
+        assert_eq!(split_line(b"a\r\nb"), (&b"a"[..], &b"b"[..]));
+        assert_eq!(split_line(b"a\n\rb"), (&b"a"[..], &b"\rb"[..]));
+        assert_eq!(split_line(b"a\rb"), (&b"a"[..], &b"b"[..]));
+        assert_eq!(split_line(b"ab"), (&b"ab"[..], &b""[..]));
+    }
+
+    #[test]
+    fn header_len() {
+        let header_len = |source: &[u8]| ProguardMapping::new(source).header_len();
+        assert_eq!(header_len(b""), 0);
+        assert_eq!(header_len(b"a.A -> b:\n# comment\n"), 0);
+        assert_eq!(header_len(b"# a\r\n\r\n# b\na.A -> b:\n"), 11);
+        assert_eq!(header_len(b"# a\n# b"), 7);
+
+        let mapping = ProguardMapping::new(b"# a: 1\n\n# b: 2\na.A -> b:\n");
+        assert_eq!(mapping.leading_headers().count(), 2);
+        assert_eq!(mapping.body().leading_headers().count(), 0);
+        assert_eq!(mapping.body().source(), b"a.A -> b:\n");
+    }
+}
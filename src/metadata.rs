@@ -0,0 +1,68 @@
+//! Debug file metadata of Proguard Mapping Files.
+
+use std::fmt::Write;
+
+use crate::mapping::ProguardMapping;
+
+/// The metadata of a mapping file, as recorded when ingesting it as a debug file.
+///
+/// Uploaders and servers use this metadata to identify a mapping, and to
+/// decide how it can be used. Its JSON representation, written by
+/// [`to_json`](Self::to_json), looks like:
+///
+/// ```json
+/// {"uuid":"...","object_type":"proguard","features":{"has_line_info":true},"size":31,"checksum":"..."}
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DebugFileMetadata {
+    /// The hyphenated UUID of the mapping file, as returned by
+    /// `ProguardMapping::header_uuid`, or by `ProguardMapping::uuid` if the
+    /// mapping has no `# uuid` header.
+    ///
+    /// This is always set by
+    /// [`ProguardMapping::debug_file_metadata`](crate::ProguardMapping::debug_file_metadata),
+    /// regardless of the `uuid` feature, and written as `null` if missing.
+    pub uuid: Option<String>,
+    /// Whether the mapping file contains line info.
+    pub has_line_info: bool,
+    /// The size of the mapping file in bytes.
+    pub size: u64,
+    /// The hex-encoded SHA-1 checksum of the mapping file.
+    pub checksum: String,
+}
+
+impl DebugFileMetadata {
+    /// The object type of all mapping files.
+    pub const OBJECT_TYPE: &'static str = "proguard";
+
+    pub(crate) fn new(mapping: &ProguardMapping<'_>) -> Self {
+        let source = mapping.source();
+        Self {
+            uuid: Some(mapping.identifier_uuid_string()),
+            has_line_info: mapping.has_line_info(),
+            size: source.len() as u64,
+            checksum: sha1_smol::Sha1::from(source).digest().to_string(),
+        }
+    }
+
+    /// Returns the metadata as a single line of JSON.
+    pub fn to_json(&self) -> String {
+        // none of the values ever need escaping.
+        let mut json = String::from("{\"uuid\":");
+        match self.uuid {
+            Some(ref uuid) => {
+                let _ = write!(json, "\"{}\"", uuid);
+            }
+            None => json.push_str("null"),
+        }
+        let _ = write!(
+            json,
+            ",\"object_type\":\"{}\",\"features\":{{\"has_line_info\":{}}},\"size\":{},\"checksum\":\"{}\"}}",
+            Self::OBJECT_TYPE,
+            self.has_line_info,
+            self.size,
+            self.checksum
+        );
+        json
+    }
+}
@@ -0,0 +1,285 @@
+//! Applying a Proguard Mapping in reverse.
+
+use std::collections::HashMap;
+use std::fmt::{Error as FmtError, Write};
+use std::sync::OnceLock;
+
+use crate::baseline_profile;
+use crate::classes::{ClassMapping, MethodMapping};
+use crate::mapper::{format_throwable, indent};
+use crate::mapping::ProguardMapping;
+use crate::stacktrace::{self, StackFrame, Throwable};
+
+/// The file name that obfuscated frames refer to.
+const SOURCE_FILE: &str = "SourceFile";
+
+/// The method name of obfuscated frames whose method is not in the mapping.
+const UNKNOWN_METHOD: &str = "<unknown>";
+
+/// Translates the original line of a method into its minified line.
+///
+/// Returns `None` if the method does not cover `line`.
+fn minified_line(method: &MethodMapping<'_>, line: usize) -> Option<usize> {
+    let line_mapping = match &method.line_mapping {
+        Some(line_mapping) => line_mapping,
+        None => return Some(line),
+    };
+    // this mirrors how `ProguardMapper` translates minified lines.
+    let (original_startline, original_endline) = match line_mapping.original_startline {
+        Some(original_startline) => (original_startline, line_mapping.original_endline),
+        None => (line_mapping.startline, Some(line_mapping.endline)),
+    };
+    match original_endline {
+        None if line == original_startline => Some(line_mapping.startline),
+        Some(original_endline) if (original_startline..=original_endline).contains(&line) => {
+            let line = line_mapping.startline + line - original_startline;
+            Some(line.min(line_mapping.endline))
+        }
+        _ => None,
+    }
+}
+
+/// An Obfuscator, applying a mapping in reverse.
+///
+/// This turns readable frames and stack traces into their obfuscated
+/// equivalent, which is useful for generating test fixtures, or for sharing
+/// traces without exposing internal names.
+///
+/// Frames are obfuscated one by one. Inlined call stacks are not collapsed
+/// into a single frame, and overloaded methods resolve to the first method
+/// with the same name which covers the frame's line.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{ProguardObfuscator, StackFrame};
+///
+/// let mapping = "com.example.MainFragment -> a.a:\n    3:4:void onClick():65:66 -> b";
+/// let obfuscator = ProguardObfuscator::from(mapping);
+///
+/// let frame = StackFrame::with_file("com.example.MainFragment", "onClick", 66, "MainFragment.java");
+/// assert_eq!(
+///     obfuscator.obfuscate_frame(&frame),
+///     StackFrame::with_file("a.a", "b", 4, "SourceFile")
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct ProguardObfuscator<'s> {
+    classes: HashMap<&'s str, ClassMapping<'s>>,
+    /// The class hosting the members of each class which R8 merged into
+    /// another one, and which has no class of its own in the mapping. Only
+    /// built for the first frame of a class that is not part of the mapping.
+    merged: OnceLock<HashMap<&'s str, &'s str>>,
+}
+
+impl<'s> From<&'s str> for ProguardObfuscator<'s> {
+    fn from(s: &'s str) -> Self {
+        let mapping = ProguardMapping::new(s.as_ref());
+        Self::new(mapping)
+    }
+}
+
+impl<'s> ProguardObfuscator<'s> {
+    /// Create a new ProguardObfuscator.
+    ///
+    /// This indexes the classes by their original name. Their members are
+    /// parsed whenever a frame is obfuscated.
+    pub fn new(mapping: ProguardMapping<'s>) -> Self {
+        let classes = mapping
+            .classes()
+            .map(|class| (class.original(), class))
+            .collect();
+
+        Self {
+            classes,
+            merged: OnceLock::new(),
+        }
+    }
+
+    /// Returns the class that the members of `class` were merged into.
+    fn merged_into(&self, class: &str) -> Option<&ClassMapping<'s>> {
+        let merged = self.merged.get_or_init(|| {
+            let mut merged = HashMap::new();
+            let range = |method: &MethodMapping<'_>| {
+                let line_mapping = method.line_mapping.as_ref()?;
+                Some((line_mapping.startline, line_mapping.endline))
+            };
+            for host in self.classes.values() {
+                let methods: Vec<_> = host.methods().collect();
+                for (i, method) in methods.iter().enumerate() {
+                    let original_class = match method.original_class {
+                        Some(class) if !self.classes.contains_key(class) => class,
+                        _ => continue,
+                    };
+                    // the members of merged classes are listed with their
+                    // original class, just like inlined ones. Contrary to
+                    // those, they are the outermost frame of their range.
+                    let inlined = range(method).is_some()
+                        && methods.get(i + 1).map(range) == Some(range(method));
+                    if !inlined {
+                        merged.entry(original_class).or_insert(host.original());
+                    }
+                }
+            }
+            merged
+        });
+        self.classes.get(merged.get(class)?)
+    }
+
+    /// Obfuscates a Class.
+    ///
+    /// This works on the fully-qualified name of the class, with its complete
+    /// module prefix.
+    pub fn obfuscate_class(&self, class: &str) -> Option<&'s str> {
+        self.classes.get(class).map(|class| class.obfuscated())
+    }
+
+    /// Obfuscates a single StackFrame.
+    ///
+    /// Frames of classes that are not part of the mapping are returned as-is.
+    /// Otherwise, the file is replaced by `SourceFile`, and the method and
+    /// line are translated if a method covering the line is found. If there
+    /// is none, the method is replaced by `<unknown>`, so that the original
+    /// name does not leak, unless it is a constructor.
+    ///
+    /// Frames of classes that R8 merged into another class are obfuscated
+    /// using the members that the other class lists for them.
+    pub fn obfuscate_frame<'a>(&'a self, frame: &StackFrame<'a>) -> StackFrame<'a> {
+        let (class, original_class) = match self.classes.get(frame.class) {
+            Some(class) => (class, None),
+            None => match self.merged_into(frame.class) {
+                Some(host) => (host, Some(frame.class)),
+                None => return frame.clone(),
+            },
+        };
+
+        // methods with a line mapping are preferred over ones without, and
+        // methods inlined from other classes are obfuscated in those classes.
+        let mut found = None;
+        let mut fallback = None;
+        for method in class.methods() {
+            if method.original_class != original_class || method.original != frame.method {
+                continue;
+            }
+            match minified_line(&method, frame.line) {
+                Some(line) if method.line_mapping.is_some() => {
+                    found = Some((method.obfuscated, line));
+                    break;
+                }
+                Some(line) => {
+                    fallback.get_or_insert((method.obfuscated, line));
+                }
+                None => {}
+            }
+        }
+
+        let (method, line) = found.or(fallback).unwrap_or_else(|| {
+            // constructors and static initializers are never renamed.
+            let method = match frame.method {
+                "<init>" | "<clinit>" => frame.method,
+                _ => UNKNOWN_METHOD,
+            };
+            (method, frame.line)
+        });
+        StackFrame {
+            class: class.obfuscated(),
+            method,
+            line,
+            file: frame.file.map(|_| SOURCE_FILE),
+            module: frame.module,
+        }
+    }
+
+    /// Obfuscates the classes and methods of an ART baseline profile.
+    ///
+    /// This is the reverse of
+    /// [`ProguardMapper::remap_baseline_profile`](crate::ProguardMapper::remap_baseline_profile).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = "com.example.Foo -> a.a:\n    void run(com.example.Foo) -> a";
+    /// let obfuscator = proguard::ProguardObfuscator::from(mapping);
+    ///
+    /// assert_eq!(
+    ///     obfuscator.obfuscate_baseline_profile("HSPLcom/example/Foo;->run(Lcom/example/Foo;)V"),
+    ///     "HSPLa/a;->a(La/a;)V"
+    /// );
+    /// ```
+    pub fn obfuscate_baseline_profile(&self, input: &str) -> String {
+        baseline_profile::translate(
+            input,
+            |class| self.obfuscate_class(class),
+            |class, method, arguments, _| {
+                self.classes
+                    .get(class)?
+                    .methods()
+                    .find(|m| {
+                        m.original == method
+                            && m.original_class.is_none()
+                            && baseline_profile::same_arguments(m.arguments, arguments)
+                    })
+                    .map(|m| m.obfuscated)
+            },
+        )
+    }
+
+    /// Obfuscates a Throwable, which is the first line of a full stacktrace.
+    pub fn obfuscate_throwable<'a>(&'a self, throwable: &Throwable<'a>) -> Option<Throwable<'a>> {
+        self.obfuscate_class(throwable.class)
+            .map(|class| Throwable {
+                class,
+                message: throwable.message,
+            })
+    }
+
+    /// Obfuscates a complete Java StackTrace, the inverse of
+    /// [`ProguardMapper::remap_stacktrace`](crate::ProguardMapper::remap_stacktrace).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let mapping = r#"com.example.CrashException -> a.a:
+    /// com.example.MainFragment -> a.b:
+    ///     3:4:void onClick():65:66 -> b"#;
+    /// let obfuscator = proguard::ProguardObfuscator::from(mapping);
+    ///
+    /// let obfuscated = obfuscator
+    ///     .obfuscate_stacktrace(
+    ///         "com.example.CrashException: boom\n    at com.example.MainFragment.onClick(MainFragment.java:65)",
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(obfuscated, "a.a: boom\n    at a.b.b(SourceFile:3)\n");
+    /// ```
+    pub fn obfuscate_stacktrace(&self, input: &str) -> Result<String, FmtError> {
+        let mut stacktrace = String::new();
+
+        for (i, (line, _)) in stacktrace::split_lines(input).enumerate() {
+            if let Some(frame) = stacktrace::parse_frame(line) {
+                if self.classes.contains_key(frame.class) || self.merged_into(frame.class).is_some()
+                {
+                    let frame = self.obfuscate_frame(&frame);
+                    writeln!(&mut stacktrace, "{}{}", indent(line), frame)?;
+                } else {
+                    writeln!(&mut stacktrace, "{}", line)?;
+                }
+                continue;
+            }
+            let throwable = if i == 0 {
+                stacktrace::parse_headline(line)
+            } else {
+                stacktrace::parse_cause(line)
+            };
+            match throwable {
+                Some((prefix, throwable)) => format_throwable(
+                    &mut stacktrace,
+                    line,
+                    prefix,
+                    self.obfuscate_throwable(&throwable),
+                )?,
+                None => writeln!(&mut stacktrace, "{}", line)?,
+            }
+        }
+        Ok(stacktrace)
+    }
+}
@@ -0,0 +1,265 @@
+//! Byte-level parsing of Proguard Mapping Files.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::iter::FusedIterator;
+
+use crate::mapping::{
+    parse_mapping, LineMapping, ParseError, ParseErrorKind, ProguardMapping, ProguardRecord,
+    ProguardRecordIter,
+};
+
+/// A Proguard Mapping Record, referring to raw bytes instead of strings.
+///
+/// Obfuscation dictionaries can produce class and member names which are not
+/// valid utf-8. [`ProguardRecord::try_parse`] rejects such lines, whereas this
+/// record keeps them usable. The `*_lossy` accessors convert the names to
+/// strings, replacing invalid sequences with `U+FFFD REPLACEMENT CHARACTER`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RawRecord<'s> {
+    /// A Proguard Header.
+    Header {
+        /// The Key of the Header.
+        key: &'s [u8],
+        /// Optional value if the Header is a KV pair.
+        value: Option<&'s [u8]>,
+    },
+    /// A Class Mapping.
+    Class {
+        /// Original name of the class.
+        original: &'s [u8],
+        /// Obfuscated name of the class.
+        obfuscated: &'s [u8],
+    },
+    /// A Field Mapping.
+    Field {
+        /// Type of the field
+        ty: &'s [u8],
+        /// Original name of the field.
+        original: &'s [u8],
+        /// Obfuscated name of the field.
+        obfuscated: &'s [u8],
+    },
+    /// A Method Mapping.
+    Method {
+        /// Return Type of the method.
+        ty: &'s [u8],
+        /// Original name of the method.
+        original: &'s [u8],
+        /// Obfuscated name of the method.
+        obfuscated: &'s [u8],
+        /// Arguments of the method as raw bytes.
+        arguments: &'s [u8],
+        /// Original class of a foreign inlined method.
+        original_class: Option<&'s [u8]>,
+        /// Optional line mapping of the method.
+        line_mapping: Option<LineMapping>,
+    },
+}
+
+impl<'s> RawRecord<'s> {
+    /// Parses a line from a proguard mapping file, which may contain invalid utf-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::RawRecord;
+    ///
+    /// let parsed = RawRecord::try_parse(b"com.example.\xffoo -> a.\xfe:").unwrap();
+    /// assert_eq!(
+    ///     parsed,
+    ///     RawRecord::Class {
+    ///         original: b"com.example.\xffoo",
+    ///         obfuscated: b"a.\xfe",
+    ///     }
+    /// );
+    /// assert_eq!(parsed.original_lossy().unwrap(), "com.example.\u{fffd}oo");
+    /// ```
+    pub fn try_parse(line: &'s [u8]) -> Result<Self, ParseError<'s>> {
+        let error = ParseError::new(
+            line,
+            ParseErrorKind::ParseError("line is not a valid proguard record"),
+        );
+
+        match std::str::from_utf8(line) {
+            Ok(text) => {
+                let record = parse_mapping(text).map_err(|_| error)?;
+                Ok(Self::from_record(line, text, record))
+            }
+            Err(_) => {
+                // Replace each invalid byte with a single placeholder, so that
+                // offsets into the sanitized line also apply to the raw line.
+                // The placeholder is not significant to the parser.
+                let sanitized = sanitize(line);
+                let record = parse_mapping(&sanitized).map_err(|_| error)?;
+                Ok(Self::from_record(line, &sanitized, record))
+            }
+        }
+    }
+
+    /// Converts a `record` parsed from `text` into one that refers to `line`,
+    /// with `text` having the same byte offsets as `line`.
+    fn from_record(line: &'s [u8], text: &str, record: ProguardRecord<'_>) -> Self {
+        let raw = |s: &str| {
+            let start = s.as_ptr() as usize - text.as_ptr() as usize;
+            &line[start..start + s.len()]
+        };
+        match record {
+            ProguardRecord::Header { key, value } => RawRecord::Header {
+                key: raw(key),
+                value: value.map(raw),
+            },
+            ProguardRecord::Class {
+                original,
+                obfuscated,
+            } => RawRecord::Class {
+                original: raw(original),
+                obfuscated: raw(obfuscated),
+            },
+            ProguardRecord::Field {
+                ty,
+                original,
+                obfuscated,
+            } => RawRecord::Field {
+                ty: raw(ty),
+                original: raw(original),
+                obfuscated: raw(obfuscated),
+            },
+            ProguardRecord::Method {
+                ty,
+                original,
+                obfuscated,
+                arguments,
+                original_class,
+                line_mapping,
+            } => RawRecord::Method {
+                ty: raw(ty),
+                original: raw(original),
+                obfuscated: raw(obfuscated),
+                arguments: raw(arguments),
+                original_class: original_class.map(raw),
+                line_mapping,
+            },
+        }
+    }
+
+    /// The original name of a class, field or method, converted lossily.
+    pub fn original_lossy(&self) -> Option<Cow<'s, str>> {
+        match *self {
+            RawRecord::Header { .. } => None,
+            RawRecord::Class { original, .. }
+            | RawRecord::Field { original, .. }
+            | RawRecord::Method { original, .. } => Some(String::from_utf8_lossy(original)),
+        }
+    }
+
+    /// The obfuscated name of a class, field or method, converted lossily.
+    pub fn obfuscated_lossy(&self) -> Option<Cow<'s, str>> {
+        match *self {
+            RawRecord::Header { .. } => None,
+            RawRecord::Class { obfuscated, .. }
+            | RawRecord::Field { obfuscated, .. }
+            | RawRecord::Method { obfuscated, .. } => Some(String::from_utf8_lossy(obfuscated)),
+        }
+    }
+
+    /// The type of a field, or return type of a method, converted lossily.
+    pub fn ty_lossy(&self) -> Option<Cow<'s, str>> {
+        match *self {
+            RawRecord::Field { ty, .. } | RawRecord::Method { ty, .. } => {
+                Some(String::from_utf8_lossy(ty))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Replaces every byte of an invalid utf-8 sequence with a `?`.
+fn sanitize(line: &[u8]) -> String {
+    let mut sanitized = String::with_capacity(line.len());
+    let mut rest = line;
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                sanitized.push_str(valid);
+                return sanitized;
+            }
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                // `valid_up_to` guarantees this is valid.
+                sanitized.push_str(std::str::from_utf8(valid).unwrap_or_default());
+                let invalid_len = e.error_len().unwrap_or(invalid.len());
+                sanitized.push_str(&"?".repeat(invalid_len));
+                rest = &invalid[invalid_len..];
+            }
+        }
+    }
+}
+
+/// An Iterator yielding [`RawRecord`]s, created by [`ProguardMapping::iter_raw`].
+#[derive(Clone, Default)]
+pub struct RawRecordIter<'s> {
+    lines: ProguardRecordIter<'s>,
+}
+
+impl<'s> RawRecordIter<'s> {
+    pub(crate) fn new(mapping: &ProguardMapping<'s>) -> Self {
+        Self {
+            lines: mapping.iter(),
+        }
+    }
+}
+
+impl fmt::Debug for RawRecordIter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RawRecordIter").finish()
+    }
+}
+
+impl<'s> Iterator for RawRecordIter<'s> {
+    type Item = Result<RawRecord<'s>, ParseError<'s>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.lines.next_line().map(RawRecord::try_parse)
+    }
+}
+
+impl FusedIterator for RawRecordIter<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sanitize_invalid() {
+        assert_eq!(sanitize(b"a\xffb"), "a?b");
+        assert_eq!(sanitize(b"a\xe2\x82b"), "a??b");
+        assert_eq!(sanitize(b"\xf0\x9f\x92"), "???");
+        assert_eq!(sanitize("ä\u{1F496}".as_bytes()), "ä\u{1F496}");
+    }
+
+    #[test]
+    fn raw_member() {
+        let parsed = RawRecord::try_parse(b"    1:2:void \xe2\x82.m\xff(int):3:4 -> \xfe").unwrap();
+        assert_eq!(
+            parsed,
+            RawRecord::Method {
+                ty: b"void",
+                original: b"m\xff",
+                obfuscated: b"\xfe",
+                arguments: b"int",
+                original_class: Some(b"\xe2\x82"),
+                line_mapping: Some(LineMapping {
+                    startline: 1,
+                    endline: 2,
+                    original_startline: Some(3),
+                    original_endline: Some(4),
+                }),
+            }
+        );
+        assert_eq!(parsed.original_lossy().unwrap(), "m\u{fffd}");
+        assert_eq!(parsed.obfuscated_lossy().unwrap(), "\u{fffd}");
+        assert_eq!(parsed.ty_lossy().unwrap(), "void");
+
+        assert!(RawRecord::try_parse(b"    int \xff").is_err());
+    }
+}
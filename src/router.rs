@@ -0,0 +1,169 @@
+//! Routing of lookups across the mappings of multiple obfuscated components.
+
+use std::fmt;
+
+use crate::mapper::{remap_stacktrace_routed, ProguardMapper, RemappedFrameIter, RetraceOptions};
+use crate::stacktrace::StackFrame;
+
+/// Dispatches lookups to one of several [`ProguardMapper`]s, based on the
+/// obfuscated package of a class.
+///
+/// Apps may embed SDKs which were obfuscated by their vendor, and ship with
+/// their own mapping. Classes in a package added with
+/// [`with_route`](Self::with_route) are remapped using the mapper of that
+/// route, and all other classes using the fallback mapper, which is usually
+/// the mapping of the app itself. If the packages of several routes match a
+/// class, the most specific one is used.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{ProguardMapper, RoutingMapper, StackFrame};
+///
+/// let app = ProguardMapper::from("com.example.App -> a.a:\n    1:1:void run():10 -> a");
+/// let sdk = ProguardMapper::from("com.vendor.sdk.Client -> com.vendor.a:\n    1:1:void send():20 -> a");
+/// let router = RoutingMapper::new(app).with_route("com.vendor", sdk);
+///
+/// assert_eq!(router.remap_class("a.a"), Some("com.example.App"));
+/// assert_eq!(router.remap_class("com.vendor.a"), Some("com.vendor.sdk.Client"));
+///
+/// let frame = StackFrame::new("com.vendor.a", "a", 1);
+/// assert_eq!(
+///     router.remap_frame(&frame).next(),
+///     Some(StackFrame::new("com.vendor.sdk.Client", "send", 20))
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct RoutingMapper<'s> {
+    /// The routes along with their package, ordered from the longest package.
+    routes: Vec<(String, ProguardMapper<'s>)>,
+    fallback: ProguardMapper<'s>,
+}
+
+impl<'s> RoutingMapper<'s> {
+    /// Creates a router which remaps all classes using `fallback`.
+    pub fn new(fallback: ProguardMapper<'s>) -> Self {
+        Self {
+            routes: Vec::new(),
+            fallback,
+        }
+    }
+
+    /// Remaps classes in the obfuscated `package` and its subpackages using `mapper`.
+    ///
+    /// The package is matched at `.` boundaries, so `com.vendor` matches
+    /// `com.vendor.a` and `com.vendor.a.b`, but not `com.vendorx.a`. A route
+    /// for a package which already has one replaces it.
+    pub fn with_route<S: Into<String>>(mut self, package: S, mapper: ProguardMapper<'s>) -> Self {
+        let mut package = package.into();
+        if package.ends_with('.') {
+            package.pop();
+        }
+        self.routes.retain(|(existing, _)| *existing != package);
+        let index = self
+            .routes
+            .partition_point(|(existing, _)| existing.len() >= package.len());
+        self.routes.insert(index, (package, mapper));
+        self
+    }
+
+    /// Returns the mapper which is used for the obfuscated `class`.
+    pub fn route(&self, class: &str) -> &ProguardMapper<'s> {
+        self.routes
+            .iter()
+            .find(|(package, _)| {
+                class
+                    .strip_prefix(package.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .map_or(&self.fallback, |(_, mapper)| mapper)
+    }
+
+    /// Remaps an obfuscated Class, see [`ProguardMapper::remap_class`].
+    pub fn remap_class(&'s self, class: &str) -> Option<&'s str> {
+        self.route(class).remap_class(class)
+    }
+
+    /// Remaps a single Stackframe, see [`ProguardMapper::remap_frame`].
+    pub fn remap_frame(&'s self, frame: &StackFrame<'s>) -> RemappedFrameIter<'s> {
+        self.route(frame.class).remap_frame(frame)
+    }
+
+    /// Remaps a complete Java StackTrace, see [`ProguardMapper::remap_stacktrace`].
+    pub fn remap_stacktrace(&self, input: &str) -> Result<String, fmt::Error> {
+        self.remap_stacktrace_with_options(input, &RetraceOptions::default())
+    }
+
+    /// Remaps a complete Java StackTrace using `options`, see
+    /// [`ProguardMapper::remap_stacktrace_with_options`].
+    ///
+    /// Every exception and frame is remapped by the mapper of its class, in a
+    /// single pass over `input`, so context such as whether a frame was seen
+    /// yet is shared across all mappers.
+    pub fn remap_stacktrace_with_options(
+        &self,
+        input: &str,
+        options: &RetraceOptions,
+    ) -> Result<String, fmt::Error> {
+        remap_stacktrace_routed(input, options, |class| self.route(class))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> RoutingMapper<'static> {
+        let app = ProguardMapper::from("com.example.App -> a.a:\n    1:1:void run():10 -> a");
+        let sdk = ProguardMapper::from(
+            "com.vendor.sdk.Client -> com.vendor.a:\n    1:1:void send():20 -> a\ncom.vendor.sdk.Error -> com.vendor.b:",
+        );
+        let nested = ProguardMapper::from("com.vendor.ui.View -> com.vendor.ui.a:");
+        RoutingMapper::new(app)
+            .with_route("com.vendor.", sdk)
+            .with_route("com.vendor.ui", nested)
+    }
+
+    #[test]
+    fn routes() {
+        let router = router();
+        assert_eq!(router.remap_class("a.a"), Some("com.example.App"));
+        assert_eq!(
+            router.remap_class("com.vendor.a"),
+            Some("com.vendor.sdk.Client")
+        );
+        assert_eq!(
+            router.remap_class("com.vendor.ui.a"),
+            Some("com.vendor.ui.View")
+        );
+        // classes are only remapped by the mapper of their route.
+        assert_eq!(router.remap_class("com.vendorx.a"), None);
+        assert_eq!(router.remap_class("com.vendor.ui.b"), None);
+    }
+
+    #[test]
+    fn stacktrace() {
+        let router = router();
+        let input = "\
+com.vendor.b: boom\r
+    at com.vendor.a.a(SourceFile:1)\r
+    at a.a.a(SourceFile:1)\r
+    ... 3 more";
+        assert_eq!(
+            router.remap_stacktrace(input).unwrap(),
+            "\
+com.vendor.sdk.Error: boom\r
+    at com.vendor.sdk.Client.send(SourceFile:20)\r
+    at com.example.App.run(SourceFile:10)\r
+    ... 3 more\n"
+        );
+
+        // a line that looks like an exception after the first frame is log
+        // output, no matter which mapper remapped the frame.
+        let input = "    at com.vendor.a.a(SourceFile:1)\na.a: not an exception\n";
+        assert_eq!(
+            router.remap_stacktrace(input).unwrap(),
+            "    at com.vendor.sdk.Client.send(SourceFile:20)\na.a: not an exception\n"
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Export of Proguard Mapping Files as SQLite databases.
+
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::mapping::{ProguardMapping, ProguardRecord};
+
+const SCHEMA: &str = "\
+CREATE TABLE classes (
+    id INTEGER PRIMARY KEY,
+    original TEXT NOT NULL,
+    obfuscated TEXT NOT NULL
+);
+CREATE TABLE members (
+    id INTEGER PRIMARY KEY,
+    class_id INTEGER NOT NULL REFERENCES classes(id),
+    kind TEXT NOT NULL,
+    type TEXT NOT NULL,
+    original TEXT NOT NULL,
+    obfuscated TEXT NOT NULL,
+    arguments TEXT,
+    original_class TEXT
+);
+CREATE TABLE lines (
+    member_id INTEGER NOT NULL REFERENCES members(id),
+    startline INTEGER NOT NULL,
+    endline INTEGER NOT NULL,
+    original_startline INTEGER,
+    original_endline INTEGER
+);
+";
+
+const INDEXES: &str = "\
+CREATE INDEX classes_obfuscated ON classes (obfuscated);
+CREATE INDEX classes_original ON classes (original);
+CREATE INDEX members_class ON members (class_id, obfuscated);
+CREATE INDEX lines_member ON lines (member_id, startline);
+";
+
+pub(crate) fn export<P: AsRef<Path>>(
+    mapping: &ProguardMapping<'_>,
+    path: P,
+) -> rusqlite::Result<()> {
+    let mut connection = Connection::open(path)?;
+    let transaction = connection.transaction()?;
+    transaction.execute_batch(SCHEMA)?;
+
+    {
+        let mut insert_class = transaction.prepare("INSERT INTO classes VALUES (?1, ?2, ?3)")?;
+        let mut insert_member =
+            transaction.prepare("INSERT INTO members VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)")?;
+        let mut insert_line =
+            transaction.prepare("INSERT INTO lines VALUES (?1, ?2, ?3, ?4, ?5)")?;
+
+        let mut member_id = 0;
+        for (class_id, class) in mapping.classes().enumerate() {
+            let class_id = class_id + 1;
+            insert_class.execute(params![class_id, class.original(), class.obfuscated()])?;
+
+            for record in class.members().filter_map(Result::ok) {
+                let (kind, ty, original, obfuscated, arguments, original_class, line_mapping) =
+                    match record {
+                        ProguardRecord::Field {
+                            ty,
+                            original,
+                            obfuscated,
+                        } => ("field", ty, original, obfuscated, None, None, None),
+                        ProguardRecord::Method {
+                            ty,
+                            original,
+                            obfuscated,
+                            arguments,
+                            original_class,
+                            line_mapping,
+                        } => (
+                            "method",
+                            ty,
+                            original,
+                            obfuscated,
+                            Some(arguments),
+                            original_class,
+                            line_mapping,
+                        ),
+                        _ => continue,
+                    };
+
+                member_id += 1;
+                insert_member.execute(params![
+                    member_id,
+                    class_id,
+                    kind,
+                    ty,
+                    original,
+                    obfuscated,
+                    arguments,
+                    original_class,
+                ])?;
+
+                if let Some(line_mapping) = line_mapping {
+                    insert_line.execute(params![
+                        member_id,
+                        line_mapping.startline,
+                        line_mapping.endline,
+                        line_mapping.original_startline,
+                        line_mapping.original_endline,
+                    ])?;
+                }
+            }
+        }
+    }
+
+    transaction.execute_batch(INDEXES)?;
+    transaction.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn export_database() {
+        let mapping = ProguardMapping::new(
+            b"\
+a.Don't -> a:
+    int field -> a
+    1:2:void a.Other.method(int):10:11 -> b
+b.B -> b:",
+        );
+        let path = std::env::temp_dir().join(format!("proguard-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        export(&mapping, &path).unwrap();
+
+        let connection = Connection::open(&path).unwrap();
+        let classes: Vec<(usize, String, String)> = connection
+            .prepare("SELECT id, original, obfuscated FROM classes ORDER BY id")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            classes,
+            vec![
+                (1, "a.Don't".to_owned(), "a".to_owned()),
+                (2, "b.B".to_owned(), "b".to_owned())
+            ]
+        );
+
+        type Member = (String, String, String, Option<String>, Option<String>);
+        let members: Vec<Member> = connection
+            .prepare(
+                "SELECT kind, original, obfuscated, arguments, original_class FROM members
+                 WHERE class_id = 1 ORDER BY id",
+            )
+            .unwrap()
+            .query_map([], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            members,
+            vec![
+                ("field".into(), "field".into(), "a".into(), None, None),
+                (
+                    "method".into(),
+                    "method".into(),
+                    "b".into(),
+                    Some("int".into()),
+                    Some("a.Other".into())
+                ),
+            ]
+        );
+
+        let line: (usize, usize, usize, usize, usize) = connection
+            .query_row(
+                "SELECT lines.member_id, startline, endline, original_startline, original_endline
+                 FROM lines JOIN members ON members.id = lines.member_id
+                 WHERE members.obfuscated = 'b'",
+                [],
+                |row| {
+                    Ok((
+                        row.get(0)?,
+                        row.get(1)?,
+                        row.get(2)?,
+                        row.get(3)?,
+                        row.get(4)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(line, (2, 1, 2, 10, 11));
+
+        drop(connection);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
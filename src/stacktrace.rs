@@ -1,6 +1,9 @@
 //! A Parser for Java Stacktraces.
 
 use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::iter::{self, Peekable};
+
+use crate::mapping::find_line_terminator;
 
 /// A full Java StackTrace as printed by [`Throwable.printStackTrace()`].
 ///
@@ -10,6 +13,7 @@ pub struct StackTrace<'s> {
     pub(crate) exception: Option<Throwable<'s>>,
     pub(crate) frames: Vec<StackFrame<'s>>,
     pub(crate) cause: Option<Box<StackTrace<'s>>>,
+    pub(crate) suppressed: Vec<StackTrace<'s>>,
 }
 
 impl<'s> StackTrace<'s> {
@@ -19,6 +23,7 @@ impl<'s> StackTrace<'s> {
             exception,
             frames,
             cause: None,
+            suppressed: vec![],
         }
     }
 
@@ -32,11 +37,21 @@ impl<'s> StackTrace<'s> {
             exception,
             frames,
             cause: Some(Box::new(cause)),
+            suppressed: vec![],
         }
     }
 
+    /// Adds exceptions that were suppressed in order to deliver this one.
+    pub fn with_suppressed(mut self, suppressed: Vec<StackTrace<'s>>) -> Self {
+        self.suppressed = suppressed;
+        self
+    }
+
     /// Parses a StackTrace from a full Java StackTrace.
     ///
+    /// `Suppressed: ` exceptions are nested by their indentation, just like
+    /// they are printed by Java.
+    ///
     /// # Examples
     ///
     /// ```rust
@@ -90,54 +105,170 @@ impl<'s> StackTrace<'s> {
     pub fn cause(&self) -> Option<&StackTrace<'_>> {
         self.cause.as_deref()
     }
+
+    /// Exceptions that were suppressed in order to deliver this one.
+    pub fn suppressed(&self) -> &[StackTrace<'_>] {
+        &self.suppressed
+    }
+
+    /// Prints the StackTrace according to `format`.
+    ///
+    /// The [`Display`] implementation of a StackTrace uses the default
+    /// [`StackTraceFormat`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{StackFrame, StackTrace, StackTraceFormat, Throwable};
+    ///
+    /// let trace = StackTrace::with_cause(
+    ///     Some(Throwable::new("a.Main")),
+    ///     vec![
+    ///         StackFrame::with_file("a.A", "run", 1, "A.java"),
+    ///         StackFrame::with_file("a.B", "call", 2, "B.java"),
+    ///     ],
+    ///     StackTrace::new(Some(Throwable::new("a.Cause")), vec![]),
+    /// );
+    /// let format = StackTraceFormat {
+    ///     indent_with_tabs: true,
+    ///     caused_by: "Cause: ".into(),
+    ///     max_frames: Some(1),
+    ///     ..Default::default()
+    /// };
+    ///
+    /// assert_eq!(
+    ///     trace.display_with(&format).to_string(),
+    ///     "a.Main\n\tat a.A.run(A.java:1)\n\t... 1 more\nCause: a.Cause\n"
+    /// );
+    /// ```
+    pub fn display_with<'a>(&'a self, format: &'a StackTraceFormat) -> impl Display + 'a {
+        DisplayStackTrace {
+            trace: self,
+            format,
+        }
+    }
+
+    fn fmt_enclosed(
+        &self,
+        f: &mut Formatter<'_>,
+        format: &StackTraceFormat,
+        prefix: &str,
+    ) -> FmtResult {
+        let indent = format.indent();
+        let shown = format.max_frames.unwrap_or(usize::MAX);
+        for frame in self.frames.iter().take(shown) {
+            writeln!(f, "{}{}{}", prefix, indent, frame)?;
+        }
+        if self.frames.len() > shown {
+            writeln!(
+                f,
+                "{}{}... {} more",
+                prefix,
+                indent,
+                self.frames.len() - shown
+            )?;
+        }
+
+        for suppressed in &self.suppressed {
+            let prefix = format!("{}{}", prefix, indent);
+            write!(f, "{}{}", prefix, format.suppressed)?;
+            if let Some(exception) = &suppressed.exception {
+                write!(f, "{}", exception)?;
+            }
+            writeln!(f)?;
+            suppressed.fmt_enclosed(f, format, &prefix)?;
+        }
+
+        if let Some(cause) = &self.cause {
+            write!(f, "{}{}", prefix, format.caused_by)?;
+            if let Some(exception) = &cause.exception {
+                write!(f, "{}", exception)?;
+            }
+            writeln!(f)?;
+            cause.fmt_enclosed(f, format, prefix)?;
+        }
+
+        Ok(())
+    }
 }
 
 impl<'s> Display for StackTrace<'s> {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
-        if let Some(exception) = &self.exception {
-            writeln!(f, "{}", exception)?;
+        self.display_with(&StackTraceFormat::default()).fmt(f)
+    }
+}
+
+/// Options for printing a [`StackTrace`], see [`StackTrace::display_with`].
+///
+/// The default matches the [`Display`] implementation of a [`StackTrace`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct StackTraceFormat {
+    /// The number of spaces per level of indentation. Defaults to `4`.
+    pub indent_width: usize,
+    /// Whether to indent with a single tab per level, like Java does, instead
+    /// of spaces.
+    pub indent_with_tabs: bool,
+    /// The prefix of a cause. Defaults to `Caused by: `.
+    pub caused_by: String,
+    /// The prefix of a suppressed exception. Defaults to `Suppressed: `.
+    pub suppressed: String,
+    /// The maximum number of frames printed for each exception.
+    ///
+    /// Further frames are elided, and replaced by a single `... N more` line.
+    pub max_frames: Option<usize>,
+}
+
+impl Default for StackTraceFormat {
+    fn default() -> Self {
+        Self {
+            indent_width: 4,
+            indent_with_tabs: false,
+            caused_by: "Caused by: ".into(),
+            suppressed: "Suppressed: ".into(),
+            max_frames: None,
         }
+    }
+}
 
-        for frame in &self.frames {
-            writeln!(f, "    {}", frame)?;
+impl StackTraceFormat {
+    /// A single level of indentation.
+    fn indent(&self) -> String {
+        if self.indent_with_tabs {
+            "\t".into()
+        } else {
+            " ".repeat(self.indent_width)
         }
+    }
+}
 
-        if let Some(cause) = &self.cause {
-            write!(f, "Caused by: {}", cause)?;
+/// Prints a [`StackTrace`] according to a [`StackTraceFormat`].
+struct DisplayStackTrace<'a, 's> {
+    trace: &'a StackTrace<'s>,
+    format: &'a StackTraceFormat,
+}
+
+impl Display for DisplayStackTrace<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if let Some(exception) = &self.trace.exception {
+            writeln!(f, "{}", exception)?;
         }
 
-        Ok(())
+        self.trace.fmt_enclosed(f, self.format, "")
     }
 }
 
 fn parse_stacktrace(content: &str) -> Option<StackTrace<'_>> {
-    let mut lines = content.lines().peekable();
+    let mut lines = split_lines(content).map(|(line, _)| line).peekable();
 
-    let exception = lines.peek().and_then(|line| parse_throwable(line));
+    let exception = lines
+        .peek()
+        .and_then(|line| parse_headline(line))
+        .map(|(_, throwable)| throwable);
     if exception.is_some() {
         lines.next();
     }
 
-    let mut stacktrace = StackTrace {
-        exception,
-        frames: vec![],
-        cause: None,
-    };
-    let mut current = &mut stacktrace;
-
-    for line in &mut lines {
-        if let Some(frame) = parse_frame(line) {
-            current.frames.push(frame);
-        } else if let Some(line) = line.strip_prefix("Caused by: ") {
-            current.cause = Some(Box::new(StackTrace {
-                exception: parse_throwable(line),
-                frames: vec![],
-                cause: None,
-            }));
-            // We just set the `cause` so it's safe to unwrap here
-            current = current.cause.as_deref_mut().unwrap();
-        }
-    }
+    let stacktrace = parse_enclosed(&mut lines, exception, 0);
 
     if stacktrace.exception.is_some() || !stacktrace.frames.is_empty() {
         Some(stacktrace)
@@ -146,6 +277,80 @@ fn parse_stacktrace(content: &str) -> Option<StackTrace<'_>> {
     }
 }
 
+/// Parses the frames, suppressed exceptions and cause of a StackTrace whose
+/// first line is indented by `indent`.
+///
+/// Just like Java prints them, suppressed exceptions are indented further
+/// than the StackTrace they belong to, whereas causes are indented the same.
+/// This returns on the first line that belongs to an enclosing StackTrace.
+fn parse_enclosed<'s, I>(
+    lines: &mut Peekable<I>,
+    exception: Option<Throwable<'s>>,
+    indent: usize,
+) -> StackTrace<'s>
+where
+    I: Iterator<Item = &'s str>,
+{
+    let mut stacktrace = StackTrace::new(exception, vec![]);
+
+    while let Some(line) = lines.peek() {
+        let trimmed = line.trim_start();
+        let line_indent = line.len() - trimmed.len();
+        if let Some(frame) = parse_frame(line) {
+            stacktrace.frames.push(frame);
+        } else if let Some(rest) = trimmed.strip_prefix("Suppressed: ") {
+            if line_indent <= indent {
+                break;
+            }
+            let exception = parse_throwable(rest);
+            lines.next();
+            let suppressed = parse_enclosed(lines, exception, line_indent);
+            stacktrace.suppressed.push(suppressed);
+            continue;
+        } else if let Some(rest) = trimmed.strip_prefix("Caused by: ") {
+            if line_indent < indent {
+                break;
+            }
+            let exception = parse_throwable(rest);
+            lines.next();
+            let cause = parse_enclosed(lines, exception, indent);
+            stacktrace.cause = Some(Box::new(cause));
+            // the cause is always printed last.
+            break;
+        }
+        lines.next();
+    }
+
+    stacktrace
+}
+
+/// Splits `input` into lines, along with their terminator.
+///
+/// Just like mapping files, lines may be terminated by `\n`, `\r\n` or a
+/// lone `\r`. The terminator of the last line is empty if it has none.
+pub(crate) fn split_lines(input: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = input;
+    iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let line = match find_line_terminator(rest.as_bytes()) {
+            Some(pos) => {
+                let len = if rest[pos..].starts_with("\r\n") {
+                    2
+                } else {
+                    1
+                };
+                let (line, terminator) = (&rest[..pos], &rest[pos..pos + len]);
+                rest = &rest[pos + len..];
+                (line, terminator)
+            }
+            None => (std::mem::take(&mut rest), ""),
+        };
+        Some(line)
+    })
+}
+
 /// A Java StackFrame.
 ///
 /// Basically a Rust version of the Java [`StackTraceElement`].
@@ -157,6 +362,8 @@ pub struct StackFrame<'s> {
     pub(crate) method: &'s str,
     pub(crate) line: usize,
     pub(crate) file: Option<&'s str>,
+    /// The class loader and module preceding the class, without the `/`.
+    pub(crate) module: Option<&'s str>,
 }
 
 impl<'s> StackFrame<'s> {
@@ -167,6 +374,7 @@ impl<'s> StackFrame<'s> {
             method,
             line,
             file: None,
+            module: None,
         }
     }
 
@@ -177,6 +385,7 @@ impl<'s> StackFrame<'s> {
             method,
             line,
             file: Some(file),
+            module: None,
         }
     }
 
@@ -197,6 +406,13 @@ impl<'s> StackFrame<'s> {
     ///         "Klass.java"
     ///     ))
     /// );
+    ///
+    /// // frames may be qualified by a class loader and a module since Java 9.
+    /// let parsed = StackFrame::try_parse(b"at app/mymodule@1.2/some.Klass.method(Klass.java:10)").unwrap();
+    /// assert_eq!(parsed.class(), "some.Klass");
+    /// assert_eq!(parsed.class_loader(), Some("app"));
+    /// assert_eq!(parsed.module_name(), Some("mymodule"));
+    /// assert_eq!(parsed.module_version(), Some("1.2"));
     /// ```
     pub fn try_parse(line: &'s [u8]) -> Option<Self> {
         let line = std::str::from_utf8(line).ok()?;
@@ -227,13 +443,83 @@ impl<'s> StackFrame<'s> {
     pub fn line(&self) -> usize {
         self.line
     }
+
+    /// Whether the frame likely belongs to code generated by the compiler.
+    ///
+    /// This is a heuristic based on the shape of the class, method and file
+    /// name alone, so it also works for frames which are not part of a
+    /// mapping, or whose mapping lacks R8 `synthesized` metadata. It detects:
+    ///
+    /// - Lambda and other synthetic classes, such as `-$$Lambda$Foo$AbCdEf`,
+    ///   `Foo$$ExternalSyntheticLambda1`, `Foo$$Lambda$14/0x0000000800c0b440`
+    ///   and `java.lang.invoke.LambdaForm$DMH`.
+    /// - Methods and classes generated by R8, which contain `$r8$`, such as
+    ///   `$r8$lambda$AbCdEf`.
+    /// - Accessors of private members such as `access$000`, lambda bodies
+    ///   such as `lambda$onCreate$0`, and Kotlin default argument bridges
+    ///   such as `foo$default`.
+    /// - Frames in the `D8$$SyntheticClass` file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::StackFrame;
+    ///
+    /// assert!(StackFrame::new("com.example.Foo$$ExternalSyntheticLambda0", "run", 0).is_synthetic());
+    /// assert!(StackFrame::new("com.example.Foo", "access$100", 12).is_synthetic());
+    /// assert!(!StackFrame::new("com.example.Foo", "onCreate", 12).is_synthetic());
+    /// ```
+    pub fn is_synthetic(&self) -> bool {
+        const CLASS_PATTERNS: &[&str] = &["$$ExternalSynthetic", "$$Lambda", "LambdaForm$", "$r8$"];
+
+        let access = self
+            .method
+            .strip_prefix("access$")
+            .is_some_and(|suffix| !suffix.is_empty() && suffix.bytes().all(|b| b.is_ascii_digit()));
+        CLASS_PATTERNS
+            .iter()
+            .any(|pattern| self.class.contains(pattern))
+            || self.method.contains("$r8$")
+            || self.method.starts_with("lambda$")
+            || self.method.ends_with("$default")
+            || access
+            || self.file == Some("D8$$SyntheticClass")
+    }
+
+    /// The name of the class loader of the StackFrame, as printed since Java 9.
+    pub fn class_loader(&self) -> Option<&str> {
+        let (class_loader, _) = self.module?.split_once('/')?;
+        Some(class_loader).filter(|name| !name.is_empty())
+    }
+
+    /// The module of the StackFrame along with its version, such as `java.base@11.0.2`.
+    fn module(&self) -> Option<&str> {
+        let module = self.module?;
+        let module = module.split_once('/').map_or(module, |(_, module)| module);
+        Some(module).filter(|module| !module.is_empty())
+    }
+
+    /// The name of the module of the StackFrame, as printed since Java 9.
+    pub fn module_name(&self) -> Option<&str> {
+        let module = self.module()?;
+        Some(module.split_once('@').map_or(module, |(name, _)| name))
+    }
+
+    /// The version of the module of the StackFrame, as printed since Java 9.
+    pub fn module_version(&self) -> Option<&str> {
+        Some(self.module()?.split_once('@')?.1)
+    }
 }
 
 impl<'s> Display for StackFrame<'s> {
     fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        f.write_str("at ")?;
+        if let Some(module) = self.module {
+            write!(f, "{}/", module)?;
+        }
         write!(
             f,
-            "at {}.{}({}:{})",
+            "{}.{}({}:{})",
             self.class,
             self.method,
             self.file.unwrap_or("<unknown>"),
@@ -242,30 +528,111 @@ impl<'s> Display for StackFrame<'s> {
     }
 }
 
+/// Anything identifying a frame to look up, see [`ProguardMapper::lookup`].
+///
+/// This is implemented for [`StackFrame`]s, and for tuples of the class and
+/// method, optionally followed by the line number. A line number of `None`
+/// is looked up like a frame without line, such as `(Unknown Source)`.
+///
+/// [`ProguardMapper::lookup`]: crate::ProguardMapper::lookup
+pub trait FrameKey<'s> {
+    /// Converts the key into a [`StackFrame`].
+    fn into_frame(self) -> StackFrame<'s>;
+}
+
+impl<'s> FrameKey<'s> for StackFrame<'s> {
+    fn into_frame(self) -> StackFrame<'s> {
+        self
+    }
+}
+
+impl<'s> FrameKey<'s> for &StackFrame<'s> {
+    fn into_frame(self) -> StackFrame<'s> {
+        self.clone()
+    }
+}
+
+impl<'s> FrameKey<'s> for (&'s str, &'s str) {
+    fn into_frame(self) -> StackFrame<'s> {
+        StackFrame::new(self.0, self.1, 0)
+    }
+}
+
+impl<'s> FrameKey<'s> for (&'s str, &'s str, usize) {
+    fn into_frame(self) -> StackFrame<'s> {
+        StackFrame::new(self.0, self.1, self.2)
+    }
+}
+
+impl<'s> FrameKey<'s> for (&'s str, &'s str, Option<usize>) {
+    fn into_frame(self) -> StackFrame<'s> {
+        StackFrame::new(self.0, self.1, self.2.unwrap_or(0))
+    }
+}
+
+/// A custom frame type which can be remapped in place, see
+/// [`ProguardMapper::retrace`].
+///
+/// Implement this for frame structs of your own, such as generated protobuf
+/// types, to remap them without converting them into [`StackFrame`]s.
+///
+/// [`ProguardMapper::retrace`]: crate::ProguardMapper::retrace
+pub trait Retraceable {
+    /// The obfuscated class of the frame.
+    fn class(&self) -> &str;
+    /// The obfuscated method of the frame.
+    fn method(&self) -> &str;
+    /// The line number of the frame, if known.
+    fn line(&self) -> Option<usize>;
+    /// Sets the remapped class of the frame.
+    fn set_class(&mut self, class: &str);
+    /// Sets the remapped method of the frame.
+    fn set_method(&mut self, method: &str);
+    /// Sets the remapped line number of the frame.
+    fn set_line(&mut self, line: Option<usize>);
+}
+
 /// Parses a single line from a Java StackTrace.
 ///
 /// Returns `None` if the line could not be parsed.
-pub(crate) fn parse_frame(line: &str) -> Option<StackFrame> {
+pub(crate) fn parse_frame(line: &str) -> Option<StackFrame<'_>> {
     let line = line.trim();
 
     if !line.starts_with("at ") || !line.ends_with(')') {
         return None;
     }
-    let mut arg_split = line[3..line.len() - 1].splitn(2, '(');
+    // Synthesized class names such as `-$$Lambda$Foo$AbCdEf` or
+    // `Foo$$ExternalSyntheticLambda1` never contain a `(`, but their source
+    // file is frequently something other than `File:line`.
+    let (target, location) = line[3..line.len() - 1].split_once('(')?;
 
-    let mut method_split = arg_split.next()?.rsplitn(2, '.');
-    let method = method_split.next()?;
-    let class = method_split.next()?;
+    // Since Java 9, the class may be preceded by `loader/module@version/`,
+    // whereas hidden classes end in a `/0x...` suffix.
+    let module_end = target
+        .rmatch_indices('/')
+        .map(|(pos, _)| pos)
+        .find(|pos| !target[pos + 1..].starts_with("0x"));
+    let (module, target) = match module_end {
+        Some(pos) => (Some(&target[..pos]), &target[pos + 1..]),
+        None => (None, target),
+    };
 
-    let mut file_split = arg_split.next()?.splitn(2, ':');
-    let file = file_split.next()?;
-    let line = file_split.next()?.parse().ok()?;
+    let (class, method) = target.rsplit_once('.')?;
+    if class.is_empty() || method.is_empty() {
+        return None;
+    }
+
+    let (file, line) = match location.rsplit_once(':') {
+        Some((file, line)) => (file, line.parse().ok()?),
+        None => (location, 0),
+    };
 
     Some(StackFrame {
         class,
         method,
         file: Some(file),
         line,
+        module,
     })
 }
 
@@ -348,18 +715,129 @@ pub(crate) fn parse_throwable(line: &str) -> Option<Throwable<'_>> {
     let mut class_split = line.splitn(2, ": ");
     let class = class_split.next()?;
     let message = class_split.next();
+    // `printStackTrace` leaves a dangling colon for an empty message.
+    let class = class.strip_suffix(':').unwrap_or(class);
 
-    if class.contains(' ') {
+    if class.is_empty() || class.contains(' ') {
         None
     } else {
         Some(Throwable { class, message })
     }
 }
 
+/// Parses the first line of a StackTrace, returning the prefix preceding the
+/// Throwable, such as `Exception in thread "main" `.
+pub(crate) fn parse_headline(line: &str) -> Option<(&str, Throwable<'_>)> {
+    let trimmed = line.trim_start();
+    let rest = trimmed
+        .strip_prefix("Exception in thread \"")
+        .and_then(|rest| rest.split_once("\" "))
+        .map_or(trimmed, |(_, rest)| rest);
+    let prefix = &line[..line.len() - rest.len()];
+    Some((prefix, parse_throwable(rest)?))
+}
+
+/// Splits the prefix that logcat adds to every line off `line`.
+///
+/// This supports the `threadtime`, `time`, `brief` and `tag` formats, such as
+/// `04-15 12:34:56.789  1234  5678 E AndroidRuntime: ` or
+/// `E/AndroidRuntime( 1234): `, as well as the format of the Android Studio
+/// logcat window, such as
+/// `2024-04-15 12:34:56.789 1234-5678/com.example E/AndroidRuntime: `. The
+/// prefix is empty if there is none.
+pub(crate) fn split_logcat_prefix(line: &str) -> (&str, &str) {
+    match logcat_prefix_len(line) {
+        Some(len) => line.split_at(len),
+        None => ("", line),
+    }
+}
+
+fn logcat_prefix_len(line: &str) -> Option<usize> {
+    let is_word = |word: &str, chars: &str| {
+        !word.is_empty()
+            && word
+                .chars()
+                .all(|c| c.is_ascii_digit() || chars.contains(c))
+    };
+
+    let mut rest = line;
+    if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        // the date and time, followed by the pid and tid in `threadtime`, or
+        // by `pid-tid/package` in Android Studio.
+        let is_process = |word: &str| match word.split_once('/') {
+            Some((ids, package)) => is_word(ids, "-") && !package.is_empty(),
+            None => is_word(word, ""),
+        };
+        let mut words = rest.split_whitespace();
+        let (date, time) = (words.next()?, words.next()?);
+        if !is_word(date, "-") || !date.contains('-') || !is_word(time, ":.") {
+            return None;
+        }
+        rest = rest.trim_start()[date.len()..].trim_start()[time.len()..].trim_start();
+        while let Some(word) = rest.split_whitespace().next().filter(|w| is_process(w)) {
+            rest = rest[word.len()..].trim_start();
+        }
+    }
+
+    let mut chars = rest.chars();
+    match (chars.next()?, chars.next()?) {
+        ('V' | 'D' | 'I' | 'W' | 'E' | 'F' | 'A', ' ' | '/') => {}
+        _ => return None,
+    }
+    // the tag, which is followed by the pid in `time` and `brief`.
+    let end = rest.find(": ")? + 2;
+    Some(line.len() - rest.len() + end)
+}
+
+/// Parses a `Caused by: ` or `Suppressed: ` line, returning the prefix
+/// preceding the Throwable.
+pub(crate) fn parse_cause(line: &str) -> Option<(&str, Throwable<'_>)> {
+    let trimmed = line.trim_start();
+    let rest = trimmed
+        .strip_prefix("Caused by: ")
+        .or_else(|| trimmed.strip_prefix("Suppressed: "))?;
+    let prefix = &line[..line.len() - rest.len()];
+    Some((prefix, parse_throwable(rest)?))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn logcat_prefixes() {
+        for (line, prefix) in [
+            (
+                "04-15 12:34:56.789  1234  5678 E AndroidRuntime: \tat a.b(SourceFile:1)",
+                "04-15 12:34:56.789  1234  5678 E AndroidRuntime: ",
+            ),
+            (
+                "2024-04-15 12:34:56.789 1234-5678/com.example E/AndroidRuntime: at a.b",
+                "2024-04-15 12:34:56.789 1234-5678/com.example E/AndroidRuntime: ",
+            ),
+            (
+                "2024-04-15 12:34:56.789 1234-5678/? W/System.err: \tat a.b",
+                "2024-04-15 12:34:56.789 1234-5678/? W/System.err: ",
+            ),
+            (
+                "04-15 12:34:56.789 E/AndroidRuntime( 1234): Caused by: a.c",
+                "04-15 12:34:56.789 E/AndroidRuntime( 1234): ",
+            ),
+            (
+                "E/AndroidRuntime( 1234): \tat a.b",
+                "E/AndroidRuntime( 1234): ",
+            ),
+            ("W/System.err: java.lang.Exception", "W/System.err: "),
+            ("\tat a.b(SourceFile:1)", ""),
+            ("java.lang.RuntimeException: boom", ""),
+            ("12:34 is not a date: at a.b", ""),
+        ] {
+            let (actual, rest) = split_logcat_prefix(line);
+            assert_eq!(actual, prefix, "{:?}", line);
+            assert_eq!(format!("{}{}", actual, rest), line);
+        }
+    }
+
     #[test]
     fn print_stack_trace() {
         let trace = StackTrace {
@@ -372,6 +850,7 @@ mod tests {
                 method: "show",
                 line: 5,
                 file: Some("Util.java"),
+                module: None,
             }],
             cause: Some(Box::new(StackTrace {
                 exception: Some(Throwable {
@@ -383,9 +862,12 @@ mod tests {
                     method: "parse",
                     line: 115,
                     file: None,
+                    module: None,
                 }],
                 cause: None,
+                suppressed: vec![],
             })),
+            suppressed: vec![],
         };
         let expect = "\
 com.example.MainFragment: Crash
@@ -396,6 +878,156 @@ Caused by: com.example.Other: Invalid data
         assert_eq!(expect, trace.to_string());
     }
 
+    #[test]
+    fn suppressed() {
+        // as printed by Java, with nested suppressed exceptions and causes.
+        let input = "\
+a.Main: main
+\tat a.A.run(A.java:1)
+\tSuppressed: a.First: first
+\t\tat a.B.close(B.java:2)
+\t\t... 1 more
+\t\tSuppressed: a.Nested
+\t\t\tat a.C.close(C.java:3)
+\tCaused by: a.FirstCause
+\t\tat a.D.open(D.java:4)
+\t\t... 2 more
+\tSuppressed: a.Second
+Caused by: a.MainCause: cause
+\tat a.E.call(E.java:5)
+\t... 1 more
+\tSuppressed: a.CauseSuppressed
+";
+        let frame = |class, line| StackFrame {
+            class,
+            method: "m",
+            line,
+            file: None,
+            module: None,
+        };
+        let trace = parse_stacktrace(input).unwrap();
+
+        fn strip<'a>(trace: &StackTrace<'a>) -> (Option<&'a str>, Vec<(&'a str, usize)>) {
+            let frames = trace.frames.iter().map(|f| (f.class, f.line)).collect();
+            (trace.exception.as_ref().map(|e| e.class), frames)
+        }
+        assert_eq!(strip(&trace), (Some("a.Main"), vec![("a.A", 1)]));
+        assert_eq!(trace.suppressed.len(), 2);
+
+        let first = &trace.suppressed[0];
+        assert_eq!(strip(first), (Some("a.First"), vec![("a.B", 2)]));
+        assert_eq!(first.suppressed.len(), 1);
+        assert_eq!(
+            strip(&first.suppressed[0]),
+            (Some("a.Nested"), vec![("a.C", 3)])
+        );
+        let first_cause = first.cause.as_deref().unwrap();
+        assert_eq!(strip(first_cause), (Some("a.FirstCause"), vec![("a.D", 4)]));
+
+        assert_eq!(strip(&trace.suppressed[1]), (Some("a.Second"), vec![]));
+
+        let cause = trace.cause.as_deref().unwrap();
+        assert_eq!(strip(cause), (Some("a.MainCause"), vec![("a.E", 5)]));
+        assert_eq!(cause.suppressed.len(), 1);
+        assert_eq!(
+            strip(&cause.suppressed[0]),
+            (Some("a.CauseSuppressed"), vec![])
+        );
+
+        // the printed trace parses back to the same structure.
+        let printed = trace.to_string();
+        assert_eq!(parse_stacktrace(&printed), Some(trace));
+
+        let trace = StackTrace::new(Some(Throwable::new("a.Main")), vec![frame("a.A", 1)])
+            .with_suppressed(vec![StackTrace::with_cause(
+                Some(Throwable::new("a.First")),
+                vec![frame("a.B", 2)],
+                StackTrace::new(Some(Throwable::new("a.Cause")), vec![frame("a.C", 3)]),
+            )]);
+        let expect = "\
+a.Main
+    at a.A.m(<unknown>:1)
+    Suppressed: a.First
+        at a.B.m(<unknown>:2)
+    Caused by: a.Cause
+        at a.C.m(<unknown>:3)
+";
+        assert_eq!(trace.to_string(), expect);
+    }
+
+    #[test]
+    fn format() {
+        let frame = |class| StackFrame::with_file(class, "m", 1, "F.java");
+        let trace = StackTrace::with_cause(
+            Some(Throwable::with_message("a.Main", "main")),
+            vec![frame("a.A"), frame("a.B"), frame("a.C")],
+            StackTrace::new(Some(Throwable::new("a.Cause")), vec![frame("a.D")]),
+        )
+        .with_suppressed(vec![StackTrace::new(
+            Some(Throwable::new("a.Suppressed")),
+            vec![frame("a.E"), frame("a.F")],
+        )]);
+
+        assert_eq!(
+            trace.display_with(&StackTraceFormat::default()).to_string(),
+            trace.to_string()
+        );
+
+        let format = StackTraceFormat {
+            indent_width: 2,
+            caused_by: "cause: ".into(),
+            suppressed: "suppressed: ".into(),
+            max_frames: Some(1),
+            ..Default::default()
+        };
+        let expect = "\
+a.Main: main
+  at a.A.m(F.java:1)
+  ... 2 more
+  suppressed: a.Suppressed
+    at a.E.m(F.java:1)
+    ... 1 more
+cause: a.Cause
+  at a.D.m(F.java:1)
+";
+        assert_eq!(trace.display_with(&format).to_string(), expect);
+
+        // Java's own format parses back to the same structure.
+        let format = StackTraceFormat {
+            indent_with_tabs: true,
+            ..Default::default()
+        };
+        let printed = trace.display_with(&format).to_string();
+        assert!(printed.contains("\n\tSuppressed: a.Suppressed\n\t\tat a.E.m"));
+        assert_eq!(parse_stacktrace(&printed), Some(trace));
+    }
+
+    #[test]
+    fn line_terminators() {
+        let lines: Vec<_> = split_lines("a\nb\r\nc\rd\n\re").collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("a", "\n"),
+                ("b", "\r\n"),
+                ("c", "\r"),
+                ("d", "\n"),
+                ("", "\r"),
+                ("e", "")
+            ]
+        );
+
+        let expect = parse_stacktrace("a.b: boom\n    at a.C.d(C.java:1)\nCaused by: a.E\n");
+        assert!(expect.as_ref().is_some_and(|trace| trace.cause.is_some()));
+        for terminator in ["\r\n", "\r"] {
+            let input = format!(
+                "a.b: boom{0}    at a.C.d(C.java:1){0}Caused by: a.E{0}",
+                terminator
+            );
+            assert_eq!(parse_stacktrace(&input), expect);
+        }
+    }
+
     #[test]
     fn stack_frame() {
         let line = "at com.example.MainFragment.onClick(SourceFile:1)";
@@ -405,6 +1037,7 @@ Caused by: com.example.Other: Invalid data
             method: "onClick",
             line: 1,
             file: Some("SourceFile"),
+            module: None,
         });
 
         assert_eq!(expect, stack_frame);
@@ -420,6 +1053,133 @@ Caused by: com.example.Other: Invalid data
         assert_eq!(expect, stack_frame);
     }
 
+    #[test]
+    fn headline() {
+        let throwable = Throwable {
+            class: "a.b",
+            message: Some("boom"),
+        };
+        assert_eq!(parse_headline("a.b: boom"), Some(("", throwable.clone())));
+        assert_eq!(
+            parse_headline("Exception in thread \"main thread\" a.b: boom"),
+            Some(("Exception in thread \"main thread\" ", throwable.clone()))
+        );
+        assert_eq!(
+            parse_cause("\tCaused by: a.b: boom"),
+            Some(("\tCaused by: ", throwable))
+        );
+        assert_eq!(
+            parse_cause("Caused by: a.b:"),
+            Some(("Caused by: ", Throwable::new("a.b")))
+        );
+        assert_eq!(
+            parse_cause("\t\tSuppressed: a.b:"),
+            Some(("\t\tSuppressed: ", Throwable::new("a.b")))
+        );
+        assert_eq!(parse_cause("a.b: boom"), None);
+        assert_eq!(parse_headline("Exception in thread \"main\" "), None);
+    }
+
+    #[test]
+    fn stack_frame_synthetic() {
+        let line = "at com.example.-$$Lambda$Foo$AbCdEf.run(Unknown Source:2)";
+        assert_eq!(
+            parse_frame(line),
+            Some(StackFrame {
+                class: "com.example.-$$Lambda$Foo$AbCdEf",
+                method: "run",
+                line: 2,
+                file: Some("Unknown Source"),
+                module: None,
+            })
+        );
+
+        let line = "at com.example.Foo$$ExternalSyntheticLambda1.run(D8$$SyntheticClass)";
+        assert_eq!(
+            parse_frame(line),
+            Some(StackFrame {
+                class: "com.example.Foo$$ExternalSyntheticLambda1",
+                method: "run",
+                line: 0,
+                file: Some("D8$$SyntheticClass"),
+                module: None,
+            })
+        );
+
+        let line = "at com.example.Foo$$Lambda$14/0x0000000800c0b440.accept(Unknown Source)";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!(frame.class, "com.example.Foo$$Lambda$14/0x0000000800c0b440");
+        assert_eq!(frame.method, "accept");
+        assert_eq!(frame.file, Some("Unknown Source"));
+
+        assert_eq!(parse_frame("at .run(Unknown Source)"), None);
+        assert_eq!(parse_frame("at com.example.Foo.(Unknown Source)"), None);
+        assert_eq!(parse_frame("at com.example.Foo.run(Foo.java:x)"), None);
+    }
+
+    #[test]
+    fn stack_frame_is_synthetic() {
+        for (line, synthetic) in [
+            ("at com.example.-$$Lambda$Foo$AbCdEf.run(Unknown Source:2)", true),
+            ("at com.example.Foo$$ExternalSyntheticLambda1.run(D8$$SyntheticClass)", true),
+            ("at com.example.Foo$$Lambda$14/0x0000000800c0b440.accept(Unknown Source)", true),
+            ("at java.base/java.lang.invoke.LambdaForm$DMH/0x0000000800c0b440.invokeStatic(Unknown Source)", true),
+            ("at com.example.Foo.$r8$lambda$AbCdEf(Foo.java:1)", true),
+            ("at com.example.Foo.lambda$onCreate$0(Foo.java:1)", true),
+            ("at com.example.Foo.access$000(Foo.java:1)", true),
+            ("at com.example.Foo.bar$default(Foo.kt:1)", true),
+            ("at com.example.a.b(SourceFile:1)", false),
+            ("at com.example.Foo$1.run(Foo.java:1)", false),
+            ("at com.example.Foo.access$(Foo.java:1)", false),
+            ("at com.example.Foo.accessLambda(Foo.java:1)", false),
+        ] {
+            assert_eq!(parse_frame(line).unwrap().is_synthetic(), synthetic, "{}", line);
+        }
+    }
+
+    #[test]
+    fn stack_frame_module() {
+        let line = "at mymodule@1.2/com.example.a.b(Foo.java:10)";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!(
+            frame,
+            StackFrame {
+                class: "com.example.a",
+                method: "b",
+                line: 10,
+                file: Some("Foo.java"),
+                module: Some("mymodule@1.2"),
+            }
+        );
+        assert_eq!(frame.class_loader(), None);
+        assert_eq!(frame.module_name(), Some("mymodule"));
+        assert_eq!(frame.module_version(), Some("1.2"));
+        assert_eq!(frame.to_string(), line);
+
+        let line = "at java.base/java.lang.Thread.run(Thread.java:829)";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!(frame.class, "java.lang.Thread");
+        assert_eq!(frame.module_name(), Some("java.base"));
+        assert_eq!(frame.module_version(), None);
+        assert_eq!(frame.to_string(), line);
+
+        let line = "at app//com.example.Foo.bar(Foo.java:1)";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!(frame.class, "com.example.Foo");
+        assert_eq!(frame.class_loader(), Some("app"));
+        assert_eq!(frame.module_name(), None);
+        assert_eq!(frame.to_string(), line);
+
+        let line = "at java.base/java.lang.invoke.LambdaForm$DMH/0x0000000800c0b440.invokeStatic(Unknown Source)";
+        let frame = parse_frame(line).unwrap();
+        assert_eq!(
+            frame.class,
+            "java.lang.invoke.LambdaForm$DMH/0x0000000800c0b440"
+        );
+        assert_eq!(frame.method, "invokeStatic");
+        assert_eq!(frame.module_name(), Some("java.base"));
+    }
+
     #[test]
     fn print_stack_frame() {
         let frame = StackFrame {
@@ -427,6 +1187,7 @@ Caused by: com.example.Other: Invalid data
             method: "onClick",
             line: 1,
             file: None,
+            module: None,
         };
 
         assert_eq!(
@@ -439,6 +1200,7 @@ Caused by: com.example.Other: Invalid data
             method: "onClick",
             line: 1,
             file: Some("SourceFile"),
+            module: None,
         };
 
         assert_eq!(
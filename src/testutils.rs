@@ -0,0 +1,343 @@
+//! Synthetic Proguard Mapping Files for tests and benchmarks.
+//!
+//! This module is only available with the `testutils` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! use proguard::testutils::SyntheticMapping;
+//! use proguard::{ProguardMapper, ProguardMapping};
+//!
+//! let synthetic = SyntheticMapping {
+//!     classes: 20,
+//!     methods: 5,
+//!     ..Default::default()
+//! };
+//! let source = synthetic.generate();
+//!
+//! let mapping = ProguardMapping::new(&source);
+//! assert!(mapping.is_valid());
+//! assert_eq!(mapping.classes().count(), 20);
+//!
+//! let mapper = ProguardMapper::new(mapping);
+//! let remapped = mapper.remap_stacktrace(&synthetic.stacktrace()).unwrap();
+//! assert!(remapped.contains("at com.example.p0.Class0"));
+//! ```
+
+use std::fmt::Write;
+
+/// The types used for fields, arguments and return values.
+const TYPES: &[&str] = &[
+    "int",
+    "long",
+    "boolean",
+    "java.lang.String",
+    "java.lang.Object",
+    "java.util.List",
+    "android.content.Context",
+    "byte[]",
+];
+
+/// The number of classes in each synthetic package.
+const CLASSES_PER_PACKAGE: usize = 16;
+
+/// The configuration of a synthetic mapping.
+///
+/// The generated mapping is deterministic, so the same configuration always
+/// produces the same output. Class `i` is named `com.example.p{i / 16}.Class{i}`
+/// and has methods named `method{j}`, which are obfuscated to short names such
+/// as `a`, `b`, …, `aa`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SyntheticMapping {
+    /// The number of classes.
+    pub classes: usize,
+    /// The number of methods of each class.
+    pub methods: usize,
+    /// The number of fields of each class.
+    pub fields: usize,
+    /// Whether methods have line mappings.
+    ///
+    /// With line mappings, some methods also contain an inlined call to the
+    /// previous class.
+    pub line_info: bool,
+    /// Whether to add R8 headers and metadata, such as `sourceFile` records
+    /// and synthesized methods.
+    pub r8_metadata: bool,
+    /// The seed that varies types and line numbers.
+    pub seed: u64,
+}
+
+impl Default for SyntheticMapping {
+    fn default() -> Self {
+        Self {
+            classes: 100,
+            methods: 10,
+            fields: 2,
+            line_info: true,
+            r8_metadata: false,
+            seed: 0,
+        }
+    }
+}
+
+impl SyntheticMapping {
+    /// Generates the mapping file.
+    pub fn generate(&self) -> Vec<u8> {
+        let mut rng = Rng::new(self.seed);
+        let mut out = String::new();
+
+        if self.r8_metadata {
+            out.push_str("# compiler: R8\n# compiler_version: 8.2.42\n# min_api: 24\n");
+        }
+
+        for class in 0..self.classes {
+            let _ = writeln!(
+                out,
+                "{} -> {}:",
+                original_class(class),
+                obfuscated_class(class)
+            );
+            if self.r8_metadata {
+                let _ = writeln!(
+                    out,
+                    "# {{\"id\":\"sourceFile\",\"fileName\":\"Class{}.kt\"}}",
+                    class
+                );
+            }
+
+            for field in 0..self.fields {
+                let _ = writeln!(
+                    out,
+                    "    {} field{} -> {}",
+                    rng.pick(TYPES),
+                    field,
+                    short_name(field)
+                );
+            }
+
+            let mut obfuscated_line = 1;
+            for method in 0..self.methods {
+                let return_type = rng.pick(TYPES);
+                let argument = rng.pick(TYPES);
+                let obfuscated = short_name(method);
+
+                if !self.line_info {
+                    let _ = writeln!(
+                        out,
+                        "    {} method{}({}) -> {}",
+                        return_type, method, argument, obfuscated
+                    );
+                } else {
+                    let lines = 1 + rng.below(5);
+                    let start = obfuscated_line;
+                    let end = start + lines - 1;
+                    obfuscated_line = end + 1;
+
+                    // inline a call to the previous class into some methods.
+                    if class > 0 && rng.below(4) == 0 {
+                        let _ = writeln!(
+                            out,
+                            "    {}:{}:void {}.method0():{}:{} -> {}",
+                            start,
+                            end,
+                            original_class(class - 1),
+                            10,
+                            10 + lines - 1,
+                            obfuscated
+                        );
+                        let _ = writeln!(
+                            out,
+                            "    {}:{}:{} method{}({}):{} -> {}",
+                            start,
+                            end,
+                            return_type,
+                            method,
+                            argument,
+                            method_line(method),
+                            obfuscated
+                        );
+                    } else {
+                        let original = method_line(method);
+                        let _ = writeln!(
+                            out,
+                            "    {}:{}:{} method{}({}):{}:{} -> {}",
+                            start,
+                            end,
+                            return_type,
+                            method,
+                            argument,
+                            original,
+                            original + lines - 1,
+                            obfuscated
+                        );
+                    }
+                }
+
+                if self.r8_metadata && rng.below(10) == 0 {
+                    out.push_str("      # {\"id\":\"com.android.tools.r8.synthesized\"}\n");
+                }
+            }
+        }
+
+        out.into_bytes()
+    }
+
+    /// Generates an obfuscated stack trace, with one frame for the first
+    /// method of each class.
+    ///
+    /// Every frame can be remapped with the generated mapping, unless the
+    /// mapping has no methods.
+    pub fn stacktrace(&self) -> String {
+        let mut out = String::from("java.lang.RuntimeException: synthetic\n");
+        if self.methods == 0 {
+            return out;
+        }
+        for class in 0..self.classes {
+            let _ = writeln!(
+                out,
+                "    at {}.{}(SourceFile:1)",
+                obfuscated_class(class),
+                short_name(0)
+            );
+        }
+        out
+    }
+}
+
+/// The original name of the class with index `class`.
+fn original_class(class: usize) -> String {
+    format!(
+        "com.example.p{}.Class{}",
+        class / CLASSES_PER_PACKAGE,
+        class
+    )
+}
+
+/// The obfuscated name of the class with index `class`.
+fn obfuscated_class(class: usize) -> String {
+    format!(
+        "{}.{}",
+        short_name(class / CLASSES_PER_PACKAGE),
+        short_name(class % CLASSES_PER_PACKAGE)
+    )
+}
+
+/// The original first line of the method with index `method`.
+fn method_line(method: usize) -> usize {
+    100 + method * 10
+}
+
+/// Formats `index` as a short obfuscated name: `a`, …, `z`, `aa`, `ab`, ….
+fn short_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'a' + (index % 26) as u8);
+        index /= 26;
+        if index == 0 {
+            break;
+        }
+        index -= 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
+
+/// A small xorshift generator, so output is reproducible without dependencies.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck at 0.
+        Self(seed ^ 0x9e37_79b9_7f4a_7c15)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+        items[self.below(items.len())]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProguardMapper, ProguardMapping, StackFrame};
+
+    #[test]
+    fn short_names() {
+        assert_eq!(short_name(0), "a");
+        assert_eq!(short_name(25), "z");
+        assert_eq!(short_name(26), "aa");
+        assert_eq!(short_name(27), "ab");
+        assert_eq!(short_name(26 + 26 * 26), "aaa");
+    }
+
+    #[test]
+    fn deterministic() {
+        let synthetic = SyntheticMapping::default();
+        assert_eq!(synthetic.generate(), synthetic.generate());
+
+        let other = SyntheticMapping {
+            seed: 1,
+            ..Default::default()
+        };
+        assert_ne!(synthetic.generate(), other.generate());
+    }
+
+    #[test]
+    fn valid_mappings() {
+        for line_info in [false, true] {
+            for r8_metadata in [false, true] {
+                let synthetic = SyntheticMapping {
+                    classes: 40,
+                    methods: 30,
+                    fields: 3,
+                    line_info,
+                    r8_metadata,
+                    seed: 7,
+                };
+                let source = synthetic.generate();
+                let mapping = ProguardMapping::new(&source);
+                assert!(mapping.is_valid());
+                assert_eq!(mapping.classes().count(), 40);
+                assert_eq!(mapping.summary().compiler_version().is_some(), r8_metadata);
+                for class in mapping.classes() {
+                    assert_eq!(class.fields().count(), 3);
+                    let methods = class.methods().filter(|m| m.original_class.is_none());
+                    assert_eq!(methods.count(), 30);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn remap() {
+        let synthetic = SyntheticMapping {
+            classes: 20,
+            methods: 3,
+            ..Default::default()
+        };
+        let source = synthetic.generate();
+        let mapper = ProguardMapper::new(ProguardMapping::new(&source));
+
+        let frame = StackFrame::new("b.a", "a", 1);
+        let outer = mapper.remap_frame(&frame).last().unwrap();
+        assert_eq!(
+            (outer.class(), outer.method(), outer.line()),
+            ("com.example.p1.Class16", "method0", 100)
+        );
+
+        let remapped = mapper.remap_stacktrace(&synthetic.stacktrace()).unwrap();
+        assert!(!remapped.contains("at a.a."));
+        assert!(remapped.contains("at com.example.p1.Class19.method0"));
+    }
+}
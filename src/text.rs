@@ -0,0 +1,94 @@
+//! Remapping of class names in free-form text.
+
+/// Whether `c` can be part of a (qualified) Java class name.
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '.'
+}
+
+/// Replaces the longest prefix of `token` that `remap` knows about.
+///
+/// Only prefixes ending at a `.` or `$` separator, or at the end of the token,
+/// are considered, so that names are never split in the middle. A prefix
+/// without any `.` is never remapped, as those are indistinguishable from
+/// ordinary words.
+fn remap_token<'r, F>(output: &mut String, token: &str, remap: &mut F)
+where
+    F: FnMut(&str) -> Option<&'r str>,
+{
+    let ends = token
+        .char_indices()
+        .filter(|&(_, c)| c == '.' || c == '$')
+        .map(|(i, _)| i)
+        .chain(Some(token.len()));
+    let mut ends: Vec<_> = ends.collect();
+    ends.dedup();
+
+    for &end in ends.iter().rev() {
+        let prefix = &token[..end];
+        if !prefix.contains('.') {
+            break;
+        }
+        if let Some(original) = remap(prefix) {
+            output.push_str(original);
+            output.push_str(&token[end..]);
+            return;
+        }
+    }
+    output.push_str(token);
+}
+
+/// Replaces every token in `input` that names a class known to `remap`.
+pub(crate) fn remap_tokens<'r, F>(input: &str, mut remap: F) -> String
+where
+    F: FnMut(&str) -> Option<&'r str>,
+{
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find(is_name_char) {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let len = rest.find(|c| !is_name_char(c)).unwrap_or(rest.len());
+        remap_token(&mut output, &rest[..len], &mut remap);
+        rest = &rest[len..];
+    }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remap(input: &str) -> String {
+        remap_tokens(input, |class| match class {
+            "a" => Some("Unused"),
+            "a.b" => Some("com.example.Outer"),
+            "a.b$c" => Some("com.example.Outer$Inner"),
+            "a.bc" => Some("com.example.Other"),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn longest_match() {
+        assert_eq!(remap("a.b"), "com.example.Outer");
+        assert_eq!(remap("a.b$c"), "com.example.Outer$Inner");
+        assert_eq!(remap("a.b$d"), "com.example.Outer$d");
+        assert_eq!(remap("a.bc"), "com.example.Other");
+        assert_eq!(remap("a.b.onClick"), "com.example.Outer.onClick");
+    }
+
+    #[test]
+    fn word_boundaries() {
+        assert_eq!(
+            remap("Clicked a.b, then a.bcd and xa.b (via a.b$c)."),
+            "Clicked com.example.Outer, then a.bcd and xa.b (via com.example.Outer$Inner)."
+        );
+        assert_eq!(remap("a cat"), "a cat");
+        assert_eq!(
+            remap("ünïcode a.b→a.bc"),
+            "ünïcode com.example.Outer→com.example.Other"
+        );
+        assert_eq!(remap(""), "");
+    }
+}
@@ -0,0 +1,257 @@
+//! Remapping of class names in view hierarchy JSON documents.
+//!
+//! The view hierarchy is a tree of windows and views, as captured by the
+//! Sentry Android SDK. Every node names the class of its view in a `type`
+//! field, which is obfuscated in minified apps. Rather than deserializing the
+//! whole document, the walker below copies the input verbatim and only
+//! replaces the string values of those fields.
+
+use std::error::Error;
+use std::fmt;
+
+/// Nesting depth after which a document is rejected.
+const MAX_DEPTH: usize = 512;
+
+/// Error returned by [`ProguardMapper::remap_view_hierarchy`](crate::ProguardMapper::remap_view_hierarchy).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewHierarchyError {
+    offset: usize,
+}
+
+impl ViewHierarchyError {
+    /// The byte offset at which the document is malformed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for ViewHierarchyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid view hierarchy JSON at offset {}", self.offset)
+    }
+}
+
+impl Error for ViewHierarchyError {}
+
+struct Walker<'a, F> {
+    input: &'a str,
+    pos: usize,
+    /// The input up to this offset has been copied to `output`.
+    copied: usize,
+    output: String,
+    remap: F,
+}
+
+impl<'a, 'r, F> Walker<'a, F>
+where
+    F: FnMut(&str) -> Option<&'r str>,
+{
+    fn error(&self) -> ViewHierarchyError {
+        ViewHierarchyError { offset: self.pos }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), ViewHierarchyError> {
+        self.skip_whitespace();
+        if self.peek() != Some(byte) {
+            return Err(self.error());
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    /// Consumes a string and returns its raw contents, without the quotes.
+    fn string(&mut self) -> Result<&'a str, ViewHierarchyError> {
+        self.expect(b'"')?;
+        let start = self.pos;
+        loop {
+            match self.peek() {
+                Some(b'"') => break,
+                Some(b'\\') => self.pos += 2,
+                Some(_) => self.pos += 1,
+                None => return Err(self.error()),
+            }
+        }
+        let contents = self
+            .input
+            .get(start..self.pos)
+            .ok_or_else(|| self.error())?;
+        self.pos += 1;
+        Ok(contents)
+    }
+
+    /// Consumes a string value, replacing it if it names an obfuscated class.
+    fn class_name(&mut self) -> Result<(), ViewHierarchyError> {
+        let class = self.string()?;
+        // class names never need escaping, so escaped strings are left as-is.
+        if class.contains('\\') {
+            return Ok(());
+        }
+        if let Some(original) = (self.remap)(class) {
+            let start = self.pos - class.len() - 1;
+            self.output.push_str(&self.input[self.copied..start]);
+            for c in original.chars() {
+                match c {
+                    '"' => self.output.push_str("\\\""),
+                    '\\' => self.output.push_str("\\\\"),
+                    c if c.is_control() => {
+                        self.output.push_str(&format!("\\u{:04x}", c as u32));
+                    }
+                    c => self.output.push(c),
+                }
+            }
+            self.copied = self.pos - 1;
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, depth: usize) -> Result<(), ViewHierarchyError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error());
+        }
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'{') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                loop {
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    self.skip_whitespace();
+                    if key == "type" && self.peek() == Some(b'"') {
+                        self.class_name()?;
+                    } else {
+                        self.value(depth + 1)?;
+                    }
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => break,
+                        _ => return Err(self.error()),
+                    }
+                }
+                self.pos += 1;
+            }
+            Some(b'[') => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                loop {
+                    self.value(depth + 1)?;
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => break,
+                        _ => return Err(self.error()),
+                    }
+                }
+                self.pos += 1;
+            }
+            Some(b'"') => {
+                self.string()?;
+            }
+            _ => {
+                // numbers, `true`, `false` and `null`.
+                let start = self.pos;
+                while let Some(b'a'..=b'z' | b'0'..=b'9' | b'-' | b'+' | b'.' | b'E') = self.peek()
+                {
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return Err(self.error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Replaces the value of every `type` field in `input` using `remap`.
+pub(crate) fn remap_types<'r, F>(input: &str, remap: F) -> Result<String, ViewHierarchyError>
+where
+    F: FnMut(&str) -> Option<&'r str>,
+{
+    let mut walker = Walker {
+        input,
+        pos: 0,
+        copied: 0,
+        output: String::with_capacity(input.len()),
+        remap,
+    };
+    walker.value(0)?;
+    walker.skip_whitespace();
+    if walker.pos != input.len() {
+        return Err(walker.error());
+    }
+    walker.output.push_str(&input[walker.copied..]);
+    Ok(walker.output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remap(input: &str) -> Result<String, ViewHierarchyError> {
+        remap_types(input, |class| match class {
+            "a.a" => Some("com.example.MainView"),
+            "a.b" => Some("com.example.Quoted\"View"),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn remaps_nested_types() {
+        let input = r#"{
+  "rendering_system": "android_view_system",
+  "windows": [
+    {
+      "type": "com.android.internal.policy.DecorView",
+      "alpha": 1.0,
+      "visible": true,
+      "children": [
+        {"type": "a.a", "identifier": "a.a", "x": -1.5e2, "children": []},
+        {"type" : "a.b", "tag": null}
+      ]
+    }
+  ]
+}"#;
+        let expected = input
+            .replace(r#""type": "a.a""#, r#""type": "com.example.MainView""#)
+            .replace(
+                r#""type" : "a.b""#,
+                r#""type" : "com.example.Quoted\"View""#,
+            );
+        assert_eq!(remap(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn leaves_other_values() {
+        let input = r#"{"type": 1, "types": "a.a", "nested": {"class": "a.a"}}"#;
+        assert_eq!(remap(input).unwrap(), input);
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(remap("").unwrap_err().offset(), 0);
+        assert_eq!(remap(r#"{"type": "a.a""#).unwrap_err().offset(), 14);
+        assert_eq!(remap(r#"{"type" "a.a"}"#).unwrap_err().offset(), 8);
+        assert_eq!(remap(r#"["a.a"] x"#).unwrap_err().offset(), 8);
+        assert!(remap(&"[".repeat(1000)).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Single-pass consumption of Proguard Mapping Files.
+
+use std::str;
+
+use crate::classes::{FieldMapping, MethodMapping};
+use crate::mapping::{
+    find_line_terminator, parse_class, parse_header, parse_member, split_line, Member,
+};
+
+/// A visitor over the records of a mapping, driven by [`ProguardMapping::visit`].
+///
+/// Records are visited in file order. Members follow the class they belong
+/// to, so a visitor building a custom index can keep track of the current
+/// class itself. All methods do nothing by default.
+///
+/// [`ProguardMapping::visit`]: crate::ProguardMapping::visit
+pub trait MappingVisitor<'s> {
+    /// Visits a header, such as `# compiler: R8`.
+    fn visit_header(&mut self, key: &'s str, value: Option<&'s str>) {
+        let _ = (key, value);
+    }
+
+    /// Visits a class line.
+    fn visit_class(&mut self, original: &'s str, obfuscated: &'s str) {
+        let _ = (original, obfuscated);
+    }
+
+    /// Visits a field of the last visited class.
+    fn visit_field(&mut self, field: &FieldMapping<'s>) {
+        let _ = field;
+    }
+
+    /// Visits a method of the last visited class.
+    fn visit_method(&mut self, method: &MethodMapping<'s>) {
+        let _ = method;
+    }
+
+    /// Visits R8 metadata, such as `{"id":"sourceFile","fileName":"Foo.kt"}`.
+    ///
+    /// The metadata belongs to the last visited class or member, or to the
+    /// whole mapping if it precedes all classes. It is passed as raw JSON,
+    /// and not to [`visit_header`](Self::visit_header).
+    fn visit_r8_header(&mut self, json: &'s str) {
+        let _ = json;
+    }
+}
+
+/// Drives `visitor` over all valid records in `source`.
+pub(crate) fn visit<'s, V>(source: &'s [u8], visitor: &mut V)
+where
+    V: MappingVisitor<'s> + ?Sized,
+{
+    let mut slice = source;
+    while !slice.is_empty() {
+        let (line, rest) = split_line(slice);
+        slice = rest;
+        visit_line(line, visitor);
+    }
+}
+
+/// Visits a single line, skipping it if it is empty or malformed.
+///
+/// Lines are dispatched on their shape, so only the parser for their kind of
+/// record runs.
+fn visit_line<'s, V>(line: &'s [u8], visitor: &mut V)
+where
+    V: MappingVisitor<'s> + ?Sized,
+{
+    let line = match str::from_utf8(line) {
+        Ok(line) => line,
+        Err(_) => return,
+    };
+    let unindented = line.trim_start_matches([' ', '\t']);
+    let indented = unindented.len() < line.len();
+    if let Some(header) = unindented.strip_prefix('#') {
+        let json = header.trim();
+        if json.starts_with('{') {
+            visitor.visit_r8_header(json);
+        } else if !indented {
+            let (key, value) = parse_header(header);
+            visitor.visit_header(key, value);
+        }
+    } else if !indented {
+        if let Ok((original, obfuscated)) = parse_class(line) {
+            visitor.visit_class(original, obfuscated);
+        }
+    } else {
+        match parse_member(line) {
+            Ok(Member::Field(field)) => visitor.visit_field(&field),
+            Ok(Member::Method(method)) => visitor.visit_method(&method),
+            Err(_) => {}
+        }
+    }
+}
+
+/// A push parser, which is fed a mapping file in chunks of arbitrary size.
+///
+/// This allows parsing a mapping directly from a network stream or an async
+/// reader, without buffering the whole file. Records may be split across
+/// chunks, only the incomplete line at the end of a chunk is kept around
+/// until the next one arrives.
+///
+/// As records borrow from the chunks, which are short-lived, the
+/// [`MappingVisitor`] needs to accept records of any lifetime.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{MappingVisitor, PushParser};
+///
+/// #[derive(Default)]
+/// struct ClassNames(Vec<String>);
+///
+/// impl MappingVisitor<'_> for ClassNames {
+///     fn visit_class(&mut self, original: &str, _obfuscated: &str) {
+///         self.0.push(original.to_owned());
+///     }
+/// }
+///
+/// let mut parser = PushParser::new();
+/// let mut names = ClassNames::default();
+/// parser.feed(b"a.A -> a:\n    void method() -> a\nb.", &mut names);
+/// parser.feed(b"B -> b:", &mut names);
+/// parser.finish(&mut names);
+///
+/// assert_eq!(names.0, vec!["a.A", "b.B"]);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PushParser {
+    /// The incomplete last line of the previous chunks.
+    partial: Vec<u8>,
+}
+
+impl PushParser {
+    /// Creates a new push parser.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses the next `chunk` of the mapping, visiting every complete record.
+    pub fn feed<V>(&mut self, chunk: &[u8], visitor: &mut V)
+    where
+        V: for<'a> MappingVisitor<'a> + ?Sized,
+    {
+        let mut rest = chunk;
+        if !self.partial.is_empty() {
+            match find_line_terminator(rest) {
+                Some(pos) => {
+                    self.partial.extend_from_slice(&rest[..pos]);
+                    visit_line(&self.partial, visitor);
+                    self.partial.clear();
+                    rest = &rest[pos + 1..];
+                }
+                None => {
+                    self.partial.extend_from_slice(rest);
+                    return;
+                }
+            }
+        }
+        // a `\r\n` split across chunks merely yields an empty line.
+        while let Some(pos) = find_line_terminator(rest) {
+            visit_line(&rest[..pos], visitor);
+            rest = &rest[pos + 1..];
+        }
+        self.partial.extend_from_slice(rest);
+    }
+
+    /// Visits the last record, which is not followed by a line terminator.
+    pub fn finish<V>(self, visitor: &mut V)
+    where
+        V: for<'a> MappingVisitor<'a> + ?Sized,
+    {
+        visit_line(&self.partial, visitor);
+    }
+}
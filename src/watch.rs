@@ -0,0 +1,346 @@
+//! Watching Proguard Mapping Files for changes.
+
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::classes::ClassMapping;
+use crate::mapper::ProguardMapper;
+use crate::mapping::ProguardMapping;
+
+/// The outcome of [`MappingWatcher::refresh`] and [`MappingWatcher::update`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Refresh {
+    /// The mapping did not change.
+    Unchanged,
+    /// Only the classes in the given byte range of the new mapping were
+    /// indexed again, all other classes were kept.
+    Incremental {
+        /// The byte range of the new mapping which was indexed again.
+        reindexed: Range<usize>,
+    },
+    /// The whole mapping was indexed again.
+    Full,
+}
+
+/// A class of the index, along with the byte range of its block.
+#[derive(Clone, Debug)]
+struct IndexedClass {
+    name: String,
+    byte_range: Range<usize>,
+}
+
+/// Keeps a Proguard Mapping file, and an index of its classes, up to date
+/// with the file on disk.
+///
+/// This is intended for long-running local tooling, such as a retrace
+/// process which keeps running while an app is rebuilt. Call
+/// [`refresh`](Self::refresh) to check the file for changes. When only some
+/// classes changed, only the changed byte region of the file is indexed
+/// again, and the other classes are kept.
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{MappingWatcher, Refresh};
+///
+/// let mut watcher =
+///     MappingWatcher::from_bytes(b"a.A -> a:\nb.B -> b:\nc.C -> c:\nd.D -> d:\n".to_vec());
+/// assert_eq!(watcher.mapper().remap_class("c"), Some("c.C"));
+///
+/// // the class preceding a change is indexed again, as it may have grown.
+/// let refresh = watcher.update(b"a.A -> a:\nb.B -> b:\nc.Renamed -> c:\nd.D -> d:\n".to_vec());
+/// assert_eq!(refresh, Refresh::Incremental { reindexed: 10..36 });
+/// assert_eq!(watcher.mapper().remap_class("c"), Some("c.Renamed"));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MappingWatcher {
+    path: Option<PathBuf>,
+    /// The modification time and length of the file when it was last read.
+    stamp: Option<(SystemTime, u64)>,
+    /// When the file was last read.
+    read_at: Option<SystemTime>,
+    /// The SHA-1 checksum of `source`.
+    hash: Option<[u8; 20]>,
+    source: Vec<u8>,
+    /// All classes of the mapping, in file order.
+    classes: Vec<IndexedClass>,
+}
+
+impl MappingWatcher {
+    /// Reads and indexes the mapping file at `path`.
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        let stamp = stamp(&path)?;
+        let read_at = SystemTime::now();
+        let mut watcher = Self::from_bytes(fs::read(&path)?);
+        watcher.path = Some(path);
+        watcher.stamp = Some(stamp);
+        watcher.read_at = Some(read_at);
+        Ok(watcher)
+    }
+
+    /// Indexes the mapping in `source`, which is not backed by a file.
+    ///
+    /// Changes are applied using [`update`](Self::update), for example when
+    /// they are received from an external file watcher.
+    pub fn from_bytes(source: Vec<u8>) -> Self {
+        let mut watcher = Self::default();
+        watcher.update(source);
+        watcher
+    }
+
+    /// The path of the watched file, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Checks whether the watched file changed, and updates the mapping.
+    ///
+    /// The file is read again if its modification time or length changed, or
+    /// if it was modified shortly before it was last read, as a later write
+    /// may not have changed the modification time on file systems with a
+    /// coarse timestamp granularity. It is then compared to the previous
+    /// mapping by its checksum.
+    pub fn refresh(&mut self) -> io::Result<Refresh> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(Refresh::Unchanged),
+        };
+        let stamp = stamp(path)?;
+        let racy = self
+            .read_at
+            .is_some_and(|read_at| stamp.0 + RACY_WINDOW >= read_at);
+        if self.stamp == Some(stamp) && !racy {
+            return Ok(Refresh::Unchanged);
+        }
+        let read_at = SystemTime::now();
+        let source = fs::read(path)?;
+        self.stamp = Some(stamp);
+        self.read_at = Some(read_at);
+        Ok(self.update(source))
+    }
+
+    /// Replaces the mapping with `source`, indexing only the changed region.
+    ///
+    /// Classes which lie entirely before or after the bytes which differ from
+    /// the previous mapping are kept. If no class could be kept, the whole
+    /// mapping is indexed again.
+    pub fn update(&mut self, source: Vec<u8>) -> Refresh {
+        let hash = sha1_smol::Sha1::from(&source).digest().bytes();
+        if self.hash == Some(hash) {
+            return Refresh::Unchanged;
+        }
+        self.hash = Some(hash);
+        let old = std::mem::replace(&mut self.source, source);
+        let new = &self.source;
+
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_end = old.len() - suffix;
+        // the line of the first change may have been a class line.
+        let line_start = old[..prefix]
+            .iter()
+            .rposition(|&byte| byte == b'\n' || byte == b'\r')
+            .map_or(0, |index| index + 1);
+
+        // classes ending on an earlier line, and starting after the last
+        // change, are unaffected.
+        let kept_before = self
+            .classes
+            .iter()
+            .take_while(|class| class.byte_range.end < line_start)
+            .count();
+        let kept_after = self.classes[kept_before..]
+            .iter()
+            .position(|class| class.byte_range.start > old_end)
+            .map_or(self.classes.len(), |index| kept_before + index);
+
+        let start = match kept_before {
+            0 => 0,
+            count => self.classes[count - 1].byte_range.end,
+        };
+        let shift = |offset: usize| offset + new.len() - old.len();
+        let end = match self.classes.get(kept_after) {
+            Some(class) => shift(class.byte_range.start),
+            None => new.len(),
+        };
+
+        let mapping = ProguardMapping::new(&new[start..end]);
+        let reindexed: Vec<_> = mapping
+            .class_handles()
+            .map(|handle| IndexedClass {
+                name: handle.name.to_owned(),
+                byte_range: handle.byte_range.start + start..handle.byte_range.end + start,
+            })
+            .collect();
+        let full = kept_before == 0 && kept_after == self.classes.len();
+        let mut after = self.classes.split_off(kept_after);
+        for class in &mut after {
+            class.byte_range = shift(class.byte_range.start)..shift(class.byte_range.end);
+        }
+        self.classes.truncate(kept_before);
+        self.classes.extend(reindexed);
+        self.classes.extend(after);
+
+        if full {
+            Refresh::Full
+        } else {
+            Refresh::Incremental {
+                reindexed: start..end,
+            }
+        }
+    }
+
+    /// The current contents of the mapping.
+    pub fn mapping(&self) -> ProguardMapping<'_> {
+        ProguardMapping::new(&self.source)
+    }
+
+    /// Returns the class with the obfuscated name `name`.
+    ///
+    /// If several classes have the same name, the last one is returned.
+    pub fn class(&self, name: &str) -> Option<ClassMapping<'_>> {
+        let class = self.classes.iter().rev().find(|class| class.name == name)?;
+        self.mapping().class_at(class.byte_range.clone())
+    }
+
+    /// Creates a [`ProguardMapper`] for the current mapping.
+    ///
+    /// The mapper is created from the index, without scanning the mapping
+    /// for classes again. This only saves the scan for class lines: The
+    /// members of each class are indexed again by every new mapper, the first
+    /// time the class is used. The mapper borrows the mapping, so it can not
+    /// outlive the next update, but it should be kept and reused until then
+    /// rather than created for every lookup.
+    pub fn mapper(&self) -> ProguardMapper<'_> {
+        let mapping = self.mapping();
+        ProguardMapper::from_classes(
+            self.classes
+                .iter()
+                .filter_map(|class| mapping.class_at(class.byte_range.clone())),
+        )
+    }
+}
+
+/// How long before it was read a file must have been modified last, so that
+/// its modification time reliably tells whether it changed since.
+const RACY_WINDOW: Duration = Duration::from_secs(2);
+
+/// The modification time and length of the file at `path`.
+fn stamp(path: &Path) -> io::Result<(SystemTime, u64)> {
+    let metadata = fs::metadata(path)?;
+    Ok((metadata.modified()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(watcher: &MappingWatcher) -> Vec<(String, &str)> {
+        watcher
+            .classes
+            .iter()
+            .map(|class| {
+                let original = watcher.class(&class.name).unwrap().original();
+                (class.name.clone(), original)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn update() {
+        let mut watcher = MappingWatcher::from_bytes(
+            b"# compiler: R8\na.A -> a:\n    void a() -> a\nb.B -> b:\n    void b() -> a\nc.C -> c:\n"
+                .to_vec(),
+        );
+        assert_eq!(watcher.classes.len(), 3);
+
+        // a member line is added to the end of the block of `a`.
+        let refresh = watcher.update(
+            b"# compiler: R8\na.A -> a:\n    void a() -> a\n    void x() -> b\nb.B -> b:\n    void b() -> a\nc.C -> c:\n"
+                .to_vec(),
+        );
+        assert_eq!(refresh, Refresh::Incremental { reindexed: 0..89 });
+        assert_eq!(watcher.class("a").unwrap().methods().count(), 2);
+        assert_eq!(watcher.class("b").unwrap().methods().count(), 1);
+
+        // the class line of `b` is changed into a member of `a`.
+        let refresh = watcher.update(
+            b"# compiler: R8\na.A -> a:\n    void a() -> a\n    void x() -> b\n    void b() -> a\nc.C -> c:\n"
+                .to_vec(),
+        );
+        assert_eq!(refresh, Refresh::Incremental { reindexed: 0..79 });
+        assert_eq!(
+            names(&watcher),
+            vec![("a".to_owned(), "a.A"), ("c".to_owned(), "c.C")]
+        );
+        assert_eq!(watcher.class("a").unwrap().methods().count(), 3);
+
+        // a class is appended.
+        let refresh = watcher.update(
+            b"# compiler: R8\na.A -> a:\n    void a() -> a\n    void x() -> b\n    void b() -> a\nc.C -> c:\nd.D -> d:\n"
+                .to_vec(),
+        );
+        assert_eq!(refresh, Refresh::Incremental { reindexed: 79..99 });
+        assert_eq!(watcher.classes.len(), 3);
+        assert_eq!(watcher.class("d").unwrap().original(), "d.D");
+
+        let source = watcher.mapping().source().to_vec();
+        assert_eq!(watcher.update(source), Refresh::Unchanged);
+    }
+
+    #[test]
+    fn matches_full_index() {
+        let versions: [&[u8]; 5] = [
+            b"a.A -> a:\n    1:1:void a():1 -> a\nb.B -> b:\n    1:1:void b():2 -> a\n",
+            b"a.A -> a:\n    1:1:void a():1 -> a\nb.B -> b:\n    1:1:void b():3 -> a\n",
+            b"a.A -> a:\n    1:1:void a():1 -> a\nb.B -> c:\n    1:1:void b():3 -> a\nb.B -> b:\n",
+            b"b.B -> b:\n",
+            b"",
+        ];
+        let mut watcher = MappingWatcher::default();
+        for version in versions.iter().chain(versions.iter().rev()) {
+            watcher.update(version.to_vec());
+            let expected: Vec<_> = ProguardMapping::new(version)
+                .class_handles()
+                .map(|handle| (handle.name.to_owned(), handle.byte_range))
+                .collect();
+            let actual: Vec<_> = watcher
+                .classes
+                .iter()
+                .map(|class| (class.name.clone(), class.byte_range.clone()))
+                .collect();
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn refresh() {
+        let path = std::env::temp_dir().join(format!("proguard-watch-{}.txt", std::process::id()));
+        fs::write(&path, b"a.A -> a:\n").unwrap();
+        let mut watcher = MappingWatcher::open(&path).unwrap();
+        assert_eq!(watcher.path(), Some(path.as_path()));
+        assert_eq!(watcher.refresh().unwrap(), Refresh::Unchanged);
+
+        fs::write(&path, b"a.A -> a:\nb.B -> b:\n").unwrap();
+        assert_eq!(watcher.refresh().unwrap(), Refresh::Full);
+        assert_eq!(watcher.mapper().remap_class("b"), Some("b.B"));
+
+        // a write of the same length right after reading the file may not
+        // change its modification time.
+        fs::write(&path, b"a.A -> a:\nc.C -> c:\n").unwrap();
+        assert_ne!(watcher.refresh().unwrap(), Refresh::Unchanged);
+        assert_eq!(watcher.mapper().remap_class("c"), Some("c.C"));
+        fs::remove_file(&path).unwrap();
+    }
+}
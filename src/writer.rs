@@ -0,0 +1,296 @@
+//! Writing of Proguard Mapping Files.
+
+use std::io::{self, Write};
+
+use sha2::{Digest, Sha256};
+#[cfg(feature = "uuid")]
+use uuid_::Uuid;
+
+use crate::mapping::{ProguardMapping, ProguardRecord};
+
+/// The metadata of the conventional header block of an R8 mapping file.
+///
+/// See [`MappingWriter::write_mapping_with_headers`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StandardHeaders {
+    /// The compiler that created the mapping, written as `# compiler`.
+    pub compiler: Option<String>,
+    /// The version of the compiler, written as `# compiler_version`.
+    pub compiler_version: Option<String>,
+    /// The minimum API level of the app, written as `# min_api`.
+    pub min_api: Option<u32>,
+}
+
+/// A writer for Proguard Mapping files.
+///
+/// This writes headers and records using the syntax of a mapping file, which
+/// can be read back using [`ProguardMapping`]. Build pipelines can use it to
+/// stamp provenance, such as the `application_id`, `version_code` or a
+/// `build_fingerprint`, into a mapping, which is then available via
+/// [`ProguardMapping::headers`].
+///
+/// # Examples
+///
+/// ```
+/// use proguard::{MappingWriter, ProguardMapping};
+///
+/// let original = ProguardMapping::new(b"a.A -> a:\n    void method() -> b\n");
+///
+/// let mut writer = MappingWriter::new(Vec::new());
+/// writer.write_header("application_id", Some("com.example")).unwrap();
+/// writer.write_header("version_code", Some("42")).unwrap();
+/// writer.write_mapping(&original).unwrap();
+/// let output = writer.into_inner();
+///
+/// let mapping = ProguardMapping::new(&output);
+/// assert_eq!(mapping.headers().application_id(), Some("com.example"));
+/// assert_eq!(mapping.headers().version_code(), Some(42));
+/// assert_eq!(mapping.class_names().next(), Some(("a.A", "a")));
+/// ```
+#[derive(Debug)]
+pub struct MappingWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> MappingWriter<W> {
+    /// Creates a new writer.
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes a `# key: value` header, or `# key` if there is no value.
+    ///
+    /// Headers are only found by [`ProguardMapping::headers`] if they precede
+    /// the first class. Fails with [`io::ErrorKind::InvalidInput`] if the
+    /// header can not be read back unchanged, for example because `key`
+    /// contains a `:` or either contains a line break.
+    pub fn write_header(&mut self, key: &str, value: Option<&str>) -> io::Result<()> {
+        let is_line_break = |c| c == '\n' || c == '\r';
+        let valid_key = !key.trim().is_empty()
+            && key.trim() == key
+            && !key.starts_with('{')
+            && !key.contains(|c| c == ':' || is_line_break(c));
+        let valid_value = match value {
+            Some(value) => value.trim() == value && !value.contains(is_line_break),
+            None => true,
+        };
+        if !valid_key || !valid_value {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "header can not be represented in a mapping file",
+            ));
+        }
+
+        let record = ProguardRecord::Header { key, value };
+        writeln!(self.writer, "{}", record)
+    }
+
+    /// Writes a `# uuid: <uuid>` header.
+    ///
+    /// The header is read back by [`ProguardMapping::header_uuid`], and then
+    /// identifies the mapping instead of its checksum-based
+    /// [`ProguardMapping::uuid`]. Just like other headers, it must precede the
+    /// first class.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{MappingWriter, ProguardMapping};
+    ///
+    /// let body = ProguardMapping::new(b"a.A -> a:\n");
+    /// let uuid = body.uuid();
+    ///
+    /// let mut writer = MappingWriter::new(Vec::new());
+    /// writer.write_uuid_header(uuid).unwrap();
+    /// writer.write_mapping(&body).unwrap();
+    /// let output = writer.into_inner();
+    ///
+    /// let mapping = ProguardMapping::new(&output);
+    /// assert_eq!(mapping.header_uuid(), Some(uuid));
+    /// assert_ne!(mapping.uuid(), uuid);
+    /// ```
+    #[cfg(feature = "uuid")]
+    pub fn write_uuid_header(&mut self, uuid: Uuid) -> io::Result<()> {
+        self.write_header("uuid", Some(&uuid.to_string()))
+    }
+
+    /// Writes a single record.
+    pub fn write_record(&mut self, record: &ProguardRecord<'_>) -> io::Result<()> {
+        writeln!(self.writer, "{}", record)
+    }
+
+    /// Writes the complete contents of `mapping` verbatim.
+    ///
+    /// A line terminator is added if the mapping does not end in one.
+    pub fn write_mapping(&mut self, mapping: &ProguardMapping<'_>) -> io::Result<()> {
+        let source = mapping.source();
+        self.writer.write_all(source)?;
+        if !source.is_empty() && !source.ends_with(b"\n") && !source.ends_with(b"\r") {
+            self.writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Writes the conventional R8 header block, followed by `mapping`.
+    ///
+    /// The `# compiler`, `# compiler_version` and `# min_api` headers are
+    /// written if they are set in `headers`. They are followed by the
+    /// `# pg_map_id` header, which is the first 7 digits of a `SHA-256`
+    /// checksum of `mapping`, and the `# pg_map_hash` header with the full
+    /// checksum, just like R8 writes them.
+    /// As the checksum covers all of `mapping`, it should not contain any
+    /// headers itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use proguard::{MappingWriter, ProguardMapping, StandardHeaders};
+    ///
+    /// let headers = StandardHeaders {
+    ///     compiler: Some("R8".into()),
+    ///     compiler_version: Some("8.3.37".into()),
+    ///     min_api: Some(24),
+    /// };
+    ///
+    /// let mut writer = MappingWriter::new(Vec::new());
+    /// let body = ProguardMapping::new(b"a.A -> a:\n");
+    /// writer.write_mapping_with_headers(&headers, &body).unwrap();
+    /// let output = writer.into_inner();
+    ///
+    /// let mapping = ProguardMapping::new(&output);
+    /// let summary = mapping.summary();
+    /// assert_eq!(summary.compiler_version(), Some("8.3.37"));
+    /// assert_eq!(summary.min_api(), Some(24));
+    /// assert_eq!(mapping.headers().get("pg_map_id"), Some("f5dab09"));
+    /// ```
+    pub fn write_mapping_with_headers(
+        &mut self,
+        headers: &StandardHeaders,
+        mapping: &ProguardMapping<'_>,
+    ) -> io::Result<()> {
+        if let Some(compiler) = &headers.compiler {
+            self.write_header("compiler", Some(compiler))?;
+        }
+        if let Some(compiler_version) = &headers.compiler_version {
+            self.write_header("compiler_version", Some(compiler_version))?;
+        }
+        if let Some(min_api) = headers.min_api {
+            self.write_header("min_api", Some(&min_api.to_string()))?;
+        }
+        let hash: String = Sha256::digest(mapping.source())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        self.write_header("pg_map_id", Some(&hash[..7]))?;
+        self.write_header("pg_map_hash", Some(&format!("SHA-256 {}", hash)))?;
+        self.write_mapping(mapping)
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> W {
+        let _ = self.writer.flush();
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_headers() {
+        let mut writer = MappingWriter::new(Vec::new());
+        writer.write_header("compiler", Some("R8")).unwrap();
+        writer
+            .write_header("build_fingerprint", Some("google/sdk:14/UE1A:user"))
+            .unwrap();
+        writer.write_header("generated", None).unwrap();
+        writer
+            .write_record(&ProguardRecord::Class {
+                original: "a.A",
+                obfuscated: "a",
+            })
+            .unwrap();
+        let output = writer.into_inner();
+        assert_eq!(
+            output,
+            b"# compiler: R8\n# build_fingerprint: google/sdk:14/UE1A:user\n# generated\na.A -> a:\n"
+        );
+
+        let mapping = ProguardMapping::new(&output);
+        let headers = mapping.headers();
+        assert_eq!(headers.build_fingerprint(), Some("google/sdk:14/UE1A:user"));
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            vec![
+                ("compiler", Some("R8")),
+                ("build_fingerprint", Some("google/sdk:14/UE1A:user")),
+                ("generated", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_headers() {
+        let mut writer = MappingWriter::new(Vec::new());
+        for (key, value) in [
+            ("", None),
+            ("a:b", Some("c")),
+            ("a\nb -> c:", None),
+            ("{\"id\"", Some("c")),
+            ("key", Some("multi\nline")),
+            (" key", Some("value")),
+            ("key", Some("value ")),
+        ] {
+            let err = writer.write_header(key, value).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
+        assert!(writer.into_inner().is_empty());
+    }
+
+    #[test]
+    fn write_standard_headers() {
+        let body = ProguardMapping::new(b"a.A -> a:\n    void method() -> b\n");
+        let hash = "63e92e9653cd1b7b1c437cce44b565c3096cacc90f169265a41f3fc4017ff702";
+
+        let mut writer = MappingWriter::new(Vec::new());
+        let headers = StandardHeaders {
+            compiler: Some("R8".into()),
+            compiler_version: None,
+            min_api: Some(21),
+        };
+        writer.write_mapping_with_headers(&headers, &body).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(
+            output,
+            format!(
+                "# compiler: R8\n# min_api: 21\n# pg_map_id: {}\n# pg_map_hash: SHA-256 {}\n{}",
+                &hash[..7],
+                hash,
+                "a.A -> a:\n    void method() -> b\n"
+            )
+        );
+
+        // the header block does not depend on the metadata.
+        let mut writer = MappingWriter::new(Vec::new());
+        writer
+            .write_mapping_with_headers(&StandardHeaders::default(), &body)
+            .unwrap();
+        let output = writer.into_inner();
+        assert!(output.starts_with(format!("# pg_map_id: {}\n", &hash[..7]).as_bytes()));
+        assert!(ProguardMapping::new(&output).is_valid());
+    }
+
+    #[test]
+    fn write_mapping() {
+        let mut writer = MappingWriter::new(Vec::new());
+        writer
+            .write_mapping(&ProguardMapping::new(b"a -> b:"))
+            .unwrap();
+        writer
+            .write_mapping(&ProguardMapping::new(b"c -> d:\n"))
+            .unwrap();
+        writer.write_mapping(&ProguardMapping::new(b"")).unwrap();
+        assert_eq!(writer.into_inner(), b"a -> b:\nc -> d:\n");
+    }
+}
@@ -1,6 +1,10 @@
 use lazy_static::lazy_static;
 
-use proguard::{ProguardMapper, ProguardMapping, StackFrame};
+use proguard::{
+    FieldMapping, LineMapping, LintKind, ManifestMismatch, MappingManifest, MappingVisitor,
+    MethodMapping, ProguardMapper, ProguardMapping, ProguardRecord, PushParser, StackFrame,
+    SyntaxErrorKind,
+};
 
 static MAPPING: &[u8] = include_bytes!("res/mapping.txt");
 lazy_static! {
@@ -12,6 +16,10 @@ lazy_static! {
             vec![byte]
         })
         .collect();
+    static ref MAPPING_MAC: Vec<u8> = MAPPING
+        .iter()
+        .map(|&byte| if byte == b'\n' { b'\r' } else { byte })
+        .collect();
 }
 
 #[test]
@@ -44,6 +52,21 @@ fn test_basic_win() {
     );
 }
 
+#[test]
+fn test_basic_mac() {
+    let mapping = ProguardMapping::new(&MAPPING_MAC[..]);
+    assert!(mapping.is_valid());
+    assert!(mapping.has_line_info());
+
+    let mapper = ProguardMapper::new(mapping);
+
+    let class = mapper.remap_class("android.support.constraint.ConstraintLayout$a");
+    assert_eq!(
+        class,
+        Some("android.support.constraint.ConstraintLayout$LayoutParams")
+    );
+}
+
 #[test]
 fn test_method_matches() {
     let mapper = ProguardMapper::new(ProguardMapping::new(MAPPING));
@@ -106,6 +129,206 @@ fn test_method_matches_win() {
     assert_eq!(mapped.next(), None);
 }
 
+#[test]
+fn test_method_matches_mac() {
+    let mapping = ProguardMapping::new(&MAPPING_MAC[..]);
+    for mapper in [
+        ProguardMapper::new(mapping.clone()),
+        ProguardMapper::new_eager(mapping),
+    ] {
+        let mut mapped =
+            mapper.remap_frame(&StackFrame::new("android.support.constraint.a.a", "a", 320));
+
+        assert_eq!(
+            mapped.next().unwrap(),
+            StackFrame::new(
+                "android.support.constraint.solver.ArrayLinkedVariables",
+                "remove",
+                320
+            )
+        );
+        assert_eq!(mapped.next(), None);
+    }
+}
+
+#[test]
+fn test_line_endings() {
+    let records: Vec<_> = ProguardMapping::new(MAPPING).iter().collect();
+    for source in [&MAPPING_WIN[..], &MAPPING_MAC[..]] {
+        let mapping = ProguardMapping::new(source);
+        assert_eq!(mapping.iter().collect::<Vec<_>>(), records);
+        assert_eq!(mapping.classes().count(), 716);
+        assert_eq!(mapping.summary().class_count(), 716);
+        assert_eq!(mapping.class_count(), 716);
+    }
+
+    // line numbers of errors are the same for every convention.
+    for terminator in ["\n", "\r\n", "\r"] {
+        let source = ["a.A -> a:", "", "    void m() -> b", "    invalid", ""].join(terminator);
+        let error = ProguardMapping::new(source.as_bytes())
+            .parse_strict()
+            .unwrap_err();
+        assert_eq!(error.line_number(), 4);
+    }
+}
+
+#[test]
+fn test_into_iterator() {
+    let mapping = ProguardMapping::new(MAPPING);
+    let mut count = 0;
+    for record in &mapping {
+        assert!(record.is_ok());
+        count += 1;
+    }
+    assert_eq!(count, mapping.iter().count());
+    assert!(mapping.records().eq(mapping.iter()));
+
+    // every iterator keeps returning `None` once it is exhausted.
+    let mut records = mapping.records();
+    records.by_ref().for_each(drop);
+    assert!(records.next().is_none());
+    let mut classes = mapping.classes();
+    classes.by_ref().for_each(drop);
+    assert!(classes.next().is_none());
+}
+
+#[test]
+fn test_lookup() {
+    let mapper = ProguardMapper::new(ProguardMapping::new(MAPPING));
+    let frame = StackFrame::new("android.support.constraint.a.a", "a", 320);
+    let expected: Vec<_> = mapper.remap_frame(&frame).collect();
+    assert!(!expected.is_empty());
+
+    assert!(mapper.lookup(&frame).eq(expected.clone()));
+    assert!(mapper.lookup(frame.clone()).eq(expected.clone()));
+    assert!(mapper
+        .lookup(("android.support.constraint.a.a", "a", Some(320)))
+        .eq(expected));
+
+    // keys without line are looked up like frames with line `0`.
+    let frame = StackFrame::new("android.support.constraint.a.a", "a", 0);
+    let expected: Vec<_> = mapper.remap_frame(&frame).collect();
+    assert!(mapper
+        .lookup(("android.support.constraint.a.a", "a", None))
+        .eq(expected.clone()));
+    assert!(mapper
+        .lookup(("android.support.constraint.a.a", "a"))
+        .eq(expected));
+}
+
+#[test]
+fn test_class_handles() {
+    for source in [MAPPING, &MAPPING_WIN[..], &MAPPING_MAC[..]] {
+        let mapping = ProguardMapping::new(source);
+        let handles: Vec<_> = mapping.class_handles().collect();
+        assert_eq!(handles.len(), 716);
+
+        // re-opening the mapping yields the very same classes.
+        let reopened = ProguardMapping::new(source);
+        for (handle, class) in handles.iter().zip(mapping.classes()) {
+            assert_eq!(handle.name, class.obfuscated());
+            let found = reopened.class_at(handle.byte_range.clone()).unwrap();
+            assert_eq!(found, class);
+            assert_eq!(
+                found.members().collect::<Vec<_>>(),
+                class.members().collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(handles.last().unwrap().byte_range.end, source.len());
+    }
+
+    let mapping = ProguardMapping::new(MAPPING);
+    assert!(mapping.class_at(0..MAPPING.len() + 1).is_none());
+    // ranges need to start at a class line.
+    let mapping = ProguardMapping::new(b"a.A -> a:\n    void m() -> b");
+    assert!(mapping.class_at(0..27).is_some());
+    assert!(mapping.class_at(10..27).is_none());
+}
+
+#[test]
+fn test_extract_class() {
+    let mapping = ProguardMapping::new(MAPPING);
+    let mapper = ProguardMapper::new(mapping.clone());
+    let frame = StackFrame::new("android.support.constraint.a.a", "a", 320);
+    assert!(mapper.remap_frame(&frame).next().is_some());
+
+    let block = mapping.extract_class(frame.class()).unwrap();
+    let extracted = ProguardMapping::new(block);
+    assert_eq!(extracted.classes().count(), 1);
+    assert!(ProguardMapper::new(extracted)
+        .remap_frame(&frame)
+        .eq(mapper.remap_frame(&frame)));
+
+    assert_eq!(mapping.extract_class("does.not.exist"), None);
+}
+
+#[test]
+fn test_concatenated_segments() {
+    let source = b"\
+# compiler: R8
+# pg_map_id: 1
+com.example.Foo -> a:
+# {\"id\":\"sourceFile\",\"fileName\":\"Foo.kt\"}
+    1:1:void foo():10 -> a
+
+# {\"id\":\"com.android.tools.r8.mapping\",\"version\":\"2.0\"}
+# pg_map_id: 2
+com.vendor.Bar -> b:
+    1:1:void bar():20 -> a
+";
+    let mapping = ProguardMapping::new(source);
+    let segments: Vec<_> = mapping.segments().collect();
+    assert_eq!(segments.len(), 2);
+    assert_eq!(segments[0].headers().get("pg_map_id"), Some("1"));
+    assert_eq!(segments[1].headers().get("pg_map_id"), Some("2"));
+    assert_eq!(segments[0].classes().count(), 1);
+    assert_eq!(
+        segments[1].class_names().next(),
+        Some(("com.vendor.Bar", "b"))
+    );
+    assert_eq!(ProguardMapping::new(b"").segments().count(), 0);
+
+    // the headers of the second segment are not part of the last class.
+    assert_eq!(
+        mapping.extract_class("a"),
+        Some(&b"com.example.Foo -> a:\n# {\"id\":\"sourceFile\",\"fileName\":\"Foo.kt\"}\n    1:1:void foo():10 -> a\n\n"[..])
+    );
+
+    for mapper in [
+        ProguardMapper::new(mapping.clone()),
+        ProguardMapper::new_eager(mapping.clone()),
+    ] {
+        for (class, expected) in [
+            ("a", ("com.example.Foo", "foo", 10)),
+            ("b", ("com.vendor.Bar", "bar", 20)),
+        ] {
+            let remapped: Vec<_> = mapper
+                .remap_frame(&StackFrame::new(class, "a", 1))
+                .collect();
+            assert_eq!(
+                remapped,
+                vec![StackFrame::new(expected.0, expected.1, expected.2)]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_java_types() {
+    let mapping = ProguardMapping::new(MAPPING);
+    for class in mapping.classes() {
+        for field in class.fields() {
+            assert_eq!(field.java_type().unwrap().to_string(), field.ty);
+        }
+        for method in class.methods() {
+            assert_eq!(method.return_type().unwrap().to_string(), method.ty);
+            let arguments = method.argument_types().unwrap();
+            let arguments: Vec<_> = arguments.iter().map(|ty| ty.to_string()).collect();
+            assert_eq!(arguments.join(","), method.arguments);
+        }
+    }
+}
+
 #[test]
 fn test_inlines() {
     let mapping = ProguardMapping::new(include_bytes!("res/mapping-inlines.txt"));
@@ -175,3 +398,635 @@ fn test_uuid_win() {
         "71d468f2-0dc4-5017-9f12-1a81081913ef".parse().unwrap()
     );
 }
+
+#[test]
+fn test_parse_strict() {
+    let records = ProguardMapping::new(MAPPING).parse_strict().unwrap();
+    assert_eq!(records.len(), ProguardMapping::new(MAPPING).iter().count());
+
+    let records = ProguardMapping::new(&MAPPING_WIN[..])
+        .parse_strict()
+        .unwrap();
+    assert_eq!(records.len(), ProguardMapping::new(MAPPING).iter().count());
+}
+
+#[test]
+fn test_parse_strict_error() {
+    let mapping = b"# compiler: R8\r\n\r\na -> b:\r\n    void method() -> b\r\n    int field b\r\n";
+    let error = ProguardMapping::new(mapping).parse_strict().unwrap_err();
+    assert_eq!(error.line_number(), 5);
+    assert_eq!(error.column(), 15);
+    assert_eq!(error.kind(), SyntaxErrorKind::MissingSeparator);
+    assert_eq!(error.line(), b"    int field b");
+
+    let error = ProguardMapping::new(b"a -> b").parse_strict().unwrap_err();
+    assert_eq!((error.line_number(), error.column()), (1, 7));
+    assert_eq!(error.kind(), SyntaxErrorKind::MissingClassTerminator);
+
+    let error = ProguardMapping::new(b"a -> b:\n    void method(int -> b")
+        .parse_strict()
+        .unwrap_err();
+    assert_eq!((error.line_number(), error.column()), (2, 16));
+    assert_eq!(error.kind(), SyntaxErrorKind::UnclosedArguments);
+
+    let error = ProguardMapping::new(b"a -> b:\n    1:2:void method():x -> b")
+        .parse_strict()
+        .unwrap_err();
+    assert_eq!((error.line_number(), error.column()), (2, 23));
+    assert_eq!(error.kind(), SyntaxErrorKind::InvalidLineNumber);
+
+    let error = ProguardMapping::new(b"a -> b:\n    int f\xff -> b")
+        .parse_strict()
+        .unwrap_err();
+    assert_eq!((error.line_number(), error.column()), (2, 10));
+    assert_eq!(error.kind(), SyntaxErrorKind::InvalidUtf8);
+}
+
+#[test]
+fn test_parse_all() {
+    let (records, errors) = ProguardMapping::new(MAPPING).parse_all();
+    assert!(errors.is_empty());
+    assert_eq!(
+        records,
+        ProguardMapping::new(MAPPING).parse_strict().unwrap()
+    );
+
+    let mapping =
+        b"a -> b:\n    int f\xff -> b\n    void method() -> b\n\n    int field b\nc -> d:";
+    let (records, errors) = ProguardMapping::new(mapping).parse_all();
+    assert_eq!(records.len(), 3);
+    assert_eq!(
+        errors
+            .iter()
+            .map(|e| (e.line_number(), e.column(), e.kind()))
+            .collect::<Vec<_>>(),
+        vec![
+            (2, 10, SyntaxErrorKind::InvalidUtf8),
+            (5, 15, SyntaxErrorKind::MissingSeparator),
+        ]
+    );
+}
+
+#[test]
+fn test_truncated() {
+    let truncated = &MAPPING[..MAPPING.len() - 10];
+    let mapping = ProguardMapping::new(truncated);
+    assert!(!mapping.is_valid());
+    assert_eq!(
+        mapping.lint().last().map(|warning| warning.kind().clone()),
+        Some(LintKind::Truncated)
+    );
+
+    let mapping = ProguardMapping::new(b"a -> b:\n    1:1:void method() -> ");
+    assert!(!mapping.is_valid());
+
+    let mapping = ProguardMapping::new(b"a -> b:\n    1:1:void method() -> a\n");
+    assert!(mapping.is_valid());
+}
+
+#[test]
+fn test_verify_manifest() {
+    let mapping = ProguardMapping::new(MAPPING);
+    let manifest = MappingManifest {
+        uuid: Some("5CD8E873-1127-5276-81B7-8FF25043ECFD".into()),
+        size: Some(MAPPING.len() as u64),
+        sha1: Some("2CBAB48F9C8FD69B4B882B3CE4821C72BE4E462F".into()),
+    };
+    assert_eq!(mapping.verify_manifest(&manifest), vec![]);
+
+    let mapping = ProguardMapping::new(&MAPPING_WIN[..]);
+    let mismatches = mapping.verify_manifest(&manifest);
+    assert_eq!(
+        mismatches[0],
+        ManifestMismatch::Uuid {
+            expected: "5CD8E873-1127-5276-81B7-8FF25043ECFD".into(),
+            actual: "71d468f2-0dc4-5017-9f12-1a81081913ef".into(),
+        }
+    );
+    assert!(mismatches.contains(&ManifestMismatch::Size {
+        expected: MAPPING.len() as u64,
+        actual: MAPPING_WIN.len() as u64,
+    }));
+    assert!(matches!(
+        mismatches.last(),
+        Some(ManifestMismatch::Sha1 { .. })
+    ));
+}
+
+#[test]
+fn test_debug_file_metadata() {
+    let mapping = ProguardMapping::new(MAPPING);
+    let metadata = mapping.debug_file_metadata();
+    assert!(metadata.has_line_info);
+    assert_eq!(metadata.size, MAPPING.len() as u64);
+    assert_eq!(
+        metadata.checksum,
+        "2cbab48f9c8fd69b4b882b3ce4821c72be4e462f"
+    );
+
+    #[cfg(feature = "uuid")]
+    assert_eq!(metadata.uuid, Some(mapping.uuid().to_string()));
+
+    let uuid = metadata.uuid.as_deref().unwrap();
+    assert_eq!(
+        metadata.to_json(),
+        format!(
+            "{{\"uuid\":\"{}\",\"object_type\":\"proguard\",\"features\":{{\"has_line_info\":true}},\"size\":{},\"checksum\":\"{}\"}}",
+            uuid,
+            MAPPING.len(),
+            metadata.checksum
+        )
+    );
+
+    // a `# uuid` header takes precedence over the checksum.
+    let uuid = "5cd8e873-1127-5276-81b7-8ff25043ecfd";
+    let source = format!("# uuid: {}\n{}", uuid.to_uppercase(), "a -> b:\n");
+    let metadata = ProguardMapping::new(source.as_bytes()).debug_file_metadata();
+    assert_eq!(metadata.uuid.as_deref(), Some(uuid));
+
+    let mapping = ProguardMapping::new(b"a -> b:\n    void method() -> b\n");
+    assert!(!mapping.debug_file_metadata().has_line_info);
+}
+
+#[test]
+fn test_canonicalize() {
+    let canonical = ProguardMapping::new(MAPPING).canonicalize();
+    for source in [&MAPPING_WIN[..], &MAPPING_MAC[..]] {
+        let other = ProguardMapping::new(source).canonicalize();
+        assert_eq!(other.sha256(), canonical.sha256());
+    }
+    assert_eq!(canonical.sha256_hex().len(), 64);
+
+    // the canonical form is a fixed point, and keeps all records.
+    let mapping = canonical.as_mapping();
+    assert_eq!(mapping.canonicalize(), canonical);
+    let records = |mapping: &ProguardMapping<'_>| mapping.iter().filter(Result::is_ok).count();
+    assert_eq!(records(&mapping), records(&ProguardMapping::new(MAPPING)));
+
+    let changed = ProguardMapping::new(b"a.A -> a:\n    void m() -> c\n").canonicalize();
+    let original = ProguardMapping::new(b"a.A -> a:\n    void m() -> b\n").canonicalize();
+    assert_ne!(changed.sha256(), original.sha256());
+}
+
+#[test]
+fn test_iteration_order() {
+    let mapping = ProguardMapping::new(MAPPING);
+
+    let from_records: Vec<_> = mapping
+        .iter()
+        .filter_map(|record| match record {
+            Ok(ProguardRecord::Class {
+                original,
+                obfuscated,
+            }) => Some((original, obfuscated)),
+            _ => None,
+        })
+        .collect();
+    let from_classes: Vec<_> = mapping
+        .classes()
+        .map(|class| (class.original(), class.obfuscated()))
+        .collect();
+    let from_class_names: Vec<_> = mapping.class_names().collect();
+
+    assert_eq!(
+        from_records.first(),
+        Some(&(
+            "android.support.annotation.Keep",
+            "android.support.annotation.Keep"
+        ))
+    );
+    assert_eq!(from_records, from_classes);
+    assert_eq!(from_records, from_class_names);
+
+    let members: Vec<_> = mapping
+        .classes()
+        .flat_map(|class| class.members())
+        .filter_map(Result::ok)
+        .collect();
+    let expected: Vec<_> = mapping
+        .iter()
+        .filter_map(Result::ok)
+        .filter(|record| {
+            !matches!(
+                record,
+                ProguardRecord::Class { .. } | ProguardRecord::Header { .. }
+            )
+        })
+        .collect();
+    assert_eq!(members, expected);
+
+    // iterating again yields identical results
+    let again: Vec<_> = mapping.class_names().collect();
+    assert_eq!(from_class_names, again);
+}
+
+#[derive(Default)]
+struct RecordCollector<'s> {
+    records: Vec<ProguardRecord<'s>>,
+}
+
+impl<'s> MappingVisitor<'s> for RecordCollector<'s> {
+    fn visit_header(&mut self, key: &'s str, value: Option<&'s str>) {
+        self.records.push(ProguardRecord::Header { key, value });
+    }
+
+    fn visit_class(&mut self, original: &'s str, obfuscated: &'s str) {
+        self.records.push(ProguardRecord::Class {
+            original,
+            obfuscated,
+        });
+    }
+
+    fn visit_field(&mut self, field: &FieldMapping<'s>) {
+        self.records.push(ProguardRecord::Field {
+            ty: field.ty,
+            original: field.original,
+            obfuscated: field.obfuscated,
+        });
+    }
+
+    fn visit_method(&mut self, method: &MethodMapping<'s>) {
+        self.records.push(ProguardRecord::Method {
+            ty: method.ty,
+            original: method.original,
+            obfuscated: method.obfuscated,
+            arguments: method.arguments,
+            original_class: method.original_class,
+            line_mapping: method.line_mapping.clone(),
+        });
+    }
+}
+
+#[test]
+fn test_visitor() {
+    for source in [
+        MAPPING,
+        &MAPPING_WIN[..],
+        b"a.A -> a:\n    invalid\n\xff\n# key: value",
+    ] {
+        let mapping = ProguardMapping::new(source);
+        let mut collector = RecordCollector::default();
+        mapping.visit(&mut collector);
+
+        let expected: Vec<_> = mapping.iter().filter_map(Result::ok).collect();
+        assert!(!expected.is_empty());
+        assert_eq!(collector.records, expected);
+    }
+}
+
+#[test]
+fn test_visitor_r8_headers() {
+    #[derive(Default)]
+    struct Collector<'s>(Vec<(&'static str, &'s str)>);
+
+    impl<'s> MappingVisitor<'s> for Collector<'s> {
+        fn visit_header(&mut self, key: &'s str, _value: Option<&'s str>) {
+            self.0.push(("header", key));
+        }
+        fn visit_class(&mut self, original: &'s str, _obfuscated: &'s str) {
+            self.0.push(("class", original));
+        }
+        fn visit_method(&mut self, method: &MethodMapping<'s>) {
+            self.0.push(("method", method.original));
+        }
+        fn visit_r8_header(&mut self, json: &'s str) {
+            self.0.push(("r8", json));
+        }
+    }
+
+    let mapping = ProguardMapping::new(
+        br#"# compiler: R8
+# {"id":"com.android.tools.r8.mapping","version":"2.2"}
+a.A -> a:
+# {"id":"sourceFile","fileName":"A.kt"}
+    1:1:void access$run():3 -> a
+      # {"id":"com.android.tools.r8.synthesized"}
+    # not metadata"#,
+    );
+    let mut collector = Collector::default();
+    mapping.visit(&mut collector);
+    assert_eq!(
+        collector.0,
+        vec![
+            ("header", "compiler"),
+            (
+                "r8",
+                r#"{"id":"com.android.tools.r8.mapping","version":"2.2"}"#
+            ),
+            ("class", "a.A"),
+            ("r8", r#"{"id":"sourceFile","fileName":"A.kt"}"#),
+            ("method", "access$run"),
+            ("r8", r#"{"id":"com.android.tools.r8.synthesized"}"#),
+        ]
+    );
+}
+
+/// Collects owned records, so it can be used with the `PushParser`.
+#[derive(Default)]
+struct RecordPrinter {
+    records: Vec<String>,
+}
+
+impl MappingVisitor<'_> for RecordPrinter {
+    fn visit_header(&mut self, key: &str, value: Option<&str>) {
+        let record = ProguardRecord::Header { key, value };
+        self.records.push(record.to_string());
+    }
+
+    fn visit_class(&mut self, original: &str, obfuscated: &str) {
+        let record = ProguardRecord::Class {
+            original,
+            obfuscated,
+        };
+        self.records.push(record.to_string());
+    }
+
+    fn visit_field(&mut self, field: &FieldMapping<'_>) {
+        self.records.push(format!(
+            "{} {} -> {}",
+            field.ty, field.original, field.obfuscated
+        ));
+    }
+
+    fn visit_method(&mut self, method: &MethodMapping<'_>) {
+        self.records.push(format!(
+            "{} {} -> {}",
+            method.ty, method.original, method.obfuscated
+        ));
+    }
+}
+
+#[test]
+fn test_push_parser() {
+    let mut expected = RecordPrinter::default();
+    ProguardMapping::new(MAPPING).visit(&mut expected);
+    assert!(!expected.records.is_empty());
+
+    for source in [MAPPING, &MAPPING_WIN[..]] {
+        for chunk_size in [1, 2, 7, 4096, source.len()] {
+            let mut parser = PushParser::new();
+            let mut printer = RecordPrinter::default();
+            for chunk in source.chunks(chunk_size) {
+                parser.feed(chunk, &mut printer);
+            }
+            parser.finish(&mut printer);
+
+            assert_eq!(printer.records, expected.records);
+        }
+    }
+}
+
+#[test]
+fn test_eager_index() {
+    for source in [
+        MAPPING,
+        &MAPPING_WIN[..],
+        include_bytes!("res/mapping-r8.txt"),
+    ] {
+        let mapping = ProguardMapping::new(source);
+        let lazy = ProguardMapper::new(mapping.clone());
+        let eager = ProguardMapper::new_eager(mapping.clone());
+
+        for class in mapping.classes() {
+            assert_eq!(
+                lazy.remap_class(class.obfuscated()),
+                eager.remap_class(class.obfuscated())
+            );
+            for method in class.methods() {
+                let line = method.line_mapping.map_or(0, |l| l.startline);
+                let frame = StackFrame::new(class.obfuscated(), method.obfuscated, line);
+                assert_eq!(
+                    lazy.remap_frame(&frame).collect::<Vec<_>>(),
+                    eager.remap_frame(&frame).collect::<Vec<_>>()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_remap_frames() {
+    let mapping = ProguardMapping::new(include_bytes!("res/mapping-r8.txt"));
+    let mapper = ProguardMapper::new(mapping.clone());
+
+    let mut frames = Vec::new();
+    for class in mapping.classes() {
+        for method in class.methods() {
+            let line = method.line_mapping.map_or(0, |l| l.startline);
+            frames.push(StackFrame::new(class.obfuscated(), method.obfuscated, line));
+        }
+    }
+    frames.push(StackFrame::new("not.in.Mapping", "a", 1));
+
+    let sequential: Vec<Vec<_>> = frames
+        .iter()
+        .map(|frame| mapper.remap_frame(frame).collect())
+        .collect();
+    assert_eq!(mapper.par_remap_frames(&frames), sequential);
+}
+
+#[test]
+fn test_hot_index_limit() {
+    for source in [MAPPING, include_bytes!("res/mapping-r8.txt")] {
+        let mapping = ProguardMapping::new(source);
+        let unlimited = ProguardMapper::new(mapping.clone());
+        let limited = ProguardMapper::new(mapping.clone()).with_hot_index_limit(2);
+
+        // every frame is remapped twice, to exercise both scanning and the index.
+        for _ in 0..2 {
+            for class in mapping.classes() {
+                for method in class.methods() {
+                    let line = method.line_mapping.map_or(0, |l| l.startline);
+                    let frame = StackFrame::new(class.obfuscated(), method.obfuscated, line);
+                    assert_eq!(
+                        unlimited.remap_frame(&frame).collect::<Vec<_>>(),
+                        limited.remap_frame(&frame).collect::<Vec<_>>()
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tab_indented() {
+    let tabbed = String::from_utf8(MAPPING.to_vec())
+        .unwrap()
+        .replace("\n    ", "\n\t");
+    let mapping = ProguardMapping::new(tabbed.as_bytes());
+    assert!(mapping.is_valid());
+    assert!(mapping.has_line_info());
+
+    let expected: Vec<_> = ProguardMapping::new(MAPPING).iter().collect();
+    let records: Vec<_> = mapping.iter().collect();
+    assert_eq!(records, expected);
+
+    for mapper in [
+        ProguardMapper::new(mapping.clone()),
+        ProguardMapper::new_eager(mapping),
+    ] {
+        let mapped: Vec<_> = mapper
+            .remap_frame(&StackFrame::new("android.support.constraint.a.a", "a", 320))
+            .collect();
+        assert_eq!(
+            mapped,
+            vec![StackFrame::new(
+                "android.support.constraint.solver.ArrayLinkedVariables",
+                "remove",
+                320
+            )]
+        );
+    }
+}
+
+#[test]
+fn test_flexible_indentation() {
+    let expected = ProguardRecord::Method {
+        ty: "void",
+        original: "method",
+        obfuscated: "a",
+        arguments: "",
+        original_class: None,
+        line_mapping: None,
+    };
+    for line in [
+        &b"  void method() -> a"[..],
+        b"    void method() -> a",
+        b"        void method() -> a",
+        b" \tvoid method() -> a",
+    ] {
+        assert_eq!(ProguardRecord::try_parse(line), Ok(expected.clone()));
+    }
+
+    let mapper = ProguardMapper::from("some.Class -> a:\n  1:1:void method():10:10 -> a");
+    assert_eq!(
+        mapper
+            .remap_frame(&StackFrame::new("a", "a", 1))
+            .collect::<Vec<_>>(),
+        vec![StackFrame::new("some.Class", "method", 10)]
+    );
+}
+
+#[test]
+fn test_reversed_line_ranges() {
+    let parsed = ProguardRecord::try_parse(b"    5:3:void method():12:10 -> a").unwrap();
+    assert_eq!(
+        parsed,
+        ProguardRecord::Method {
+            ty: "void",
+            original: "method",
+            obfuscated: "a",
+            arguments: "",
+            original_class: None,
+            line_mapping: Some(LineMapping {
+                startline: 3,
+                endline: 5,
+                original_startline: Some(10),
+                original_endline: Some(12),
+            }),
+        }
+    );
+
+    let mapper = ProguardMapper::from("a.A -> a:\n    5:3:void method():12:10 -> a");
+    let remapped: Vec<_> = mapper.remap_frame(&StackFrame::new("a", "a", 4)).collect();
+    assert_eq!(remapped, vec![StackFrame::new("a.A", "method", 11)]);
+}
+
+#[test]
+fn test_trailing_comments() {
+    let mapping = ProguardMapping::new(
+        b"\
+# compiler: R8
+some.Class -> a: # kept by rule X
+    int field -> b    # a field
+    1:1:void method():10:10 -> a\t# inlined
+",
+    );
+    assert_eq!(mapping.parse_strict().unwrap().len(), 4);
+
+    let mapper = ProguardMapper::new(mapping);
+    assert_eq!(mapper.remap_class("a"), Some("some.Class"));
+    assert_eq!(
+        mapper
+            .remap_frame(&StackFrame::new("a", "a", 1))
+            .collect::<Vec<_>>(),
+        vec![StackFrame::new("some.Class", "method", 10)]
+    );
+
+    assert_eq!(
+        ProguardRecord::try_parse_with_comment(b"    int field -> b    # a field"),
+        Ok((
+            ProguardRecord::Field {
+                ty: "int",
+                original: "field",
+                obfuscated: "b",
+            },
+            Some("a field")
+        ))
+    );
+    assert_eq!(
+        ProguardRecord::try_parse_with_comment(b"# compiler: R8"),
+        Ok((
+            ProguardRecord::Header {
+                key: "compiler",
+                value: Some("R8"),
+            },
+            None
+        ))
+    );
+}
+
+#[test]
+fn test_display_roundtrip() {
+    for source in [
+        MAPPING,
+        include_bytes!("res/mapping-inlines.txt"),
+        include_bytes!("res/mapping-r8.txt"),
+    ] {
+        for record in ProguardMapping::new(source).iter() {
+            let record = record.unwrap();
+            let line = record.to_string();
+            assert_eq!(ProguardRecord::try_parse(line.as_bytes()), Ok(record));
+        }
+    }
+
+    let source = "\
+# compiler: R8
+# {\"id\":\"sourceFile\",\"fileName\":\"Foo.java\"}
+com.example.Foo -> a:
+    int field -> a
+    void method() -> b
+    1:1:void method(int):12 -> c
+    2:3:java.lang.String com.example.Bar.inlined():4:5 -> c";
+    let output: Vec<_> = ProguardMapping::new(source.as_bytes())
+        .iter()
+        .map(|record| record.unwrap().to_string())
+        .collect();
+    assert_eq!(output.join("\n"), source);
+}
+
+#[test]
+fn test_raw_lines() {
+    // a filter which drops one field, and reproduces all other lines exactly.
+    let source =
+        b"# compiler: R8\r\n\na.A -> a:\n\tint keep -> a\r\n    int drop -> b\n\n    invalid";
+    let mut iter = ProguardMapping::new(source).iter();
+    let mut output = Vec::new();
+    let mut errors = Vec::new();
+    while let Some((line, record)) = iter.next_with_line() {
+        match record {
+            Some(Ok(ProguardRecord::Field {
+                original: "drop", ..
+            })) => continue,
+            Some(Ok(_)) | None => {}
+            Some(Err(err)) => errors.push(err.line()),
+        }
+        output.extend_from_slice(line);
+    }
+    assert_eq!(
+        output,
+        b"# compiler: R8\r\n\na.A -> a:\n\tint keep -> a\r\n\n    invalid"
+    );
+    assert_eq!(errors, vec![&b"    invalid"[..]]);
+}
@@ -0,0 +1,232 @@
+use std::error::Error as _;
+use std::io::{Cursor, ErrorKind, Write};
+
+use proguard::{
+    CacheError, Error, ProguardCache, ProguardCacheBuf, ProguardMapper, ProguardMapping, StackFrame,
+};
+
+static MAPPING: &[u8] = include_bytes!("res/mapping-inlines.txt");
+
+/// All `(class, method, line)` frames which could appear for `mapping`.
+fn frames(mapping: &ProguardMapping<'_>) -> Vec<(String, String, usize)> {
+    let mut frames = Vec::new();
+    for class in mapping.classes() {
+        for method in class.methods() {
+            let (start, end) = method.line_mapping.map_or((0, 0), |line_mapping| {
+                (line_mapping.startline, line_mapping.endline)
+            });
+            // line 0 is what frames without a line number are parsed as.
+            let unknown = (start > 0).then_some(0);
+            for line in unknown.into_iter().chain(start..=end + 1) {
+                frames.push((
+                    class.obfuscated().to_owned(),
+                    method.obfuscated.to_owned(),
+                    line,
+                ));
+            }
+        }
+    }
+    frames
+}
+
+/// Checks that `cache` remaps every `step`th frame of `mapping` like [`ProguardMapper`].
+fn assert_same_as_mapper(cache: &ProguardCache<'_>, mapping: ProguardMapping<'_>, step: usize) {
+    let frames: Vec<_> = frames(&mapping).into_iter().step_by(step).collect();
+    let mapper = ProguardMapper::new(mapping);
+    assert!(!frames.is_empty());
+
+    for (class, method, line) in &frames {
+        let frame = StackFrame::new(class, method, *line);
+        assert_eq!(cache.remap_class(class), mapper.remap_class(class));
+        assert_eq!(
+            cache.remap_frame(&frame).collect::<Vec<_>>(),
+            mapper.remap_frame(&frame).collect::<Vec<_>>(),
+            "{:?}",
+            frame
+        );
+
+        let mut cached = cache.remap_frame(&frame);
+        let mut remapped = mapper.remap_frame(&frame);
+        loop {
+            let next = cached.next_with_confidence();
+            assert_eq!(next, remapped.next_with_confidence(), "{:?}", frame);
+            if next.is_none() {
+                break;
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cache_roundtrip() {
+    let mapping = ProguardMapping::new(MAPPING);
+    let mut buf = Vec::new();
+    ProguardCache::write(&mapping, &mut buf).unwrap();
+
+    let cache = ProguardCache::parse(&buf).unwrap();
+    assert_eq!(
+        cache.version(),
+        (ProguardCache::MAJOR_VERSION, ProguardCache::MINOR_VERSION)
+    );
+    assert_eq!(cache.class_count(), mapping.classes().count());
+    assert_same_as_mapper(&cache, mapping, 1);
+}
+
+#[test]
+fn test_cache_r8() {
+    let mapping = ProguardMapping::new(include_bytes!("res/mapping-r8.txt"));
+    let mut buf = Vec::new();
+    ProguardCache::write(&mapping, &mut buf).unwrap();
+
+    let cache = ProguardCache::parse(&buf).unwrap();
+    assert_same_as_mapper(&cache, mapping, 10);
+}
+
+#[test]
+fn test_cache_streaming() {
+    let mapping = ProguardMapping::new(MAPPING);
+
+    // write the cache into the middle of a container stream.
+    let mut stream = Cursor::new(Vec::new());
+    stream.write_all(b"HEADER").unwrap();
+    ProguardCache::write(&mapping, &mut stream).unwrap();
+    let len = stream.position() - 6;
+    stream.write_all(b"TRAILER").unwrap();
+
+    let buf = ProguardCacheBuf::from_reader_range(&mut stream, 6, len).unwrap();
+    assert_eq!(buf.as_bytes().len() as u64, len);
+    assert_same_as_mapper(&buf.as_cache(), mapping.clone(), 1);
+
+    let err = ProguardCacheBuf::from_reader_range(&mut stream, 6, len + 100).unwrap_err();
+    assert!(matches!(err, Error::Io(ref e) if e.kind() == ErrorKind::UnexpectedEof));
+
+    let err = ProguardCacheBuf::from_reader(&b"not a cache"[..]).unwrap_err();
+    assert!(matches!(err, Error::Cache(CacheError::InvalidMagic)));
+    assert_eq!(
+        err.source().unwrap().downcast_ref::<CacheError>(),
+        Some(&CacheError::InvalidMagic)
+    );
+}
+
+#[test]
+fn test_cache_fixture_v1_0() {
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.0.cache")).unwrap();
+    assert_eq!(cache.version(), (1, 0));
+    assert_same_as_mapper(&cache, ProguardMapping::new(MAPPING), 1);
+}
+
+#[test]
+fn test_cache_fixture_v1_1() {
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.1.cache")).unwrap();
+    assert_eq!(cache.version(), (1, 1));
+    assert!(cache.has_line_info());
+    assert_same_as_mapper(&cache, ProguardMapping::new(MAPPING), 1);
+}
+
+#[test]
+fn test_cache_fixture_v1_2() {
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.2.cache")).unwrap();
+    assert_eq!(cache.version(), (1, 2));
+    assert!(cache.has_line_info());
+    assert_same_as_mapper(&cache, ProguardMapping::new(MAPPING), 1);
+}
+
+#[test]
+fn test_cache_fixture_v1_3() {
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.3.cache")).unwrap();
+    assert_eq!(cache.version(), (1, 3));
+    assert_same_as_mapper(&cache, ProguardMapping::new(MAPPING), 1);
+}
+
+#[test]
+fn test_cache_fixture_v1_4() {
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.4.cache")).unwrap();
+    assert_eq!(cache.version(), (1, 4));
+    assert_same_as_mapper(&cache, ProguardMapping::new(MAPPING), 1);
+}
+
+#[test]
+fn test_cache_fixture_v1_5() {
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.5.cache")).unwrap();
+    assert_eq!(cache.version(), (1, 5));
+    assert_eq!(cache.pg_map_id(), Some("5b46fdc"));
+    #[cfg(feature = "uuid")]
+    assert_eq!(cache.uuid().unwrap(), ProguardMapping::new(MAPPING).uuid());
+    assert_same_as_mapper(&cache, ProguardMapping::new(MAPPING), 1);
+
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.4.cache")).unwrap();
+    assert_eq!(cache.pg_map_id(), None);
+    #[cfg(feature = "uuid")]
+    assert_eq!(cache.uuid(), None);
+}
+
+#[cfg(feature = "uuid")]
+#[test]
+fn test_cache_header_uuid() {
+    let uuid = ProguardMapping::new(MAPPING).uuid();
+    let mut source = format!("# uuid: {}\n", uuid).into_bytes();
+    source.extend_from_slice(MAPPING);
+
+    let mut buf = Vec::new();
+    ProguardCache::write(&ProguardMapping::new(&source), &mut buf).unwrap();
+    let cache = ProguardCache::parse(&buf).unwrap();
+    assert_eq!(cache.uuid(), Some(uuid));
+}
+
+#[test]
+fn test_cache_fixture_v1_6() {
+    let cache = ProguardCache::parse(include_bytes!("res/mapping-inlines-v1.6.cache")).unwrap();
+    assert_eq!(cache.version(), (1, 6));
+    assert_same_as_mapper(&cache, ProguardMapping::new(MAPPING), 1);
+}
+
+#[test]
+fn test_cache_update() {
+    let source = String::from_utf8(MAPPING.to_vec()).unwrap();
+    let changed = source.replacen("13:13 -> read", "14:14 -> read", 1)
+        + "com.example.Added -> zz:\n    1:1:void added():3 -> a\n";
+    let mapping = ProguardMapping::new(changed.as_bytes());
+    let mut expected = Vec::new();
+    ProguardCache::write(&mapping, &mut expected).unwrap();
+
+    for previous in [
+        &include_bytes!("res/mapping-inlines-v1.6.cache")[..],
+        include_bytes!("res/mapping-inlines-v1.5.cache"),
+        include_bytes!("res/mapping-inlines-v1.4.cache"),
+        include_bytes!("res/mapping-inlines-v1.3.cache"),
+        &expected,
+    ] {
+        let previous = ProguardCache::parse(previous).unwrap();
+        let mut buf = Vec::new();
+        previous.update(&mapping, &mut buf).unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    let cache = ProguardCache::parse(&expected).unwrap();
+    assert_same_as_mapper(&cache, mapping, 1);
+}
+
+#[test]
+fn test_cache_has_line_info() {
+    for (source, has_line_info) in [
+        (&b"a.A -> a:\n    1:1:void m():3 -> a"[..], true),
+        (b"a.A -> a:\n    void m() -> a\n    int f -> b", false),
+        (b"", false),
+    ] {
+        let mapping = ProguardMapping::new(source);
+        let mut buf = Vec::new();
+        ProguardCache::write(&mapping, &mut buf).unwrap();
+
+        let cache = ProguardCache::parse(&buf).unwrap();
+        assert_eq!(cache.has_line_info(), has_line_info);
+        assert_eq!(mapping.has_line_info(), has_line_info);
+        assert_eq!(
+            ProguardMapper::new(mapping.clone()).has_line_info(),
+            has_line_info
+        );
+        assert_eq!(
+            ProguardMapper::new_eager(mapping).has_line_info(),
+            has_line_info
+        );
+    }
+}
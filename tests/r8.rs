@@ -1,6 +1,6 @@
 use lazy_static::lazy_static;
 
-use proguard::{ProguardMapper, ProguardMapping, StackFrame};
+use proguard::{CompilerVersion, ProguardMapper, ProguardMapping, StackFrame};
 
 static MAPPING_R8: &[u8] = include_bytes!("res/mapping-r8.txt");
 
@@ -76,9 +76,19 @@ fn test_summary() {
     let summary = mapping.summary();
     assert_eq!(summary.compiler(), Some("R8"));
     assert_eq!(summary.compiler_version(), Some("1.3.49"));
+    assert_eq!(
+        summary.compiler_version_typed(),
+        Some(CompilerVersion::new(1, 3, 49))
+    );
     assert_eq!(summary.min_api(), Some(15));
     assert_eq!(summary.class_count(), 1167);
+    assert_eq!(mapping.class_count(), 1167);
     assert_eq!(summary.method_count(), 24076);
+
+    let headers = mapping.headers();
+    assert_eq!(headers.get("compiler_version"), Some("1.3.49"));
+    assert_eq!(headers.min_api(), Some(15));
+    assert_eq!(headers.application_id(), None);
 }
 
 #[cfg(feature = "uuid")]
@@ -1,4 +1,7 @@
-use proguard::{ProguardMapper, StackFrame};
+use proguard::{
+    Confidence, ProguardMapper, ProguardMapping, ProguardObfuscator, RetraceOptions, Retraceable,
+    SourceLocation, StackFrame, StackTrace,
+};
 
 #[test]
 fn test_remap() {
@@ -106,3 +109,1156 @@ fn test_remap_kotlin() {
             .trim()
     );
 }
+
+#[test]
+fn test_remap_synthetic_lambda() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Foo$$ExternalSyntheticLambda1 -> a.b:
+    void run() -> run
+com.example.-$$Lambda$Foo$AbCdEf -> a.c:
+    1:1:void com.example.Foo.lambda$onCreate$0():12:12 -> run
+    1:1:void run():0 -> run"#,
+    );
+
+    let mapped = mapper
+        .remap_stacktrace(
+            "\
+java.lang.RuntimeException: boom
+    at a.b.run(D8$$SyntheticClass)
+    at a.c.run(Unknown Source:1)",
+        )
+        .unwrap();
+
+    assert_eq!(
+        mapped.trim(),
+        "\
+java.lang.RuntimeException: boom
+    at com.example.Foo$$ExternalSyntheticLambda1.run(D8$$SyntheticClass:0)
+    at com.example.Foo.lambda$onCreate$0(<unknown>:12)
+    at com.example.-$$Lambda$Foo$AbCdEf.run(Unknown Source:0)"
+    );
+}
+
+#[test]
+fn test_remap_headlines() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.CrashException -> a.a:
+com.example.IoFailure -> a.b:"#,
+    );
+
+    let mapped = mapper
+        .remap_stacktrace(
+            "\
+Exception in thread \"main\" a.a: boom
+    at java.lang.Thread.run(Thread.java:1)
+Caused by: a.b:
+    ... 1 more
+    Caused by: a.b: nested
+        ... 1 more",
+        )
+        .unwrap();
+
+    assert_eq!(
+        mapped.trim(),
+        "\
+Exception in thread \"main\" com.example.CrashException: boom
+    at java.lang.Thread.run(Thread.java:1)
+Caused by: com.example.IoFailure
+    ... 1 more
+    Caused by: com.example.IoFailure: nested
+        ... 1 more"
+    );
+}
+
+#[test]
+fn test_remap_suppressed() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.CrashException -> a.a:
+com.example.CloseException -> a.b:
+com.example.Resource -> a.c:
+    1:1:void close():20:20 -> a"#,
+    );
+    let input = "\
+a.a: boom
+\tat a.c.a(SourceFile:1)
+\tSuppressed: a.b: while closing
+\t\tat a.c.a(SourceFile:1)
+";
+
+    let trace = StackTrace::try_parse(input.as_bytes()).unwrap();
+    let mapped = mapper.remap_stacktrace_typed(&trace);
+    let suppressed = &mapped.suppressed()[0];
+    assert_eq!(
+        suppressed.exception().map(|e| e.class()),
+        Some("com.example.CloseException")
+    );
+    assert_eq!(
+        suppressed.frames(),
+        &[StackFrame::with_file(
+            "com.example.Resource",
+            "close",
+            20,
+            "SourceFile"
+        )]
+    );
+    assert_eq!(
+        mapped.to_string(),
+        "\
+com.example.CrashException: boom
+    at com.example.Resource.close(SourceFile:20)
+    Suppressed: com.example.CloseException: while closing
+        at com.example.Resource.close(SourceFile:20)
+"
+    );
+
+    assert_eq!(
+        mapper.remap_stacktrace(input).unwrap(),
+        "\
+com.example.CrashException: boom
+\tat com.example.Resource.close(SourceFile:20)
+\tSuppressed: com.example.CloseException: while closing
+\t\tat com.example.Resource.close(SourceFile:20)
+"
+    );
+}
+
+#[test]
+fn test_annotate_obfuscated() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.MainFragment -> a.a:
+    1:1:void com.example.Rocket.fly():83:83 -> a
+    1:1:void onClick(android.view.View):65 -> a"#,
+    );
+    let options = RetraceOptions {
+        annotate_obfuscated: true,
+        ..Default::default()
+    };
+
+    let mapped = mapper
+        .remap_stacktrace_with_options(
+            "\
+java.lang.IllegalStateException: boom
+    at a.a.a(SourceFile:1)
+    at android.view.View.performClick(View.java:7393)",
+            &options,
+        )
+        .unwrap();
+    assert_eq!(
+        mapped,
+        "\
+java.lang.IllegalStateException: boom
+    at com.example.Rocket.fly(<unknown>:83) // was a.a.a(SourceFile:1)
+    at com.example.MainFragment.onClick(SourceFile:65) // was a.a.a(SourceFile:1)
+    at android.view.View.performClick(View.java:7393)
+"
+    );
+}
+
+#[test]
+fn test_annotate_unmapped() {
+    let mapper = ProguardMapper::from(
+        "com.example.Foo -> a.a:\n    1:1:void bar():10 -> a\ncom.Keep -> com.Keep:\n    1:1:void run():1:1 -> run",
+    );
+    let options = RetraceOptions {
+        annotate_unmapped: true,
+        ..Default::default()
+    };
+
+    let mapped = mapper
+        .remap_stacktrace_with_options(
+            "\
+java.lang.IllegalStateException: boom
+    at a.a.a(SourceFile:1)
+    at a.a.b(SourceFile:1)
+    at b.b.a(SourceFile:2)
+    at com.Keep.run(SourceFile:1)",
+            &options,
+        )
+        .unwrap();
+    // classes which are kept under their original name are found, even though
+    // remapping does not change them.
+    assert_eq!(
+        mapped,
+        "\
+java.lang.IllegalStateException: boom
+    at com.example.Foo.bar(SourceFile:10)
+    at a.a.b(SourceFile:1) // not found in mapping
+    at b.b.a(SourceFile:2) // not found in mapping
+    at com.Keep.run(SourceFile:1)
+"
+    );
+}
+
+#[test]
+fn test_infer_file_names() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.MainFragment -> a.a:
+# {"id":"sourceFile","fileName":"MainFragment.kt"}
+    1:1:void com.example.Rocket$Engine.fly():83:83 -> a
+    1:1:void onClick(android.view.View):65 -> a
+com.example.Util -> a.b:
+    1:1:void run():10:10 -> a"#,
+    );
+    let options = RetraceOptions {
+        infer_file_names: true,
+        ..Default::default()
+    };
+
+    let mapped = mapper
+        .remap_stacktrace_with_options(
+            "\
+java.lang.IllegalStateException: boom
+    at a.a.a(SourceFile:1)
+    at a.b.a(a:1)
+    at a.b.a(Unknown Source:1)
+    at android.view.View.performClick(View.java:7393)",
+            &options,
+        )
+        .unwrap();
+    assert_eq!(
+        mapped,
+        "\
+java.lang.IllegalStateException: boom
+    at com.example.Rocket$Engine.fly(Rocket.java:83)
+    at com.example.MainFragment.onClick(MainFragment.kt:65)
+    at com.example.Util.run(Util.java:10)
+    at com.example.Util.run(Unknown Source:10)
+    at android.view.View.performClick(View.java:7393)
+"
+    );
+}
+
+#[test]
+fn test_remap_class_names() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Foo -> a.a:
+    1:1:void run():10 -> a
+com.example.Bar -> a.b:
+com.example.Lock -> a.c:"#,
+    );
+    let input = "\
+java.lang.ClassCastException: a.b cannot be cast to a.a.
+    at a.a.a(SourceFile:1)
+    - locked <0x0f3c> (a a.c)
+Thread: a.ab waiting on a.c$1
+";
+
+    // only frames are remapped by default.
+    assert_eq!(
+        mapper.remap_stacktrace(input).unwrap(),
+        "\
+java.lang.ClassCastException: a.b cannot be cast to a.a.
+    at com.example.Foo.run(SourceFile:10)
+    - locked <0x0f3c> (a a.c)
+Thread: a.ab waiting on a.c$1
+"
+    );
+
+    let options = RetraceOptions {
+        remap_class_names: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        mapper
+            .remap_stacktrace_with_options(input, &options)
+            .unwrap(),
+        "\
+java.lang.ClassCastException: com.example.Bar cannot be cast to com.example.Foo.
+    at com.example.Foo.run(SourceFile:10)
+    - locked <0x0f3c> (a com.example.Lock)
+Thread: a.ab waiting on com.example.Lock$1
+"
+    );
+}
+
+#[test]
+fn test_source_location() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Foo -> a.a:
+# {"id":"sourceFile","fileName":"Foo.kt"}
+    1:1:void run():10 -> a"#,
+    );
+    let input = "\
+java.lang.RuntimeException: boom
+    at a.a.a(SourceFile:1)
+    at b.b.b(SourceFile:2)
+";
+    let remap = |source_location| {
+        let options = RetraceOptions {
+            infer_file_names: true,
+            source_location,
+            ..Default::default()
+        };
+        mapper
+            .remap_stacktrace_with_options(input, &options)
+            .unwrap()
+    };
+
+    assert_eq!(
+        remap(SourceLocation::FileAndLine),
+        mapper
+            .remap_stacktrace_with_options(
+                input,
+                &RetraceOptions {
+                    infer_file_names: true,
+                    ..Default::default()
+                }
+            )
+            .unwrap()
+    );
+    assert!(remap(SourceLocation::FileAndLine).contains("    at com.example.Foo.run(Foo.kt:10)\n"));
+    assert!(remap(SourceLocation::File).contains("    at com.example.Foo.run(Foo.kt)\n"));
+    assert!(remap(SourceLocation::UnknownSource)
+        .contains("    at com.example.Foo.run(Unknown Source)\n"));
+    assert_eq!(
+        remap(SourceLocation::Omitted),
+        "\
+java.lang.RuntimeException: boom
+    at com.example.Foo.run
+    at b.b.b(SourceFile:2)
+"
+    );
+
+    // this applies to r8 compatible output as well.
+    let options = RetraceOptions {
+        r8_compat: true,
+        source_location: SourceLocation::File,
+        ..Default::default()
+    };
+    assert!(mapper
+        .remap_stacktrace_with_options(input, &options)
+        .unwrap()
+        .contains("    at com.example.Foo.run(Foo.kt)\n"));
+}
+
+#[test]
+fn test_baseline_profile() {
+    let mapping = r#"com.example.Repository -> a.a:
+    java.util.List load(com.example.Query,int) -> a
+    java.util.List load(com.example.Query) -> b
+    1:4:void <init>():12:15 -> <init>
+com.example.Query -> a.b:
+    1:1:boolean isEmpty():20:20 -> a"#;
+    let original = "\
+HSPLcom/example/Repository;-><init>()V
+HSPLcom/example/Repository;->load(Lcom/example/Query;I)Ljava/util/List;
+PLcom/example/Repository;->load(Lcom/example/Query;)Ljava/util/List;
+Lcom/example/Query;
+SPLcom/example/Query;->isEmpty()Z
+Lcom/example/ui/**;
+";
+    let obfuscated = "\
+HSPLa/a;-><init>()V
+HSPLa/a;->a(La/b;I)Ljava/util/List;
+PLa/a;->b(La/b;)Ljava/util/List;
+La/b;
+SPLa/b;->a()Z
+Lcom/example/ui/**;
+";
+
+    let obfuscator = ProguardObfuscator::from(mapping);
+    assert_eq!(obfuscator.obfuscate_baseline_profile(original), obfuscated);
+
+    let mapper = ProguardMapper::from(mapping);
+    assert_eq!(mapper.remap_baseline_profile(obfuscated), original);
+}
+
+#[test]
+fn test_logcat_prefixes() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.CrashException -> a.a:
+com.example.Foo -> a.b:
+    1:1:void com.example.Bar.inner():20:20 -> a
+    1:1:void bar():10:10 -> a"#,
+    );
+    let input = "\
+04-15 12:34:56.789  1234  1234 E AndroidRuntime: FATAL EXCEPTION: main
+04-15 12:34:56.789  1234  1234 E AndroidRuntime: a.a: boom
+04-15 12:34:56.789  1234  1234 E AndroidRuntime: \tat a.b.a(SourceFile:1)
+E/AndroidRuntime( 1234): Caused by: a.a
+E/AndroidRuntime( 1234): \tat a.b.a(SourceFile:1)
+";
+
+    // logcat lines are left alone by default.
+    assert_eq!(mapper.remap_stacktrace(input).unwrap(), input);
+
+    let options = RetraceOptions {
+        logcat_prefixes: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        mapper
+            .remap_stacktrace_with_options(input, &options)
+            .unwrap(),
+        "\
+04-15 12:34:56.789  1234  1234 E AndroidRuntime: FATAL EXCEPTION: main
+04-15 12:34:56.789  1234  1234 E AndroidRuntime: com.example.CrashException: boom
+04-15 12:34:56.789  1234  1234 E AndroidRuntime: \tat com.example.Bar.inner(<unknown>:20)
+04-15 12:34:56.789  1234  1234 E AndroidRuntime: \tat com.example.Foo.bar(SourceFile:10)
+E/AndroidRuntime( 1234): Caused by: com.example.CrashException
+E/AndroidRuntime( 1234): \tat com.example.Bar.inner(<unknown>:20)
+E/AndroidRuntime( 1234): \tat com.example.Foo.bar(SourceFile:10)
+"
+    );
+}
+
+#[test]
+fn test_match_truncated_classes() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.CrashException -> io.sentry.Crash:
+com.example.MainFragment -> io.sentry.sample.MainFragmentKt:
+    1:1:void onClick():65 -> a
+    1:1:void onCreate():70 -> onCreate
+com.example.Other -> io.sentry.sample.MainFragmentXy:
+    1:1:void run():12 -> a
+com.example.Util -> io.sentry.sample.MainFragmentXz:"#,
+    );
+    // logcat cuts off the last line of a message that is too long.
+    let prefix = "04-15 12:34:56.789  1234  5678 E AndroidRuntime: ";
+    let trace = |last: &str| {
+        format!(
+            "{prefix}io.sentry.Crash: boom\n\
+             {prefix}\tat io.sentry.sample.MainFragmentKt.a(SourceFile:1)\n\
+             {prefix}\tat {last}\n",
+            prefix = prefix,
+            last = last
+        )
+    };
+
+    // truncated frames are left alone by default.
+    let input = trace("io.sentry.sample.MainFragmentXy.a(Sour");
+    let options = RetraceOptions {
+        logcat_prefixes: true,
+        ..Default::default()
+    };
+    assert!(mapper
+        .remap_stacktrace_with_options(&input, &options)
+        .unwrap()
+        .ends_with(&format!(
+            "{}\tat io.sentry.sample.MainFragmentXy.a(Sour\n",
+            prefix
+        )));
+
+    let options = RetraceOptions {
+        match_truncated_classes: true,
+        ..options
+    };
+    for (last, expected) in [
+        (
+            "io.sentry.sample.MainFragmentXy.a(Sour",
+            "com.example.Other.run(Sour",
+        ),
+        (
+            "io.sentry.sample.MainFragmentKt.onCr",
+            "com.example.MainFragment.onCreate",
+        ),
+        (
+            "io.sentry.sample.MainFragmentKt.a",
+            "com.example.MainFragment.onClick",
+        ),
+        ("io.sentry.sample.MainFragmentK", "com.example.MainFragment"),
+        // the class can not be told apart from `MainFragmentXz`.
+        (
+            "io.sentry.sample.MainFragmentX",
+            "io.sentry.sample.MainFragmentX",
+        ),
+    ] {
+        assert_eq!(
+            mapper
+                .remap_stacktrace_with_options(&trace(last), &options)
+                .unwrap(),
+            format!(
+                "{prefix}com.example.CrashException: boom\n\
+                 {prefix}\tat com.example.MainFragment.onClick(SourceFile:65)\n\
+                 {prefix}\tat {expected}\n",
+                prefix = prefix,
+                expected = expected
+            )
+        );
+    }
+}
+
+#[test]
+fn test_method_index() {
+    let mapping = r#"com.example.Foo -> a.a:
+    1:1:void bar():10:10 -> zza
+    1:1:void baz():20:20 -> a
+    void onClick() -> onClick
+com.example.Other -> a.b:
+    1:1:void other():30:30 -> a"#;
+    let input = "\
+java.lang.RuntimeException: boom
+    at c.c.zza(SourceFile:1)
+    at c.c.a(SourceFile:1)
+    at c.c.onClick(SourceFile:1)
+    at a.b.zza(SourceFile:1)
+";
+
+    // frames of missing classes are left alone without the index.
+    let mapper = ProguardMapper::from(mapping);
+    assert_eq!(mapper.remap_stacktrace(input).unwrap(), input);
+
+    let mapper = ProguardMapper::from(mapping).with_method_index();
+    assert_eq!(
+        mapper
+            .remap_frame_by_method(&StackFrame::new("c.c", "zza", 1))
+            .collect::<Vec<_>>(),
+        vec![StackFrame::new("com.example.Foo", "bar", 10)]
+    );
+    // `a` is ambiguous, `onClick` is not renamed, and `a.b` is part of the mapping.
+    assert_eq!(
+        mapper
+            .remap_frame_by_method(&StackFrame::new("c.c", "a", 1))
+            .next(),
+        None
+    );
+    assert_eq!(
+        mapper
+            .remap_frame_by_method(&StackFrame::new("c.c", "onClick", 1))
+            .next(),
+        None
+    );
+    assert_eq!(
+        mapper
+            .remap_frame_by_method(&StackFrame::new("a.b", "zza", 1))
+            .next(),
+        None
+    );
+
+    assert_eq!(
+        mapper.remap_stacktrace(input).unwrap(),
+        "\
+java.lang.RuntimeException: boom
+    at com.example.Foo.bar(SourceFile:10) // guessed by method name
+    at c.c.a(SourceFile:1)
+    at c.c.onClick(SourceFile:1)
+    at a.b.zza(SourceFile:1)
+"
+    );
+}
+
+#[test]
+fn test_obfuscate() {
+    let mapping = r#"com.example.CrashException -> a.a:
+com.example.MainActivity -> a.b:
+    11:11:void <init>() -> <init>
+    17:26:void onCreate(android.os.Bundle) -> a
+    1:1:void onClick(android.view.View):29:29 -> b
+    2:3:void onClick(android.view.View):40:41 -> b
+    void onStop() -> c"#;
+    let obfuscator = ProguardObfuscator::from(mapping);
+
+    assert_eq!(
+        obfuscator.obfuscate_class("com.example.MainActivity"),
+        Some("a.b")
+    );
+    assert_eq!(obfuscator.obfuscate_class("a.b"), None);
+    assert_eq!(
+        obfuscator.obfuscate_frame(&StackFrame::new("com.example.MainActivity", "onStop", 5)),
+        StackFrame::new("a.b", "c", 5)
+    );
+    // methods not covered by the mapping do not keep their original name.
+    assert_eq!(
+        obfuscator.obfuscate_frame(&StackFrame::new("com.example.MainActivity", "onClick", 30)),
+        StackFrame::new("a.b", "<unknown>", 30)
+    );
+    assert_eq!(
+        obfuscator.obfuscate_frame(&StackFrame::new("com.example.MainActivity", "<clinit>", 5)),
+        StackFrame::new("a.b", "<clinit>", 5)
+    );
+
+    let input = "\
+com.example.CrashException: boom
+    at com.example.MainActivity.onClick(MainActivity.java:41)
+    at com.example.MainActivity.onCreate(MainActivity.java:20)
+    at android.app.Activity.performCreate(Activity.java:8000)
+Caused by: java.lang.IllegalStateException
+    at com.example.MainActivity.onClick(MainActivity.java:29)
+";
+    let obfuscated = obfuscator.obfuscate_stacktrace(input).unwrap();
+    assert_eq!(
+        obfuscated,
+        "\
+a.a: boom
+    at a.b.b(SourceFile:3)
+    at a.b.a(SourceFile:20)
+    at android.app.Activity.performCreate(Activity.java:8000)
+Caused by: java.lang.IllegalStateException
+    at a.b.b(SourceFile:1)
+"
+    );
+
+    // remapping the obfuscated trace yields the original one.
+    let mapper = ProguardMapper::from(mapping);
+    let remapped = mapper.remap_stacktrace(&obfuscated).unwrap();
+    assert_eq!(remapped, input.replace("MainActivity.java", "SourceFile"));
+
+    // frames keep the indentation of the input.
+    let obfuscated = obfuscator
+        .obfuscate_stacktrace(
+            "a: boom\n\tat com.example.MainActivity.onCreate(MainActivity.java:20)",
+        )
+        .unwrap();
+    assert_eq!(obfuscated, "a: boom\n\tat a.b.a(SourceFile:20)\n");
+}
+
+#[test]
+fn test_remap_passthrough() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.CrashException -> a.a:
+com.example.Foo -> a.b:
+    1:1:void bar():10:10 -> a"#,
+    );
+
+    let input = "\
+E/AndroidRuntime: FATAL EXCEPTION: main
+
+a.a: boom
+\tat a.b.a(SourceFile:1)
+\t... 3 more
+I/log: at a.b.a(SourceFile:1)
+
+";
+    assert_eq!(
+        mapper.remap_stacktrace(input).unwrap(),
+        "\
+E/AndroidRuntime: FATAL EXCEPTION: main
+
+com.example.CrashException: boom
+\tat com.example.Foo.bar(SourceFile:10)
+\t... 3 more
+I/log: at a.b.a(SourceFile:1)
+
+"
+    );
+
+    assert_eq!(
+        mapper
+            .remap_stacktrace("a.a\r\n\tat a.b.a(SourceFile:1)\r\n\r\n")
+            .unwrap(),
+        "com.example.CrashException\r\n\tat com.example.Foo.bar(SourceFile:10)\r\n\r\n"
+    );
+    assert_eq!(
+        mapper
+            .remap_stacktrace("a.a\r\tat a.b.a(SourceFile:1)\r")
+            .unwrap(),
+        "com.example.CrashException\r\tat com.example.Foo.bar(SourceFile:10)\r"
+    );
+}
+
+#[test]
+fn test_remap_module_frames() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.CrashException -> a.a:
+com.example.Foo -> a.b:
+    1:1:void bar():10:10 -> a"#,
+    );
+
+    let input = "\
+a.a: boom
+\tat app/mymodule@1.2/a.b.a(SourceFile:1)
+\tat java.base/java.lang.Thread.run(Thread.java:829)
+";
+    assert_eq!(
+        mapper.remap_stacktrace(input).unwrap(),
+        "\
+com.example.CrashException: boom
+\tat app/mymodule@1.2/com.example.Foo.bar(SourceFile:10)
+\tat java.base/java.lang.Thread.run(Thread.java:829)
+"
+    );
+}
+
+#[test]
+fn test_remap_confidence() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Foo -> a.a:
+    0:65535:void all():100:65635 -> a
+    5:10:void range():20:25 -> a
+    11:11:void exact():30 -> a
+    void noLines() -> b"#,
+    )
+    .with_method_index();
+
+    let confidence = |frame: StackFrame<'_>| {
+        let mut remapped = mapper.remap_frame(&frame);
+        let mut levels = Vec::new();
+        while let Some((_, confidence)) = remapped.next_with_confidence() {
+            levels.push(confidence);
+        }
+        levels
+    };
+    assert_eq!(
+        confidence(StackFrame::new("a.a", "a", 1)),
+        [Confidence::CatchAll]
+    );
+    assert_eq!(
+        confidence(StackFrame::new("a.a", "a", 7)),
+        [Confidence::Range]
+    );
+    assert_eq!(
+        confidence(StackFrame::new("a.a", "a", 11)),
+        [Confidence::Exact]
+    );
+    assert_eq!(
+        confidence(StackFrame::new("a.a", "b", 1)),
+        [Confidence::CatchAll]
+    );
+
+    let mut guessed = mapper.remap_frame_by_method(&StackFrame::new("c.c", "b", 1));
+    assert_eq!(
+        guessed.next_with_confidence(),
+        Some((
+            StackFrame::new("com.example.Foo", "noLines", 0),
+            Confidence::Heuristic
+        ))
+    );
+    assert!(Confidence::Heuristic < Confidence::CatchAll);
+    assert!(Confidence::Range < Confidence::Exact);
+}
+
+#[test]
+fn test_remap_frame_detailed() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Foo -> a.a:
+    1:3:void inner():10:12 -> a
+    1:3:void middle():20 -> a
+    1:3:void outer():30 -> a
+    4:4:void other():40:40 -> a
+    void noLines() -> b
+    void noLines(int) -> b"#,
+    );
+
+    let frame = StackFrame::new("a.a", "a", 2);
+    let remapped = mapper.remap_frame_detailed(&frame);
+    let summary: Vec<_> = remapped
+        .iter()
+        .map(|f| (f.method, f.line, f.inlined_into.is_some(), f.confidence))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("inner", 11, true, Confidence::Range),
+            ("middle", 20, true, Confidence::Range),
+            ("outer", 30, false, Confidence::Range),
+        ]
+    );
+    // inlined frames point at their remapped caller.
+    assert_eq!(
+        remapped[0].inlined_into,
+        Some(StackFrame::new("com.example.Foo", "middle", 20))
+    );
+    assert_eq!(
+        remapped[1].inlined_into,
+        Some(StackFrame::new("com.example.Foo", "outer", 30))
+    );
+
+    let remapped = mapper.remap_frame_detailed(&StackFrame::new("a.a", "a", 4));
+    assert_eq!(remapped.len(), 1);
+    assert_eq!(remapped[0].inlined_into, None);
+    assert_eq!(remapped[0].confidence, Confidence::Exact);
+
+    // overloads without line numbers are alternatives, not inlined frames.
+    let remapped = mapper.remap_frame_detailed(&StackFrame::new("a.a", "b", 4));
+    assert_eq!(remapped.len(), 2);
+    assert!(remapped.iter().all(|f| f.inlined_into.is_none()));
+
+    assert!(mapper
+        .remap_frame_detailed(&StackFrame::new("a.b", "a", 1))
+        .is_empty());
+}
+
+#[test]
+fn test_remap_sentry_frame() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Foo -> a.a:
+# {"id":"sourceFile","fileName":"Foo.kt"}
+    1:3:void com.example.Bar.inner():10:12 -> a
+    1:3:void outer():30 -> a
+    void noLines() -> b"#,
+    );
+
+    let summary = |module, function, lineno| -> Vec<_> {
+        mapper
+            .remap_sentry_frame(module, function, lineno)
+            .into_iter()
+            .map(|f| (f.module, f.function, f.filename, f.lineno))
+            .collect()
+    };
+
+    assert_eq!(
+        summary("a.a", "a", Some(2)),
+        vec![
+            ("com.example.Foo", "outer", Some("Foo.kt"), Some(30)),
+            ("com.example.Bar", "inner", None, Some(11)),
+        ]
+    );
+    assert_eq!(
+        summary("a.a", "b", None),
+        vec![("com.example.Foo", "noLines", Some("Foo.kt"), None)]
+    );
+    // frames without a line number do not match line ranges, so only the
+    // class is remapped.
+    assert_eq!(
+        summary("a.a", "a", None),
+        vec![("com.example.Foo", "a", Some("Foo.kt"), None)]
+    );
+    assert_eq!(summary("b.b", "a", None), vec![("b.b", "a", None, None)]);
+}
+
+#[test]
+fn test_remap_catch_all_range() {
+    let mapping = ProguardMapping::new(
+        b"\
+com.example.Foo -> a.a:
+    0:65535:void all():100:65635 -> a
+    5:10:void specific():20:25 -> a",
+    );
+
+    for mapper in [
+        ProguardMapper::new(mapping.clone()),
+        ProguardMapper::new_eager(mapping),
+    ] {
+        let mapped: Vec<_> = mapper
+            .remap_frame(&StackFrame::new("a.a", "a", 7))
+            .collect();
+        assert_eq!(
+            mapped,
+            vec![StackFrame::new("com.example.Foo", "specific", 22)]
+        );
+
+        let mapped: Vec<_> = mapper
+            .remap_frame(&StackFrame::new("a.a", "a", 1))
+            .collect();
+        assert_eq!(mapped, vec![StackFrame::new("com.example.Foo", "all", 101)]);
+    }
+}
+
+#[test]
+fn test_remap_unknown_line() {
+    let mapping = ProguardMapping::new(
+        b"\
+com.example.Foo -> a.a:
+    1:3:void unique():10:12 -> a
+    4:6:void unique():20:22 -> a
+    1:3:void com.example.Bar.inner():10:12 -> b
+    1:3:void outer():30 -> b
+    4:6:void other():40:42 -> b",
+    );
+
+    for mapper in [
+        ProguardMapper::new(mapping.clone()),
+        ProguardMapper::new_eager(mapping),
+    ] {
+        let mut mapped = mapper.remap_frame(&StackFrame::new("a.a", "a", 0));
+        assert_eq!(
+            mapped.next_with_confidence(),
+            Some((
+                StackFrame::new("com.example.Foo", "unique", 0),
+                Confidence::CatchAll
+            ))
+        );
+        assert_eq!(mapped.next(), None);
+
+        // ambiguous methods stay obfuscated.
+        let mapped: Vec<_> = mapper
+            .remap_frame(&StackFrame::new("a.a", "b", 0))
+            .collect();
+        assert_eq!(mapped, vec![]);
+
+        let remapped = mapper
+            .remap_stacktrace("java.lang.IllegalStateException\n    at a.a.a(Unknown Source)")
+            .unwrap();
+        assert_eq!(
+            remapped,
+            "java.lang.IllegalStateException\n    at com.example.Foo.unique(Unknown Source:0)\n"
+        );
+    }
+}
+
+#[test]
+fn test_disambiguator() {
+    let mapping = "\
+com.example.Foo -> a.a:
+    1:2:void com.example.Bar.inner():10:11 -> a
+    1:2:void first(int):20 -> a
+    void second(java.lang.String) -> a";
+
+    let mapper = ProguardMapper::from(mapping).with_disambiguator(|frame, candidates| {
+        assert_eq!((frame.class(), frame.method()), ("a.a", "a"));
+        let names: Vec<_> = candidates.iter().map(|method| method.original).collect();
+        assert_eq!(names, ["first", "second"]);
+        candidates
+            .iter()
+            .position(|method| method.arguments == "int")
+    });
+
+    // the inlined frame is kept along with its outermost method.
+    let remapped: Vec<_> = mapper
+        .remap_frame(&StackFrame::new("a.a", "a", 1))
+        .collect();
+    assert_eq!(
+        remapped,
+        vec![
+            StackFrame::new("com.example.Bar", "inner", 10),
+            StackFrame::new("com.example.Foo", "first", 20),
+        ]
+    );
+
+    // frames which are not ambiguous do not call the disambiguator.
+    let remapped: Vec<_> = mapper
+        .remap_frame(&StackFrame::new("a.a", "a", 3))
+        .collect();
+    assert_eq!(
+        remapped,
+        vec![StackFrame::new("com.example.Foo", "second", 0)]
+    );
+
+    let mapper = ProguardMapper::from(mapping).with_disambiguator(|_, _| None);
+    assert_eq!(
+        mapper.remap_frame(&StackFrame::new("a.a", "a", 1)).count(),
+        3
+    );
+}
+
+#[test]
+fn test_remap_field_path() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Order -> a.a:
+    com.example.Customer customer -> a
+    java.util.List items -> b
+    int total -> c
+com.example.Customer -> a.b:
+    java.lang.String name -> a
+    com.example.Customer referrer -> b"#,
+    );
+
+    assert_eq!(mapper.remap_field_path("a.a", "c"), "total");
+    assert_eq!(
+        mapper.remap_field_path("a.a", "a.b.b.a"),
+        "customer.referrer.referrer.name"
+    );
+    assert_eq!(mapper.remap_field_path("a.a", "$.a.a"), "$.customer.name");
+    // the element type of collections is unknown.
+    assert_eq!(mapper.remap_field_path("a.a", "$.b[1].a"), "$.items[1].a");
+    assert_eq!(mapper.remap_field_path("a.a", "a.x.a"), "customer.x.a");
+    assert_eq!(mapper.remap_field_path("x.x", "a.b"), "a.b");
+}
+
+#[test]
+fn test_class_merging() {
+    // `com.example.Merged` was merged into `com.example.Host` by R8.
+    let mapping = "\
+com.example.Host -> a:
+    int com.example.Merged.count -> a
+    1:1:void com.example.Merged.foo():10:10 -> a
+    2:2:java.lang.Object java.util.List.get(int):0:0 -> b
+    2:2:void com.example.Merged.bar():40 -> b
+    3:3:void own():20:20 -> c
+    void com.example.Merged.noLines() -> d";
+    let mapper = ProguardMapper::from(mapping);
+
+    let remap = |method, line| -> Vec<_> {
+        mapper
+            .remap_frame(&StackFrame::new("a", method, line))
+            .map(|frame| (frame.class().to_owned(), frame.method().to_owned()))
+            .collect()
+    };
+    let frame = |class: &str, method: &str| (class.to_owned(), method.to_owned());
+    assert_eq!(remap("a", 1), vec![frame("com.example.Merged", "foo")]);
+    assert_eq!(
+        remap("b", 2),
+        vec![
+            frame("java.util.List", "get"),
+            frame("com.example.Merged", "bar")
+        ]
+    );
+    assert_eq!(remap("c", 3), vec![frame("com.example.Host", "own")]);
+    assert_eq!(remap("d", 0), vec![frame("com.example.Merged", "noLines")]);
+    assert_eq!(mapper.remap_field_path("a", "a"), "count");
+
+    // frames of the merged class are obfuscated using the host class.
+    let obfuscator = ProguardObfuscator::from(mapping);
+    let obfuscate = |class, method, line| {
+        obfuscator.obfuscate_frame(&StackFrame::with_file(class, method, line, "Merged.java"))
+    };
+    assert_eq!(
+        obfuscate("com.example.Merged", "foo", 10),
+        StackFrame::with_file("a", "a", 1, "SourceFile")
+    );
+    assert_eq!(
+        obfuscate("com.example.Merged", "bar", 40),
+        StackFrame::with_file("a", "b", 2, "SourceFile")
+    );
+    assert_eq!(
+        obfuscate("com.example.Merged", "noLines", 5),
+        StackFrame::with_file("a", "d", 5, "SourceFile")
+    );
+    // inlined library classes are not merged.
+    assert_eq!(
+        obfuscate("java.util.List", "get", 0),
+        StackFrame::with_file("java.util.List", "get", 0, "Merged.java")
+    );
+
+    let obfuscated = obfuscator
+        .obfuscate_stacktrace("java.lang.Exception\n    at com.example.Merged.foo(Merged.java:10)")
+        .unwrap();
+    assert_eq!(
+        obfuscated,
+        "java.lang.Exception\n    at a.a(SourceFile:1)\n"
+    );
+}
+
+#[test]
+fn test_coverage() {
+    let mapper = ProguardMapper::from(
+        r#"com.example.Foo -> a:
+    1:2:void bar():10:11 -> a
+com.example.Baz -> b:"#,
+    );
+    let trace = StackTrace::try_parse(
+        b"\
+a: boom
+    at a.a(SourceFile:2)
+    at a.a(SourceFile:5)
+    at android.view.View.performClick(View.java:7393)
+Caused by: b: inner
+    at b.c(SourceFile:1)
+    at android.view.View.performClick(View.java:7393)",
+    )
+    .unwrap();
+
+    let coverage = mapper.coverage(&trace);
+    assert!(!coverage.is_complete());
+    assert_eq!(
+        coverage.resolved,
+        vec![StackFrame::with_file("a", "a", 2, "SourceFile")]
+    );
+    assert_eq!(coverage.unresolved.len(), 4);
+    assert_eq!(coverage.missing_classes, vec!["android.view.View"]);
+}
+
+/// Splits a test vector into its mapping, input and expected output.
+fn read_vector(vector: &str) -> (String, String, String) {
+    let mut sections = vec![String::new(); 3];
+    let mut current = 0;
+    for line in vector.lines() {
+        match line {
+            "--- mapping" => current = 0,
+            "--- input" => current = 1,
+            "--- expected" => current = 2,
+            _ => {
+                sections[current].push_str(line);
+                sections[current].push('\n');
+            }
+        }
+    }
+    let expected = sections.pop().unwrap();
+    let input = sections.pop().unwrap();
+    let mapping = sections.pop().unwrap();
+    (mapping, input, expected)
+}
+
+#[test]
+fn test_r8_compat() {
+    let vectors = [
+        include_str!("res/r8-retrace/inline.txt"),
+        include_str!("res/r8-retrace/ambiguous.txt"),
+        include_str!("res/r8-retrace/synthesized.txt"),
+    ];
+    let options = RetraceOptions {
+        r8_compat: true,
+        ..Default::default()
+    };
+
+    for vector in vectors {
+        let (mapping, input, expected) = read_vector(vector);
+        for mapper in [
+            ProguardMapper::new(ProguardMapping::new(mapping.as_bytes())),
+            ProguardMapper::new_eager(ProguardMapping::new(mapping.as_bytes())),
+        ] {
+            let remapped = mapper
+                .remap_stacktrace_with_options(&input, &options)
+                .unwrap();
+            assert_eq!(remapped, expected);
+        }
+    }
+}
+
+/// A frame as it could be generated from a protobuf schema.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct EventFrame {
+    id: u64,
+    module: String,
+    function: String,
+    lineno: Option<u32>,
+}
+
+impl Retraceable for EventFrame {
+    fn class(&self) -> &str {
+        &self.module
+    }
+    fn method(&self) -> &str {
+        &self.function
+    }
+    fn line(&self) -> Option<usize> {
+        self.lineno.map(|line| line as usize)
+    }
+    fn set_class(&mut self, class: &str) {
+        self.module = class.to_owned();
+    }
+    fn set_method(&mut self, method: &str) {
+        self.function = method.to_owned();
+    }
+    fn set_line(&mut self, line: Option<usize>) {
+        self.lineno = line.map(|line| line as u32);
+    }
+}
+
+#[test]
+fn test_retraceable() {
+    let mapper = ProguardMapper::from(
+        r#"some.Class -> a:
+    4:4:void inlined():20:20 -> a
+    4:4:void caller():10 -> a
+    void unknown() -> b"#,
+    );
+    let frame = |function: &str, lineno| EventFrame {
+        id: 7,
+        module: "a".into(),
+        function: function.into(),
+        lineno,
+    };
+
+    let mut inlined = frame("a", Some(4));
+    let callers = mapper.retrace(&mut inlined).unwrap();
+    assert_eq!(
+        inlined,
+        EventFrame {
+            id: 7,
+            module: "some.Class".into(),
+            function: "inlined".into(),
+            lineno: Some(20),
+        }
+    );
+    assert_eq!(
+        callers,
+        vec![EventFrame {
+            id: 7,
+            module: "some.Class".into(),
+            function: "caller".into(),
+            lineno: Some(10),
+        }]
+    );
+
+    let mut line_less = frame("b", None);
+    assert_eq!(mapper.retrace(&mut line_less), Some(vec![]));
+    assert_eq!(
+        (line_less.function.as_str(), line_less.lineno),
+        ("unknown", None)
+    );
+
+    let mut missing = frame("c", Some(1));
+    assert_eq!(mapper.retrace(&mut missing), None);
+    assert_eq!(missing, frame("c", Some(1)));
+}